@@ -0,0 +1,96 @@
+use crate::{
+    cmd::{get_wallet_secret, load_wallet, Opts},
+    result::Result,
+    signer::{agent::LockedKeypair, remote, Signer},
+};
+use std::{
+    env, fs,
+    path::PathBuf,
+    process,
+    time::{Duration, Instant},
+};
+use structopt::StructOpt;
+
+#[derive(Debug, StructOpt)]
+/// Run an agent that keeps the decrypted key of a wallet in memory and
+/// signs for other commands. The wallet is decrypted once, and commands
+/// that sign use the agent instead of asking for the password when
+/// HELIUM_WALLET_AGENT_SOCK is set to the agent's socket. The agent only
+/// signs transactions, not raw data, and runs until its time to live
+/// expires or it is stopped.
+pub struct Cmd {
+    /// Path of the Unix socket to listen on. Defaults to a socket in a
+    /// new temporary directory that only the current user can access
+    #[structopt(long)]
+    socket: Option<PathBuf>,
+
+    /// Number of seconds to keep the key. The key is kept until the
+    /// agent is stopped if 0
+    #[structopt(long, default_value = "3600")]
+    ttl: u64,
+
+    /// Replace an existing socket at the given path
+    #[structopt(long)]
+    force: bool,
+}
+
+impl Cmd {
+    pub fn run(&self, opts: Opts) -> Result {
+        let wallet = load_wallet(opts.files)?;
+        let secret = get_wallet_secret(&wallet, &opts.keyfile)?;
+        let keypair = LockedKeypair::new(&secret.decrypt(&wallet)?)?;
+
+        let (socket, dir) = match &self.socket {
+            Some(socket) => (socket.clone(), None),
+            None => {
+                let dir = create_private_dir()?;
+                (dir.join("agent.sock"), Some(dir))
+            }
+        };
+        let expires = match self.ttl {
+            0 => None,
+            ttl => Some(Instant::now() + Duration::from_secs(ttl)),
+        };
+
+        println!("export HELIUM_WALLET_AGENT_SOCK={}", socket.display());
+        let term = console::Term::stderr();
+        match expires {
+            Some(_) => term.write_line(&format!(
+                "Agent for {} expires in {} seconds",
+                keypair.pubkey_bin(),
+                self.ttl
+            ))?,
+            None => term.write_line(&format!("Agent for {}", keypair.pubkey_bin()))?,
+        }
+        let result = remote::serve(
+            &socket,
+            self.force,
+            expires,
+            &keypair,
+            |txn_type, summary| {
+                term.write_line(&format!("{}: {}", txn_type, summary))?;
+                if txn_type == remote::RAW_TXN_TYPE {
+                    return Err("The agent does not sign raw data".into());
+                }
+                Ok(true)
+            },
+        );
+        if let Some(dir) = dir {
+            let _ = fs::remove_dir_all(dir);
+        }
+        result
+    }
+}
+
+/// Creates a temporary directory that only the current user can access
+fn create_private_dir() -> Result<PathBuf> {
+    let dir = env::temp_dir().join(format!("helium-wallet-{}", process::id()));
+    let mut builder = fs::DirBuilder::new();
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::DirBuilderExt;
+        builder.mode(0o700);
+    }
+    builder.create(&dir)?;
+    Ok(dir)
+}
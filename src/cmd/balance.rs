@@ -18,7 +18,7 @@ pub struct Cmd {
 
 impl Cmd {
     pub fn run(&self, opts: Opts) -> Result {
-        let client = Client::new_with_base_url(api_url());
+        let client = Client::new_with_base_url(api_url(opts.network.unwrap_or_default())?);
         let mut results = Vec::with_capacity(self.addresses.len());
         for address in collect_addresses(opts.files, self.addresses.clone())? {
             results.push((address.to_string(), client.get_account(&address)));
@@ -1,20 +1,43 @@
 use crate::{
-    cmd::{get_file_extension, get_password, get_seed_words, verify, Opts},
-    format::{self, Format},
-    keypair::{Keypair, Seed},
-    mnemonic::mnemonic_to_entropy,
-    pwhash::PWHash,
+    cmd::{
+        gen_seed_words, get_custodian_keys, get_new_wallet_secret, get_pin, get_seed_passphrase,
+        get_seed_words, open_output_file, print_json, print_table, pwhash::PWHashOpts, verify,
+        write_shards, Opts, OutputFormat, WalletSecret,
+    },
+    format::Format,
+    keypair::{KeyType, Keypair, Network, PubKeyBin, PublicKey},
+    mnemonic::{mnemonic_to_seed, Language, SeedType},
     result::Result,
-    wallet::Wallet,
+    secret::Secret,
+    signer::{pkcs11, SignerConfig},
+    traits::B58,
+    vanity::{self, Pattern},
+    wallet::{Metadata, Wallet},
 };
-use std::{fs, io, path::PathBuf};
-use structopt::StructOpt;
+use prettytable::{format as table_format, Table};
+use serde_derive::Deserialize;
+use serde_json::json;
+use std::{fs, io::Write, path::PathBuf, str, time::Duration};
+use structopt::{clap::arg_enum, StructOpt};
+
+arg_enum! {
+    #[derive(Debug, Clone, Copy)]
+    pub enum ImportFormat {
+        Hex,
+        B58,
+        SecretKey,
+        Json,
+    }
+}
 
 #[derive(Debug, StructOpt)]
 /// Create a new wallet
 pub enum Cmd {
     Basic(Basic),
     Sharded(Sharded),
+    Slotted(Slotted),
+    Vanity(Vanity),
+    Pkcs11(Pkcs11),
 }
 
 #[derive(Debug, StructOpt)]
@@ -29,8 +52,18 @@ pub struct Basic {
     force: bool,
 
     #[structopt(long)]
-    /// Use space separated seed words to create the wallet
-    seed: bool,
+    /// A label to store in the wallet
+    label: Option<String>,
+
+    #[structopt(long)]
+    /// Keyfile the wallet needs besides the password to decrypt it
+    keyfile: Option<PathBuf>,
+
+    #[structopt(flatten)]
+    pwhash: PWHashOpts,
+
+    #[structopt(flatten)]
+    seed: SeedOpts,
 }
 
 #[derive(Debug, StructOpt)]
@@ -52,9 +85,223 @@ pub struct Sharded {
     /// Number of shards required to recover the key
     recovery_threshold: u8,
 
+    #[structopt(long = "custodian", number_of_values(1))]
+    /// Address of a custodian to seal a shard to. Give one custodian
+    /// per shard, in shard order. A sealed shard can only be used with
+    /// the custodian's wallet
+    custodians: Vec<PubKeyBin>,
+
+    #[structopt(long)]
+    /// A label to store in the wallet
+    label: Option<String>,
+
+    #[structopt(long)]
+    /// Keyfile the wallet needs besides the password to decrypt it
+    keyfile: Option<PathBuf>,
+
+    #[structopt(flatten)]
+    pwhash: PWHashOpts,
+
+    #[structopt(flatten)]
+    seed: SeedOpts,
+}
+
+#[derive(Debug, StructOpt)]
+/// Create a new wallet with unlock slots. The wallet starts with one
+/// slot for the password, and more slots with other passwords or
+/// keyfiles are added with the slot command.
+pub struct Slotted {
+    #[structopt(short, long, default_value = "wallet.key")]
+    /// Output file to store the key in
+    output: PathBuf,
+
+    #[structopt(long)]
+    /// Overwrite an existing file
+    force: bool,
+
+    #[structopt(long, default_value = "password")]
+    /// Name of the slot for the password
+    slot: String,
+
+    #[structopt(long)]
+    /// A label to store in the wallet
+    label: Option<String>,
+
+    #[structopt(flatten)]
+    pwhash: PWHashOpts,
+
+    #[structopt(flatten)]
+    seed: SeedOpts,
+}
+
+#[derive(Debug, StructOpt)]
+/// Create a new wallet with an address that matches a pattern. Keys
+/// are generated on all cores until one is found with an address that
+/// matches all of the given prefix, suffix and regular expression.
+/// Every additional character in the pattern makes the search about 58
+/// times longer.
+pub struct Vanity {
+    #[structopt(short, long, default_value = "wallet.key")]
+    /// Output file to store the key in
+    output: PathBuf,
+
+    #[structopt(long)]
+    /// Overwrite an existing file
+    force: bool,
+
+    #[structopt(long)]
+    /// Characters the address should start with. Every address starts
+    /// with "1" followed by a character fixed by the network, the
+    /// prefix is matched against the characters after those
+    prefix: Option<String>,
+
+    #[structopt(long)]
+    /// Characters the address should end with
+    suffix: Option<String>,
+
+    #[structopt(long)]
+    /// A regular expression the full address should match
+    regex: Option<String>,
+
+    #[structopt(short = "i", long)]
+    /// Match the prefix, suffix and regular expression case
+    /// insensitively
+    ignore_case: bool,
+
+    #[structopt(long)]
+    /// Number of threads to search with. Defaults to the number of
+    /// cores
+    threads: Option<usize>,
+
+    #[structopt(long)]
+    /// Create a sharded wallet instead of a basic one
+    sharded: bool,
+
+    #[structopt(short = "n", long = "shards", default_value = "5")]
+    /// Number of shards to break the key into for a sharded wallet
+    key_share_count: u8,
+
+    #[structopt(short = "k", long = "required-shards", default_value = "3")]
+    /// Number of shards required to recover the key for a sharded
+    /// wallet
+    recovery_threshold: u8,
+
+    #[structopt(long)]
+    /// A label to store in the wallet
+    label: Option<String>,
+
+    #[structopt(long)]
+    /// Keyfile the wallet needs besides the password to decrypt it
+    keyfile: Option<PathBuf>,
+
+    #[structopt(flatten)]
+    pwhash: PWHashOpts,
+}
+
+#[derive(Debug, StructOpt)]
+/// Create a signer reference file for an ed25519 key on a PKCS#11
+/// token. The key is looked up on the token and its address is
+/// recorded in the reference file, which can then be used in place of
+/// a wallet file to sign with the key on the token. The token PIN is
+/// read from HELIUM_WALLET_PKCS11_PIN or prompted for.
+pub struct Pkcs11 {
+    #[structopt(short, long, default_value = "wallet.key")]
+    /// Output file to store the reference in
+    output: PathBuf,
+
+    #[structopt(long)]
+    /// Overwrite an existing file
+    force: bool,
+
+    #[structopt(long)]
+    /// Path of the PKCS#11 module, for example libsofthsm2.so
+    module: PathBuf,
+
+    #[structopt(long)]
+    /// Id of the slot holding the token
+    slot: Option<u64>,
+
+    #[structopt(long)]
+    /// Label of the token holding the key
+    token_label: Option<String>,
+
+    #[structopt(long)]
+    /// Label of the key on the token
+    key_label: Option<String>,
+
+    #[structopt(long)]
+    /// Hex encoded id of the key on the token
+    key_id: Option<String>,
+}
+
+#[derive(Debug, StructOpt)]
+// Options for creating a wallet from seed words or an existing key. Not
+// a doc comment, since structopt would show it as the about text of the
+// commands that flatten these options in.
+pub struct SeedOpts {
     #[structopt(long)]
     /// Use space separated seed words to create the wallet
     seed: bool,
+
+    #[structopt(long, conflicts_with = "seed")]
+    /// Generate new seed words for the wallet and display them for
+    /// backup
+    generate_seed: bool,
+
+    #[structopt(long,
+                possible_values = &["mobile", "bip39"],
+                case_insensitive = true,
+                default_value = "mobile")]
+    /// The type of seed words to use. "mobile" seed words are
+    /// compatible with the mobile wallet, "bip39" seed words are
+    /// standard BIP39 seed words of 12 to 24 words
+    seed_type: SeedType,
+
+    #[structopt(long,
+                requires = "generate-seed",
+                possible_values = &["12", "15", "18", "21", "24"])]
+    /// The number of BIP39 seed words to generate. Defaults to 24.
+    /// Mobile seed words are always 12 words
+    seed_words: Option<usize>,
+
+    #[structopt(long,
+                possible_values = &Language::NAMES,
+                case_insensitive = true)]
+    /// The language of BIP39 seed words. Seed words that are entered
+    /// are detected when no language is given, generated seed words
+    /// default to english
+    language: Option<Language>,
+
+    #[structopt(long)]
+    /// Prompt for a passphrase to use with BIP39 seed words
+    passphrase: bool,
+
+    #[structopt(long,
+                possible_values = &["hex", "b58", "secretkey", "json"],
+                case_insensitive = true,
+                conflicts_with_all = &["seed", "generate-seed"])]
+    /// Import an existing key instead of generating one. "hex" and
+    /// "b58" import an ed25519 seed, "secretkey" a hex or base58
+    /// encoded 64 byte libsodium secret key, and "json" a JSON export
+    /// of the JS or mobile wallet
+    import: Option<ImportFormat>,
+
+    #[structopt(long, requires = "import")]
+    /// File to read the imported key from. The key is prompted for if
+    /// not given
+    import_file: Option<PathBuf>,
+
+    #[structopt(long, requires = "import")]
+    /// The address the imported key is expected to have
+    address: Option<PubKeyBin>,
+
+    #[structopt(long,
+                possible_values = &["ed25519", "ecc_compact"],
+                case_insensitive = true,
+                default_value = "ed25519")]
+    /// The type of key to generate. Seed words and imports are only
+    /// supported for ed25519 keys
+    key_type: KeyType,
 }
 
 impl Cmd {
@@ -62,73 +309,358 @@ impl Cmd {
         match self {
             Cmd::Basic(cmd) => cmd.run(opts),
             Cmd::Sharded(cmd) => cmd.run(opts),
+            Cmd::Slotted(cmd) => cmd.run(opts),
+            Cmd::Vanity(cmd) => cmd.run(opts),
+            Cmd::Pkcs11(cmd) => cmd.run(opts),
         }
     }
 }
 
 impl Basic {
     pub fn run(&self, opts: Opts) -> Result {
-        let seed_words = if self.seed {
-            Some(get_seed_words()?)
+        let pwhash = self.pwhash.pwhash()?;
+        let keypair = self.seed.gen_keypair(opts.network.unwrap_or_default())?;
+        let secret = get_new_wallet_secret(&self.keyfile)?;
+        let wallet = write_wallet(
+            &keypair,
+            &secret,
+            &self.output,
+            self.force,
+            Format::basic(pwhash),
+            self.label.clone(),
+        )?;
+        verify::print_result(&wallet, true, opts.format)
+    }
+}
+
+impl Slotted {
+    pub fn run(&self, opts: Opts) -> Result {
+        let pwhash = self.pwhash.pwhash()?;
+        let keypair = self.seed.gen_keypair(opts.network.unwrap_or_default())?;
+        let secret = get_new_wallet_secret(&None)?;
+        let wallet = write_wallet(
+            &keypair,
+            &secret,
+            &self.output,
+            self.force,
+            Format::slotted(&self.slot, pwhash),
+            self.label.clone(),
+        )?;
+        verify::print_result(&wallet, true, opts.format)
+    }
+}
+
+impl Sharded {
+    pub fn run(&self, opts: Opts) -> Result {
+        let custodians = get_custodian_keys(&self.custodians, self.key_share_count)?;
+        let pwhash = self.pwhash.pwhash()?;
+        let keypair = self.seed.gen_keypair(opts.network.unwrap_or_default())?;
+        let secret = get_new_wallet_secret(&self.keyfile)?;
+        let wallet = write_sharded(
+            &keypair,
+            &secret,
+            &self.output,
+            self.force,
+            Format::sharded(self.key_share_count, self.recovery_threshold, pwhash),
+            self.label.clone(),
+            &custodians,
+        )?;
+        verify::print_result(&wallet, true, opts.format)
+    }
+}
+
+impl Vanity {
+    pub fn run(&self, opts: Opts) -> Result {
+        let pattern = Pattern::new(
+            self.prefix.as_deref(),
+            self.suffix.as_deref(),
+            self.regex.as_deref(),
+            self.ignore_case,
+        )?;
+        let pwhash = self.pwhash.pwhash()?;
+        let difficulty = pattern.difficulty();
+        let threads = self.threads.unwrap_or_else(num_cpus::get);
+        let secret = get_new_wallet_secret(&self.keyfile)?;
+
+        let term = console::Term::stderr();
+        let network = opts.network.unwrap_or_default();
+        let keypair = vanity::search(pattern, network, threads, |attempts, elapsed| {
+            if !term.is_term() {
+                return;
+            }
+            let rate = attempts as f64 / elapsed.as_secs_f64().max(1.0);
+            let mut line = format!(
+                "Searched {} keys ({:.0}/s) in {}",
+                attempts,
+                rate,
+                format_duration(elapsed)
+            );
+            if let Some(difficulty) = difficulty {
+                let expected = Duration::from_secs_f64(difficulty / rate.max(1.0));
+                line.push_str(&format!(
+                    ", expected {} keys in about {}",
+                    difficulty.round(),
+                    format_duration(expected)
+                ));
+            }
+            let _ = term.clear_line();
+            let _ = term.write_str(&line);
+        })?;
+        if term.is_term() {
+            term.clear_line()?;
+        }
+
+        let wallet = if self.sharded {
+            write_sharded(
+                &keypair,
+                &secret,
+                &self.output,
+                self.force,
+                Format::sharded(self.key_share_count, self.recovery_threshold, pwhash),
+                self.label.clone(),
+                &[],
+            )?
         } else {
-            None
-        };
-        let password = get_password(true)?;
-        let keypair = gen_keypair(seed_words)?;
-        let format = format::Basic {
-            pwhash: PWHash::argon2id13_default(),
+            write_wallet(
+                &keypair,
+                &secret,
+                &self.output,
+                self.force,
+                Format::basic(pwhash),
+                self.label.clone(),
+            )?
         };
-        let wallet = Wallet::encrypt(&keypair, password.as_bytes(), Format::Basic(format))?;
-        let mut writer = open_output_file(&self.output, !self.force)?;
-        wallet.write(&mut writer)?;
         verify::print_result(&wallet, true, opts.format)
     }
 }
 
-impl Sharded {
+impl Pkcs11 {
     pub fn run(&self, opts: Opts) -> Result {
+        let mut config = pkcs11::Config {
+            module: self.module.clone(),
+            slot: self.slot,
+            token_label: self.token_label.clone(),
+            key_label: self.key_label.clone(),
+            key_id: self.key_id.clone(),
+            network: opts.network.unwrap_or_default(),
+            address: None,
+        };
+        let signer = pkcs11::open(&config, &get_pin()?)?;
+        let address = signer.pubkey_bin().to_b58()?;
+        config.address = Some(address.clone());
+
+        let reference = serde_json::to_string_pretty(&SignerConfig::Pkcs11(config))?;
+        let mut writer = open_output_file(&self.output, !self.force)?;
+        writeln!(writer, "{}", reference)?;
+        print_reference(&address, "pkcs11", &self.output, opts.format)
+    }
+}
+
+fn print_reference(address: &str, signer: &str, output: &PathBuf, format: OutputFormat) -> Result {
+    match format {
+        OutputFormat::Table => {
+            let mut table = Table::new();
+            table.set_format(*table_format::consts::FORMAT_NO_LINESEP_WITH_TITLE);
+            table.set_titles(row!["Address", "Signer", "File"]);
+            table.add_row(row![address, signer, output.display()]);
+            print_table(&table)
+        }
+        OutputFormat::Json => {
+            let table = json!({
+                "address": address,
+                "signer": signer,
+                "file": output,
+            });
+            print_json(&table)
+        }
+    }
+}
+
+fn write_wallet(
+    keypair: &Keypair,
+    secret: &WalletSecret,
+    output: &PathBuf,
+    force: bool,
+    format: Format,
+    label: Option<String>,
+) -> Result<Wallet> {
+    let wallet = secret.encrypt(keypair, format, Metadata::new(keypair.network(), label))?;
+    let mut writer = open_output_file(output, !force)?;
+    wallet.write(&mut writer)?;
+    Ok(wallet)
+}
+
+fn write_sharded(
+    keypair: &Keypair,
+    secret: &WalletSecret,
+    output: &PathBuf,
+    force: bool,
+    format: Format,
+    label: Option<String>,
+    custodians: &[Option<PublicKey>],
+) -> Result<Wallet> {
+    let wallet = secret.encrypt(keypair, format, Metadata::new(keypair.network(), label))?;
+    write_shards(&wallet, output, force, custodians)?;
+    Ok(wallet)
+}
+
+/// Formats a duration as hours, minutes and seconds
+fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    match (secs / 3600, secs / 60 % 60, secs % 60) {
+        (0, 0, s) => format!("{}s", s),
+        (0, m, s) => format!("{}m {}s", m, s),
+        (h, m, s) => format!("{}h {}m {}s", h, m, s),
+    }
+}
+
+impl SeedOpts {
+    fn gen_keypair(&self, network: Network) -> Result<Keypair> {
+        if self.key_type != KeyType::Ed25519 {
+            if self.seed || self.generate_seed || self.import.is_some() {
+                return Err(format!(
+                    "Seed words and imports are not supported for {} keys",
+                    self.key_type
+                )
+                .into());
+            }
+            return Ok(Keypair::gen_keypair_with_type(self.key_type)?.with_network(network));
+        }
+        if let Some(format) = self.import {
+            let keypair = self.import_keypair(format, network)?;
+            if let Some(address) = &self.address {
+                if !keypair.pubkey_bin().same_key(address)? {
+                    return Err(format!(
+                        "Imported key has address {}, expected {}",
+                        keypair.pubkey_bin(),
+                        address
+                    )
+                    .into());
+                }
+                check_address_network(address, network)?;
+            }
+            return Ok(keypair);
+        }
         let seed_words = if self.seed {
-            Some(get_seed_words()?)
+            Some(get_seed_words(self.seed_type, self.language)?)
+        } else if self.generate_seed {
+            let language = self.language.unwrap_or(Language::English);
+            Some(gen_seed_words(self.seed_type, language, self.seed_words)?)
         } else {
             None
         };
-        let password = get_password(true)?;
-
-        let keypair = gen_keypair(seed_words)?;
-        let format = format::Sharded {
-            key_share_count: self.key_share_count,
-            recovery_threshold: self.recovery_threshold,
-            pwhash: PWHash::argon2id13_default(),
-            key_shares: vec![],
-        };
-        let wallet = Wallet::encrypt(&keypair, password.as_bytes(), Format::Sharded(format))?;
-
-        let extension = get_file_extension(&self.output);
-        for (i, shard) in wallet.shards()?.iter().enumerate() {
-            let mut filename = self.output.clone();
-            let share_extension = format!("{}.{}", extension, (i + 1).to_string());
-            filename.set_extension(share_extension);
-            let mut writer = open_output_file(&filename, !self.force)?;
-            shard.write(&mut writer)?;
+        match seed_words {
+            Some(words) => {
+                let passphrase = if self.passphrase {
+                    get_seed_passphrase()?
+                } else {
+                    Secret::new(vec![])
+                };
+                let seed = mnemonic_to_seed(
+                    words,
+                    self.seed_type,
+                    self.language,
+                    str::from_utf8(&passphrase)?,
+                )?;
+                Ok(Keypair::gen_keypair_from_seed(&seed)?.with_network(network))
+            }
+            None => Ok(Keypair::gen_keypair()?.with_network(network)),
         }
-        verify::print_result(&wallet, true, opts.format)
+    }
+
+    fn import_keypair(&self, format: ImportFormat, network: Network) -> Result<Keypair> {
+        let data = match &self.import_file {
+            Some(path) => Secret::new(fs::read_to_string(path)?),
+            None => Secret::new(
+                dialoguer::Password::new()
+                    .with_prompt("Key to import")
+                    .interact()?,
+            ),
+        };
+        let data = data.trim();
+        let keypair = match format {
+            ImportFormat::Hex => keypair_from_seed(&decode_hex(data)?)?,
+            ImportFormat::B58 => keypair_from_seed(&decode_b58(data)?)?,
+            ImportFormat::SecretKey => Keypair::from_secret_key(&decode_hex_or_b58(data)?)?,
+            ImportFormat::Json => {
+                let json: ImportJson = serde_json::from_str(data)?;
+                let keypair = if let Some(seed) = &json.seed {
+                    keypair_from_seed(&decode_b58(seed)?)?
+                } else if let Some(sk) = &json.sk {
+                    Keypair::from_secret_key(&decode_hex_or_b58(sk)?)?
+                } else if let Some(words) = &json.words {
+                    let words = words.iter().map(|word| word.to_string()).collect();
+                    let seed = mnemonic_to_seed(words, SeedType::Mobile, None, "")?;
+                    Keypair::gen_keypair_from_seed(&seed)?
+                } else {
+                    return Err("No seed, sk or words found in JSON".into());
+                };
+                if let Some(address) = &json.address {
+                    let address = PubKeyBin::from_b58(address)?;
+                    if !keypair.pubkey_bin().same_key(&address)? {
+                        return Err("Imported key does not match the address in the JSON".into());
+                    }
+                    check_address_network(&address, network)?;
+                }
+                keypair
+            }
+        };
+        Ok(keypair.with_network(network))
     }
 }
 
-fn gen_keypair(seed_words: Option<Vec<String>>) -> Result<Keypair> {
-    match seed_words {
-        Some(words) => {
-            let entropy = mnemonic_to_entropy(words)?;
-            Ok(Keypair::gen_keypair_from_seed(&Seed(entropy)))
-        }
-        None => Ok(Keypair::gen_keypair()),
+/// The JSON of an imported key, as exported by the mobile wallet. The
+/// secret fields are deserialized into secrets.
+#[derive(Deserialize)]
+struct ImportJson {
+    address: Option<String>,
+    seed: Option<Secret<String>>,
+    sk: Option<Secret<String>>,
+    words: Option<Vec<Secret<String>>>,
+}
+
+/// Checks that the address of an imported key is for the network the
+/// wallet is created for
+fn check_address_network(address: &PubKeyBin, network: Network) -> Result {
+    let address_network = address.network()?;
+    if address_network != network {
+        return Err(format!(
+            "Address {} is for {}, not {}. Use --network {} to create a {} wallet",
+            address, address_network, network, address_network, address_network
+        )
+        .into());
     }
+    Ok(())
 }
 
-fn open_output_file(filename: &PathBuf, create: bool) -> io::Result<fs::File> {
-    fs::OpenOptions::new()
-        .write(true)
-        .create(true)
-        .create_new(create)
-        .open(filename)
+fn keypair_from_seed(data: &[u8]) -> Result<Keypair> {
+    if data.len() != 32 {
+        return Err("Invalid seed length, expected 32 bytes".into());
+    }
+    let mut seed = Secret::<[u8; 32]>::zeroed();
+    seed.copy_from_slice(data);
+    Keypair::gen_keypair_from_seed(&seed)
+}
+
+/// Decodes hex encoded secret data into a secret
+fn decode_hex(data: &str) -> Result<Secret<Vec<u8>>> {
+    let mut bytes = Secret::new(vec![0; data.len() / 2]);
+    hex::decode_to_slice(data, &mut bytes[..])?;
+    Ok(bytes)
+}
+
+/// Decodes base58 encoded secret data into a secret
+fn decode_b58(data: &str) -> Result<Secret<Vec<u8>>> {
+    let mut buffer = Secret::new(vec![0; data.len()]);
+    let len = bs58::decode(data).into(&mut buffer[..])?;
+    Ok(Secret::new(buffer[..len].to_vec()))
+}
+
+/// Decodes a 64 byte secret key that is either hex or base58 encoded
+fn decode_hex_or_b58(data: &str) -> Result<Secret<Vec<u8>>> {
+    if data.len() == 128 {
+        decode_hex(data)
+    } else {
+        decode_b58(data)
+    }
 }
@@ -0,0 +1,125 @@
+use crate::{
+    cmd::{get_wallet_secret, load_wallet, open_secret_file, print_json, read_input, Opts},
+    encrypt,
+    keypair::{PubKeyBin, PublicKey},
+    result::Result,
+    traits::{B58, B64},
+};
+use serde_json::json;
+use std::{convert::TryFrom, fs, io::Write, path::PathBuf};
+use structopt::StructOpt;
+
+#[derive(Debug, StructOpt)]
+/// Encrypt data to a wallet address so only the owner of that wallet
+/// can decrypt it. The data is given as an argument, read from a file
+/// with --input, or read from stdin.
+pub struct EncryptCmd {
+    /// The data to encrypt
+    #[structopt(name = "DATA")]
+    data: Option<String>,
+
+    /// File to read the data from
+    #[structopt(long, conflicts_with = "DATA")]
+    input: Option<PathBuf>,
+
+    /// Address to encrypt to
+    #[structopt(long)]
+    to: PubKeyBin,
+
+    /// Prove to the recipient that the data is from the wallet given
+    /// with -f. This needs the wallet password
+    #[structopt(long)]
+    authenticate: bool,
+
+    /// File to write the encrypted data to. The encrypted data is
+    /// printed base64 encoded if no file is given
+    #[structopt(short = "o", long)]
+    output: Option<PathBuf>,
+}
+
+#[derive(Debug, StructOpt)]
+/// Decrypt data encrypted to the wallet with encrypt. The encrypted
+/// data is given base64 encoded as an argument or on stdin, or read
+/// from a file with --input.
+pub struct DecryptCmd {
+    /// Base64 encoded encrypted data
+    #[structopt(name = "DATA")]
+    data: Option<String>,
+
+    /// File with the encrypted data
+    #[structopt(long, conflicts_with = "DATA")]
+    input: Option<PathBuf>,
+
+    /// File to write the decrypted data to. The decrypted data is
+    /// printed if no file is given, which only works for text
+    #[structopt(short = "o", long)]
+    output: Option<PathBuf>,
+}
+
+impl EncryptCmd {
+    pub fn run(&self, opts: Opts) -> Result {
+        let data = read_input(&self.data, &self.input)?;
+        let recipient = PublicKey::try_from(self.to)?;
+        let sender = if self.authenticate {
+            let wallet = load_wallet(opts.files)?;
+            Some(get_wallet_secret(&wallet, &opts.keyfile)?.decrypt(&wallet)?)
+        } else {
+            None
+        };
+        let envelope = encrypt::encrypt(&data, &recipient, sender.as_ref())?;
+
+        let mut json = json!({
+            "to": self.to.to_b58()?,
+            "from": match &sender {
+                Some(sender) => Some(sender.pubkey_bin().to_b58()?),
+                None => None,
+            },
+        });
+        match &self.output {
+            Some(output) => {
+                fs::write(output, &envelope)?;
+                json["output"] = output.display().to_string().into();
+            }
+            None => json["data"] = envelope.to_b64()?.into(),
+        }
+        print_json(&json)
+    }
+}
+
+impl DecryptCmd {
+    pub fn run(&self, opts: Opts) -> Result {
+        let envelope = match &self.input {
+            Some(path) => fs::read(path)?,
+            None => {
+                let data = read_input(&self.data, &None)?;
+                Vec::<u8>::from_b64(String::from_utf8(data)?.trim())?
+            }
+        };
+        let wallet = load_wallet(opts.files)?;
+        let keypair = get_wallet_secret(&wallet, &opts.keyfile)?.decrypt(&wallet)?;
+        let decrypted = encrypt::decrypt(&envelope, &keypair)?;
+
+        let mut json = json!({
+            "to": keypair.pubkey_bin().to_b58()?,
+            "from": match &decrypted.sender {
+                Some(sender) => Some(sender.to_b58()?),
+                None => None,
+            },
+        });
+        match &self.output {
+            Some(output) => {
+                // Decrypted data is often a secret
+                let mut writer = open_secret_file(output, false)?;
+                writer.write_all(&decrypted.data)?;
+                json["output"] = output.display().to_string().into();
+            }
+            None => {
+                let data = String::from_utf8(decrypted.data).map_err(|_| {
+                    "Decrypted data is not text, use --output to write it to a file"
+                })?;
+                json["data"] = data.into();
+            }
+        }
+        print_json(&json)
+    }
+}
@@ -0,0 +1,153 @@
+use crate::{
+    cmd::{get_wallet_secret, load_wallet, open_secret_file, Opts},
+    keypair::Keypair,
+    mnemonic::seed_to_mnemonic,
+    result::Result,
+    secret::{self, Secret},
+    traits::B58,
+};
+use serde_derive::Serialize;
+use std::{
+    io::{self, Write},
+    path::PathBuf,
+};
+use structopt::{clap::arg_enum, StructOpt};
+
+arg_enum! {
+    #[derive(Debug, Clone, Copy)]
+    pub enum ExportFormat {
+        Mnemonic,
+        Hex,
+        B58,
+        Mobile,
+    }
+}
+
+/// The text that has to be typed to confirm an export
+const EXPORT_CONFIRMATION: &str = "export";
+
+#[derive(Debug, StructOpt)]
+/// Export the secret key of a wallet. The wallet is decrypted and its
+/// ed25519 seed is written out as seed words, hex, base58, or the JSON
+/// used by the mobile app. Anyone with the exported secret has full
+/// control over the wallet.
+pub struct Cmd {
+    #[structopt(possible_values = &["mnemonic", "hex", "b58", "mobile"],
+                case_insensitive = true)]
+    /// The format to export the secret in. Seed words ("mnemonic" and
+    /// "mobile") can only be exported for wallets created from, or
+    /// compatible with, mobile seed words. Wallets created from BIP39
+    /// seed words are exported as "hex" or "b58"
+    secret_format: ExportFormat,
+
+    #[structopt(short, long)]
+    /// File to write the secret to. The secret is written to standard
+    /// output if not given
+    output: Option<PathBuf>,
+
+    #[structopt(long)]
+    /// Allow writing the secret to standard output when it is not a
+    /// terminal, and overwrite an existing output file
+    force: bool,
+}
+
+impl Cmd {
+    pub fn run(&self, opts: Opts) -> Result {
+        if self.output.is_none() && !self.force && !console::Term::stdout().is_term() {
+            return Err("Refusing to export a secret to a non-terminal, use --force".into());
+        }
+        let wallet = load_wallet(opts.files)?;
+        let keypair = get_wallet_secret(&wallet, &opts.keyfile)?.decrypt(&wallet)?;
+        let secret = export_secret(&keypair, self.secret_format)?;
+
+        confirm_export()?;
+        match &self.output {
+            Some(output) => {
+                let mut writer = open_secret_file(output, !self.force)?;
+                writer.write_all(&secret)?;
+                writeln!(writer)?;
+                writer.sync_all()?;
+            }
+            None => {
+                let mut stdout = io::stdout();
+                stdout.write_all(&secret)?;
+                writeln!(stdout)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn confirm_export() -> Result {
+    let term = console::Term::stderr();
+    term.write_str(&format!(
+        "The exported secret gives full control over the wallet. Type \"{}\" to continue: ",
+        EXPORT_CONFIRMATION
+    ))?;
+    let mut confirmation = String::new();
+    io::stdin().read_line(&mut confirmation)?;
+    if confirmation.trim() != EXPORT_CONFIRMATION {
+        return Err("Export not confirmed".into());
+    }
+    Ok(())
+}
+
+/// The JSON the mobile wallet imports a key from
+#[derive(Serialize)]
+struct MobileExport<'a> {
+    address: String,
+    seed: &'a str,
+    words: &'a [String],
+}
+
+/// Exports the seed of the keypair in the given format. The secret is
+/// built in place so that no copies of it are left behind.
+fn export_secret(keypair: &Keypair, format: ExportFormat) -> Result<Secret<Vec<u8>>> {
+    let seed = keypair.seed()?;
+    match format {
+        ExportFormat::Mnemonic => Ok(join_words(seed_to_mnemonic(&seed)?)),
+        ExportFormat::Hex => {
+            let mut hex = Secret::new(vec![0; seed.len() * 2]);
+            hex::encode_to_slice(&seed[..], &mut hex[..])?;
+            Ok(hex)
+        }
+        ExportFormat::B58 => encode_b58(&seed[..]),
+        ExportFormat::Mobile => {
+            let words = seed_to_mnemonic(&seed)?;
+            let seed = encode_b58(&seed[..])?;
+            let export = MobileExport {
+                address: keypair.public.to_b58()?,
+                seed: std::str::from_utf8(&seed)?,
+                words: &words,
+            };
+            // Large enough for the JSON to never grow
+            let mut json = Secret::new(vec![0; 1024]);
+            let mut writer = &mut json[..];
+            let result = serde_json::to_writer(&mut writer, &export);
+            let len = 1024 - writer.len();
+            words.into_iter().for_each(secret::wipe);
+            result?;
+            Ok(Secret::new(json[..len].to_vec()))
+        }
+    }
+}
+
+/// Joins seed words with spaces into a secret and zeroes the words
+fn join_words(words: Vec<String>) -> Secret<Vec<u8>> {
+    let len = words.iter().map(|word| word.len() + 1).sum::<usize>();
+    let mut joined = Secret::new(vec![b' '; len.saturating_sub(1)]);
+    let mut start = 0;
+    for word in words {
+        joined[start..start + word.len()].copy_from_slice(word.as_bytes());
+        start += word.len() + 1;
+        secret::wipe(word);
+    }
+    joined
+}
+
+/// Encodes the data as base58 into a secret
+fn encode_b58(data: &[u8]) -> Result<Secret<Vec<u8>>> {
+    let mut buffer = Secret::new(vec![0; data.len() * 2]);
+    let len = bs58::encode(data).into(&mut buffer[..])?;
+    Ok(Secret::new(buffer[..len].to_vec()))
+}
@@ -0,0 +1,392 @@
+use crate::{
+    cmd::{get_password, open_secret_file, print_json, Opts},
+    frost::{
+        dkg::{self, Round1Package, Round2Package, State},
+        sign::{self, Nonces, SigningPackage, SigningRequest},
+        KeyShareFile, Sealed,
+    },
+    pwhash::Argon2id13,
+    result::Result,
+    secret::Secret,
+    signer::remote,
+};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::json;
+use sodiumoxide::crypto::pwhash::argon2id13;
+use std::{
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+};
+use structopt::StructOpt;
+
+#[derive(Debug, StructOpt)]
+/// Commands for threshold signing with a FROST group. Every participant
+/// holds a share of the group key in a share file, and any threshold of
+/// participants can sign for the group address without the key ever
+/// being put together.
+pub enum Cmd {
+    Dkg(Dkg),
+    Commit(Commit),
+    Sign(Sign),
+}
+
+#[derive(Debug, StructOpt)]
+/// Generate the key shares of a new FROST group. Every participant runs
+/// each of the three parts and sends the output of part 1 and part 2 to
+/// all other participants before running the next part.
+pub enum Dkg {
+    Part1(Part1),
+    Part2(Part2),
+    Part3(Part3),
+}
+
+#[derive(Debug, StructOpt)]
+/// Start a key generation. The secret state is stored encrypted with
+/// the wallet password until part 3.
+pub struct Part1 {
+    /// The identifier of this participant, from 1 to the number of
+    /// participants
+    #[structopt(long)]
+    id: u16,
+
+    /// The number of participants needed to sign
+    #[structopt(long)]
+    threshold: u16,
+
+    /// The number of participants in the group
+    #[structopt(long)]
+    participants: u16,
+
+    /// File to store the secret state in [default: dkg.<id>.state]
+    #[structopt(long)]
+    state: Option<PathBuf>,
+
+    /// File to write the package for the other participants to
+    /// [default: round1.<id>.json]
+    #[structopt(short = "o", long)]
+    output: Option<PathBuf>,
+}
+
+#[derive(Debug, StructOpt)]
+/// Encrypt a share for each of the other participants
+pub struct Part2 {
+    /// File with the secret state of part 1
+    #[structopt(long)]
+    state: PathBuf,
+
+    /// Part 1 package(s) of the other participants. Use this option
+    /// multiple times for multiple packages.
+    #[structopt(long, number_of_values(1))]
+    round1: Vec<PathBuf>,
+
+    /// File to write the package for the other participants to
+    /// [default: round2.<id>.json]
+    #[structopt(short = "o", long)]
+    output: Option<PathBuf>,
+}
+
+#[derive(Debug, StructOpt)]
+/// Check the shares of the other participants and write the key share
+/// of this participant. The key share is encrypted with the wallet
+/// password and the state file is removed.
+pub struct Part3 {
+    /// File with the secret state of part 1
+    #[structopt(long)]
+    state: PathBuf,
+
+    /// Part 1 package(s) of the other participants
+    #[structopt(long, number_of_values(1))]
+    round1: Vec<PathBuf>,
+
+    /// Part 2 package(s) of the other participants
+    #[structopt(long, number_of_values(1))]
+    round2: Vec<PathBuf>,
+
+    /// File to write the key share to [default: share.<id>.json]
+    #[structopt(short = "o", long)]
+    output: Option<PathBuf>,
+
+    /// Also write a signer reference file that coordinates signatures
+    /// for the group, with signing sessions in a "sessions" directory
+    /// next to it
+    #[structopt(long)]
+    reference: Option<PathBuf>,
+}
+
+#[derive(Debug, StructOpt)]
+/// Commit to sign a signing request with the key share given with -f.
+/// The secret nonces are stored encrypted next to the share file until
+/// the signing package arrives.
+pub struct Commit {
+    #[structopt(name = "REQUEST FILE")]
+    request: PathBuf,
+
+    /// File to write the commitment to [default: commit.<id>.json]
+    #[structopt(short = "o", long)]
+    output: Option<PathBuf>,
+}
+
+#[derive(Debug, StructOpt)]
+/// Sign a signing package with the key share given with -f. The
+/// transaction summary is shown and has to be confirmed before signing.
+/// The nonces committed to are removed, so a package can only be signed
+/// once.
+pub struct Sign {
+    #[structopt(name = "PACKAGE FILE")]
+    package: PathBuf,
+
+    /// File to write the signature share to [default: sigshare.<id>.json]
+    #[structopt(short = "o", long)]
+    output: Option<PathBuf>,
+}
+
+impl Cmd {
+    pub fn run(&self, opts: Opts) -> Result {
+        match self {
+            Cmd::Dkg(cmd) => cmd.run(opts),
+            Cmd::Commit(cmd) => cmd.run(opts),
+            Cmd::Sign(cmd) => cmd.run(opts),
+        }
+    }
+}
+
+impl Dkg {
+    pub fn run(&self, opts: Opts) -> Result {
+        match self {
+            Dkg::Part1(cmd) => cmd.run(opts),
+            Dkg::Part2(cmd) => cmd.run(opts),
+            Dkg::Part3(cmd) => cmd.run(opts),
+        }
+    }
+}
+
+impl Part1 {
+    pub fn run(&self, opts: Opts) -> Result {
+        let network = opts.network.unwrap_or_default();
+        let (state, package) = dkg::part1(self.id, self.threshold, self.participants, network)?;
+        let password = get_password(true)?;
+
+        let state_file = default_path(&self.state, "dkg", self.id, "state");
+        let sealed = Sealed::seal(
+            &Secret::new(serde_json::to_vec(&state)?),
+            &password,
+            interactive_pwhash(),
+        )?;
+        write_secret_json(&state_file, &sealed)?;
+        let output = default_path(&self.output, "round1", self.id, "json");
+        write_json(&output, &package)?;
+        print_json(&json!({
+            "identifier": self.id,
+            "network": network.to_string(),
+            "state": state_file,
+            "round1": output,
+        }))
+    }
+}
+
+impl Part2 {
+    pub fn run(&self, _opts: Opts) -> Result {
+        let password = get_password(false)?;
+        let state = open_state(&self.state, &password)?;
+        let round1: Vec<Round1Package> = read_files(&self.round1)?;
+        let package = dkg::part2(&state, &round1)?;
+
+        let output = default_path(&self.output, "round2", state.identifier(), "json");
+        write_json(&output, &package)?;
+        print_json(&json!({
+            "identifier": state.identifier(),
+            "round2": output,
+        }))
+    }
+}
+
+impl Part3 {
+    pub fn run(&self, _opts: Opts) -> Result {
+        let password = get_password(false)?;
+        let state = open_state(&self.state, &password)?;
+        let round1: Vec<Round1Package> = read_files(&self.round1)?;
+        let round2: Vec<Round2Package> = read_files(&self.round2)?;
+        let share = dkg::part3(&state, &round1, &round2)?;
+
+        let output = default_path(&self.output, "share", state.identifier(), "json");
+        let share_file = KeyShareFile::seal(&share, &password, Argon2id13::default())?;
+        write_secret_json(&output, &share_file)?;
+        if let Some(reference) = &self.reference {
+            write_json(
+                reference,
+                &json!({
+                    "signer": "frost",
+                    "group": fs::canonicalize(&output)?,
+                    "sessions": "sessions",
+                }),
+            )?;
+        }
+        fs::remove_file(&self.state)?;
+        print_json(&json!({
+            "address": share_file.address,
+            "identifier": share_file.identifier,
+            "threshold": share_file.group.threshold,
+            "participants": share_file.group.verifying_shares.len(),
+            "network": share_file.group.network.to_string(),
+            "share": output,
+        }))
+    }
+}
+
+impl Commit {
+    pub fn run(&self, opts: Opts) -> Result {
+        let share_path = share_path(&opts)?;
+        let share_file = KeyShareFile::load(share_path)?;
+        let request: SigningRequest = read_file(&self.request)?;
+        check_request(&share_file, &request)?;
+        let nonces_path = nonces_path(share_path, &request.session)?;
+        if nonces_path.exists() {
+            return Err(format!("Already committed to signing session {}", request.session).into());
+        }
+
+        let password = get_password(false)?;
+        let share = share_file.open(&password)?;
+        let (nonces, commitment) = sign::commit(&share, &request)?;
+        let sealed = Sealed::seal(
+            &Secret::new(serde_json::to_vec(&nonces)?),
+            &password,
+            interactive_pwhash(),
+        )?;
+        write_secret_json(&nonces_path, &sealed)?;
+        let output = default_path(&self.output, "commit", share.identifier, "json");
+        write_json(&output, &commitment)?;
+        print_json(&json!({
+            "address": share_file.address,
+            "identifier": share.identifier,
+            "session": request.session,
+            "txn_type": request.txn_type,
+            "summary": request.summary,
+            "commit": output,
+        }))
+    }
+}
+
+impl Sign {
+    pub fn run(&self, opts: Opts) -> Result {
+        let share_path = share_path(&opts)?;
+        let share_file = KeyShareFile::load(share_path)?;
+        let package: SigningPackage = read_file(&self.package)?;
+        check_request(&share_file, &package.request)?;
+        let nonces_path = nonces_path(share_path, &package.request.session)?;
+        if !nonces_path.exists() {
+            return Err(format!(
+                "Not committed to signing session {}, or already signed",
+                package.request.session
+            )
+            .into());
+        }
+        let term = console::Term::stderr();
+        term.write_line(&format!(
+            "{}: {}",
+            package.request.txn_type, package.request.summary
+        ))?;
+        if !dialoguer::Confirm::new()
+            .with_prompt("Sign this transaction?")
+            .interact()?
+        {
+            return Err("Signing refused".into());
+        }
+
+        let password = get_password(false)?;
+        let share = share_file.open(&password)?;
+        let sealed: Sealed = read_file(&nonces_path)?;
+        let nonces: Nonces = serde_json::from_slice(&sealed.open(&password)?)?;
+        // Nonces must never be used twice, so they are gone before they
+        // are used once
+        fs::remove_file(&nonces_path)?;
+        let signature_share = sign::sign(&share, nonces, &package)?;
+
+        let output = default_path(&self.output, "sigshare", share.identifier, "json");
+        write_json(&output, &signature_share)?;
+        print_json(&json!({
+            "address": share_file.address,
+            "identifier": share.identifier,
+            "session": package.request.session,
+            "txn_type": package.request.txn_type,
+            "summary": package.request.summary,
+            "sigshare": output,
+        }))
+    }
+}
+
+/// Checks that a signing request is for the group of the given share,
+/// and that its summary describes the data that is signed
+fn check_request(share_file: &KeyShareFile, request: &SigningRequest) -> Result {
+    if request.address != share_file.address {
+        return Err(format!(
+            "Signing request is for {}, not {}",
+            request.address, share_file.address
+        )
+        .into());
+    }
+    if remote::txn_summary(&request.txn_type, &request.data()?)? != request.summary {
+        return Err("Summary does not match the transaction".into());
+    }
+    Ok(())
+}
+
+fn share_path(opts: &Opts) -> Result<&Path> {
+    match opts.files.first() {
+        Some(path) => Ok(path),
+        None => Err("A key share file is expected".into()),
+    }
+}
+
+/// The file the nonces of a signing session are kept in until they are
+/// used. Session identifiers come from signing requests, so anything but
+/// the 16 random bytes in hex is rejected before it ends up in a path.
+fn nonces_path(share_path: &Path, session: &str) -> Result<PathBuf> {
+    if session.len() != 32 || !session.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err(format!("Invalid signing session {:?}", session).into());
+    }
+    let mut path = share_path.as_os_str().to_owned();
+    path.push(format!(".{}.nonces", session));
+    Ok(PathBuf::from(path))
+}
+
+fn open_state(path: &Path, password: &[u8]) -> Result<State> {
+    let sealed: Sealed = read_file(path)?;
+    Ok(serde_json::from_slice(&sealed.open(password)?)?)
+}
+
+/// Short lived secrets are protected with the cheaper interactive
+/// password hash limits
+fn interactive_pwhash() -> Argon2id13 {
+    Argon2id13::with_limits(
+        argon2id13::OPSLIMIT_INTERACTIVE,
+        argon2id13::MEMLIMIT_INTERACTIVE,
+    )
+}
+
+fn default_path(path: &Option<PathBuf>, prefix: &str, identifier: u16, extension: &str) -> PathBuf {
+    match path {
+        Some(path) => path.clone(),
+        None => PathBuf::from(format!("{}.{}.{}", prefix, identifier, extension)),
+    }
+}
+
+fn read_file<T: DeserializeOwned>(path: &Path) -> Result<T> {
+    let data = fs::read(path)?;
+    Ok(serde_json::from_slice(&data).map_err(|err| format!("{}: {}", path.display(), err))?)
+}
+
+fn read_files<T: DeserializeOwned>(paths: &[PathBuf]) -> Result<Vec<T>> {
+    paths.iter().map(|path| read_file(path)).collect()
+}
+
+fn write_json<T: Serialize>(path: &Path, value: &T) -> Result {
+    fs::write(path, serde_json::to_string_pretty(value)?)?;
+    Ok(())
+}
+
+fn write_secret_json<T: Serialize>(path: &Path, value: &T) -> Result {
+    let mut writer = open_secret_file(&path.to_path_buf(), true)?;
+    writer.write_all(serde_json::to_string_pretty(value)?.as_bytes())?;
+    Ok(())
+}
@@ -36,7 +36,7 @@ pub struct List {
 
 impl List {
     pub fn run(&self, opts: Opts) -> Result {
-        let client = Client::new_with_base_url(api_url());
+        let client = Client::new_with_base_url(api_url(opts.network.unwrap_or_default())?);
         let mut results: Vec<(String, Result<Vec<Hotspot>>)> =
             Vec::with_capacity(self.addresses.len());
         for address in collect_addresses(opts.files, self.addresses.clone())? {
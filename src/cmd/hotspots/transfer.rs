@@ -1,6 +1,6 @@
 use crate::{
     cmd::{
-        api_url, get_password, get_txn_fees, load_wallet, print_json, status_json, Opts,
+        api_url, get_txn_fees, load_signer, print_json, status_json, wallet_network, Opts,
         OutputFormat,
     },
     keypair::PubKeyBin,
@@ -61,12 +61,13 @@ impl Buy {
 
 impl Transfer {
     pub fn run(self, opts: Opts) -> Result {
-        let wallet = load_wallet(opts.files)?;
-        let client = Client::new_with_base_url(api_url());
+        let signer = load_signer(opts.files, &opts.keyfile)?;
+        let network = wallet_network(&signer.pubkey_bin(), opts.network)?;
+        let client = Client::new_with_base_url(api_url(network)?);
 
         match self {
             Self::Sell(sell) => {
-                let seller = wallet.pubkey_bin.to_vec();
+                let seller = signer.pubkey_bin().to_vec();
                 let buyer = PubKeyBin::from_b58(&sell.buyer)?;
                 let buyer_account = client.get_account(&buyer.to_b58()?)?;
                 let gateway = PubKeyBin::from_b58(&sell.gateway)?.to_vec();
@@ -82,9 +83,7 @@ impl Transfer {
                     buyer_nonce: buyer_account.speculative_nonce + 1,
                 };
                 txn.fee = txn.txn_fee(&get_txn_fees(&client)?)?;
-                let password = get_password(false)?;
-                let keypair = wallet.decrypt(password.as_bytes())?;
-                txn.seller_signature = txn.sign(&keypair)?;
+                txn.seller_signature = txn.sign(signer.as_ref())?;
                 println!("{}", txn.in_envelope().to_b64()?);
                 Ok(())
             }
@@ -108,9 +107,7 @@ impl Transfer {
                             return Err("Hotspot transfer nonce no longer valid".into());
                         }
 
-                        let password = get_password(false)?;
-                        let keypair = wallet.decrypt(password.as_bytes())?;
-                        t.buyer_signature = t.sign(&keypair)?;
+                        t.buyer_signature = t.sign(signer.as_ref())?;
                         let status = if buy.commit {
                             Some(client.submit_txn(&envelope)?)
                         } else {
@@ -1,7 +1,7 @@
 use crate::{
     cmd::{
-        api_url, get_password, get_txn_fees, load_wallet, print_footer, print_json, status_json,
-        status_str, Opts, OutputFormat,
+        api_url, check_payee_network, get_txn_fees, load_signer, print_footer, print_json,
+        status_json, status_str, wallet_network, Opts, OutputFormat,
     },
     keypair::{Keypair, PubKeyBin},
     result::Result,
@@ -43,6 +43,10 @@ pub struct Create {
     /// Commit the payment to the API
     #[structopt(long)]
     commit: bool,
+
+    /// Allow payees on a different network than the wallet
+    #[structopt(long)]
+    allow_cross_network: bool,
 }
 
 #[derive(Debug, StructOpt)]
@@ -75,19 +79,19 @@ impl Cmd {
 
 impl Create {
     pub fn run(&self, opts: Opts) -> Result {
-        let password = get_password(false)?;
-        let wallet = load_wallet(opts.files)?;
-        let client = Client::new_with_base_url(api_url());
-
-        let keypair = wallet.decrypt(password.as_bytes())?;
-        let account = client.get_account(&keypair.public.to_b58()?)?;
-        let address = Keypair::gen_keypair().pubkey_bin();
-
+        let signer = load_signer(opts.files, &opts.keyfile)?;
+        let network = wallet_network(&signer.pubkey_bin(), opts.network)?;
+        let client = Client::new_with_base_url(api_url(network)?);
+        let payee = PubKeyBin::from_b58(&self.payee)?;
+        check_payee_network(network, &payee, self.allow_cross_network)?;
+
+        let account = client.get_account(&signer.pubkey_bin().to_b58()?)?;
+        let address = Keypair::gen_keypair()?.with_network(network).pubkey_bin();
         let mut txn = BlockchainTxnCreateHtlcV1 {
             amount: self.hnt.to_bones(),
             fee: 0,
-            payee: PubKeyBin::from_b58(&self.payee)?.into(),
-            payer: keypair.pubkey_bin().into(),
+            payee: payee.into(),
+            payer: signer.pubkey_bin().into(),
             address: address.into(),
             hashlock: hex::decode(self.hashlock.clone()).unwrap(),
             timelock: self.timelock,
@@ -95,7 +99,7 @@ impl Create {
             signature: Vec::new(),
         };
         txn.fee = txn.txn_fee(&get_txn_fees(&client)?)?;
-        txn.signature = txn.sign(&keypair)?;
+        txn.signature = txn.sign(signer.as_ref())?;
         let envelope = txn.in_envelope();
 
         let status = if self.commit {
@@ -146,20 +150,19 @@ fn print_create_txn(
 
 impl Redeem {
     pub fn run(&self, opts: Opts) -> Result {
-        let password = get_password(false)?;
-        let wallet = load_wallet(opts.files)?;
-        let keypair = wallet.decrypt(password.as_bytes())?;
-        let client = Client::new_with_base_url(api_url());
+        let signer = load_signer(opts.files, &opts.keyfile)?;
+        let network = wallet_network(&signer.pubkey_bin(), opts.network)?;
+        let client = Client::new_with_base_url(api_url(network)?);
 
         let mut txn = BlockchainTxnRedeemHtlcV1 {
             fee: 0,
-            payee: keypair.pubkey_bin().into(),
+            payee: signer.pubkey_bin().into(),
             address: PubKeyBin::from_b58(&self.address)?.into(),
             preimage: self.preimage.clone().into_bytes(),
             signature: Vec::new(),
         };
         txn.fee = txn.txn_fee(&get_txn_fees(&client)?)?;
-        txn.signature = txn.sign(&keypair)?;
+        txn.signature = txn.sign(signer.as_ref())?;
         let envelope = txn.in_envelope();
 
         let status = if self.commit {
@@ -1,5 +1,8 @@
 use crate::{
-    cmd::{api_url, load_wallet, print_json, print_table, Opts, OutputFormat},
+    cmd::{
+        api_url, load_wallet, print_json, print_table, pwhash::pwhash_params, wallet_network, Opts,
+        OutputFormat,
+    },
     result::Result,
     wallet::Wallet,
 };
@@ -25,7 +28,8 @@ impl Cmd {
             print_qr(&address)?;
             Ok(())
         } else {
-            let client = Client::new_with_base_url(api_url());
+            let network = wallet_network(&wallet.pubkey_bin, opts.network)?;
+            let client = Client::new_with_base_url(api_url(network)?);
             let account = client.get_account(&wallet.address()?)?;
             print_wallet(&wallet, &account, opts.format)
         }
@@ -38,8 +42,12 @@ fn print_wallet(wallet: &Wallet, account: &Account, format: OutputFormat) -> Res
             let mut table = Table::new();
             table.add_row(row!["Key", "Value"]);
             table.add_row(row!["Address", account.address]);
+            table.add_row(row!["Label", wallet.label().unwrap_or("")]);
+            table.add_row(row!["Network", wallet.network()?]);
             table.add_row(row!["Sharded", wallet.is_sharded()]);
-            table.add_row(row!["PWHash", wallet.pwhash()]);
+            table.add_row(row!["Keyfile", wallet.needs_keyfile()]);
+            table.add_row(row!["PWHash", wallet.pwhash()?]);
+            table.add_row(row!["PWHash Params", wallet.pwhash()?.params()]);
             table.add_row(row!["Balance", Hnt::from_bones(account.balance)]);
             table.add_row(row!["DC Balance", account.dc_balance]);
             table.add_row(row![
@@ -50,8 +58,13 @@ fn print_wallet(wallet: &Wallet, account: &Account, format: OutputFormat) -> Res
         }
         OutputFormat::Json => {
             let table = json!({
+                "label": wallet.label(),
+                "created": wallet.created(),
+                "network": wallet.network()?.to_string(),
                 "sharded": wallet.is_sharded(),
-                "pwhash": wallet.pwhash().to_string(),
+                "keyfile": wallet.needs_keyfile(),
+                "pwhash": wallet.pwhash()?.to_string(),
+                "pwhash_params": pwhash_params(wallet.pwhash()?),
                 "account": account,
             });
             print_json(&table)
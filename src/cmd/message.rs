@@ -0,0 +1,100 @@
+use crate::{
+    cmd::{load_signer, print_json, print_table, read_input, Opts, OutputFormat},
+    message::{self, SignedMessage},
+    result::Result,
+};
+use prettytable::{format, Table};
+use serde_json::json;
+use std::{fs, path::PathBuf};
+use structopt::StructOpt;
+
+#[derive(Debug, StructOpt)]
+/// Sign a message to prove control of the wallet address. The message
+/// is given as an argument, read from a file with --input, or read from
+/// stdin. The signature is over a hash of the message in a format that
+/// can never be mistaken for a transaction.
+pub struct SignCmd {
+    /// The message to sign
+    #[structopt(name = "MESSAGE")]
+    message: Option<String>,
+
+    /// File to read the message from
+    #[structopt(long, conflicts_with = "MESSAGE")]
+    input: Option<PathBuf>,
+}
+
+#[derive(Debug, StructOpt)]
+/// Verify a message signature made with sign-message. The message is
+/// given the same way as for sign-message, and the signature either as
+/// an address and signature or as the JSON output of sign-message.
+pub struct VerifyCmd {
+    /// The message that was signed
+    #[structopt(name = "MESSAGE")]
+    message: Option<String>,
+
+    /// File to read the message from
+    #[structopt(long, conflicts_with = "MESSAGE")]
+    input: Option<PathBuf>,
+
+    /// Address that signed the message
+    #[structopt(long, required_unless = "proof", requires = "signature")]
+    address: Option<String>,
+
+    /// Base64 encoded signature
+    #[structopt(long, requires = "address")]
+    signature: Option<String>,
+
+    /// File with the JSON output of sign-message
+    #[structopt(long, conflicts_with = "address")]
+    proof: Option<PathBuf>,
+}
+
+impl SignCmd {
+    pub fn run(&self, opts: Opts) -> Result {
+        let message = read_input(&self.message, &self.input)?;
+        let signer = load_signer(opts.files, &opts.keyfile)?;
+        let signed = message::sign_message(signer.as_ref(), &message)?;
+        match opts.format {
+            OutputFormat::Table => {
+                let mut table = Table::new();
+                table.set_format(*format::consts::FORMAT_NO_LINESEP_WITH_TITLE);
+                table.set_titles(row!["Address", "Hash", "Signature"]);
+                table.add_row(row![signed.address, signed.hash, signed.signature]);
+                print_table(&table)
+            }
+            OutputFormat::Json => print_json(&signed),
+        }
+    }
+}
+
+impl VerifyCmd {
+    pub fn run(&self, opts: Opts) -> Result {
+        let message = read_input(&self.message, &self.input)?;
+        let signed = match (&self.proof, &self.address, &self.signature) {
+            (Some(path), _, _) => serde_json::from_slice(&fs::read(path)?)
+                .map_err(|err| format!("{}: invalid message signature: {}", path.display(), err))?,
+            (None, Some(address), Some(signature)) => SignedMessage {
+                address: address.to_string(),
+                hash: hex::encode(message::message_hash(&message)),
+                signature: signature.to_string(),
+            },
+            _ => return Err("An address and signature or a proof file is expected".into()),
+        };
+        let result = signed.verify(&message);
+        match opts.format {
+            OutputFormat::Table => {
+                let mut table = Table::new();
+                table.set_format(*format::consts::FORMAT_NO_LINESEP_WITH_TITLE);
+                table.set_titles(row!["Address", "Hash", "Verify"]);
+                table.add_row(row![signed.address, signed.hash, result.is_ok()]);
+                print_table(&table)
+            }
+            OutputFormat::Json => print_json(&json!({
+                "address": signed.address,
+                "hash": signed.hash,
+                "signature": signed.signature,
+                "verify": result.is_ok(),
+            })),
+        }
+    }
+}
@@ -1,27 +1,48 @@
 use crate::{
-    keypair::PubKeyBin,
-    mnemonic,
+    format::Format,
+    keypair::{Keypair, Network, PubKeyBin, PublicKey},
+    mnemonic::{self, Language, SeedType},
     result::Result,
+    secret::Secret,
+    signer::{pkcs11, remote, Signer, SignerConfig},
     traits::{TxnFeeConfig, B58},
-    wallet::Wallet,
+    wallet::{Metadata, SealedWallet, Wallet},
 };
 use helium_api::{Client, PendingTxnStatus};
-use std::{env, fs, io, path::PathBuf};
+use sodiumoxide::randombytes;
+use std::{
+    collections::BTreeMap,
+    convert::TryFrom,
+    env, fs,
+    io::{self, Read},
+    path::{Path, PathBuf},
+};
 use structopt::{clap::arg_enum, StructOpt};
 
+pub mod agent;
 pub mod balance;
 pub mod burn;
 pub mod create;
+pub mod encrypt;
+pub mod export;
+pub mod frost;
 pub mod hotspots;
 pub mod htlc;
 pub mod info;
+pub mod message;
 pub mod multisig;
 pub mod onboard;
 pub mod oracle;
 pub mod oui;
+pub mod passwd;
 pub mod pay;
+pub mod pwhash;
 pub mod request;
+pub mod reshard;
 pub mod securities;
+pub mod signer;
+pub mod slot;
+pub mod token;
 pub mod upgrade;
 pub mod vars;
 pub mod verify;
@@ -52,34 +73,286 @@ pub struct Opts {
                 case_insensitive = true,
                 default_value = "table")]
     format: OutputFormat,
+
+    /// Network to use. Commands that use a wallet default to the
+    /// network of the wallet and refuse a wallet for another network.
+    /// Other commands, and new wallets, default to mainnet
+    #[structopt(long = "network",
+                possible_values = &["mainnet", "testnet"],
+                case_insensitive = true)]
+    network: Option<Network>,
+
+    /// Keyfile for wallets that need a keyfile besides the password.
+    /// For wallets with unlock slots, the keyfile of a keyfile slot to
+    /// unlock the wallet with instead of a password
+    #[structopt(long = "keyfile")]
+    keyfile: Option<PathBuf>,
 }
 
+/// The custodian of every given shard of a wallet by shard number, or
+/// None for shards that are not sealed
+pub type ShardCustodians = BTreeMap<u8, Option<PubKeyBin>>;
+
 fn load_wallet(files: Vec<PathBuf>) -> Result<Wallet> {
+    Ok(load_wallet_custodians(files)?.0)
+}
+
+/// Loads a wallet like `load_wallet`, and returns the custodians the
+/// given shards are sealed to along with it
+fn load_wallet_custodians(files: Vec<PathBuf>) -> Result<(Wallet, ShardCustodians)> {
+    let mut keypairs = vec![];
+    let mut custodians = ShardCustodians::new();
     let mut files_iter = files.iter();
-    let mut first_wallet = match files_iter.next() {
-        Some(path) => {
-            let mut reader = fs::File::open(path)?;
-            Wallet::read(&mut reader)?
-        }
+    let (mut first_wallet, custodian) = match files_iter.next() {
+        Some(path) => read_wallet(path, &mut keypairs)?,
         None => return Err("At least one wallet file expected".into()),
     };
+    if let Ok(number) = first_wallet.shard_number() {
+        custodians.insert(number, custodian);
+    }
 
     for path in files_iter {
-        let mut reader = fs::File::open(path)?;
-        let w = Wallet::read(&mut reader)?;
-        first_wallet.absorb_shard(&w)?;
+        let (w, custodian) = read_wallet(path, &mut keypairs)?;
+        first_wallet
+            .absorb_shard(&w)
+            .map_err(|err| format!("{}: {}", path.display(), err))?;
+        custodians.insert(w.shard_number()?, custodian);
+    }
+
+    Ok((first_wallet, custodians))
+}
+
+/// Reads a wallet file, and returns the custodian it was sealed to if
+/// any. Shards that are sealed to a custodian are unsealed with the
+/// custodian's wallet, which is decrypted once and kept in the given
+/// keypairs for other shards.
+fn read_wallet(path: &Path, keypairs: &mut Vec<Keypair>) -> Result<(Wallet, Option<PubKeyBin>)> {
+    let data = fs::read(path)?;
+    if !SealedWallet::is_sealed(&data) {
+        return Ok((Wallet::read(&mut &data[..])?, None));
+    }
+    let sealed = SealedWallet::read(&mut &data[..])?;
+    let index = match keypairs
+        .iter()
+        .position(|keypair| keypair.pubkey_bin() == sealed.custodian)
+    {
+        Some(index) => index,
+        None => {
+            let keypair = load_custodian(&sealed.custodian)
+                .map_err(|err| format!("{}: {}", path.display(), err))?;
+            keypairs.push(keypair);
+            keypairs.len() - 1
+        }
+    };
+    let wallet = sealed
+        .unseal(&keypairs[index])
+        .map_err(|err| format!("{}: {}", path.display(), err))?;
+    Ok((wallet, Some(sealed.custodian)))
+}
+
+/// Decrypts the custodian wallet for the given address. Custodian
+/// wallets are looked up in the list of files in
+/// HELIUM_WALLET_CUSTODIAN, skipping files that can not be read as a
+/// wallet. A custodian wallet that needs a keyfile, or is unlocked with
+/// a keyfile slot, takes the keyfile in HELIUM_WALLET_CUSTODIAN_KEYFILE.
+fn load_custodian(address: &PubKeyBin) -> Result<Keypair> {
+    let not_found = || {
+        format!(
+            "Shard is sealed to custodian {}, add their wallet to HELIUM_WALLET_CUSTODIAN",
+            address
+        )
+    };
+    let paths = env::var_os("HELIUM_WALLET_CUSTODIAN").ok_or_else(not_found)?;
+    for path in env::split_paths(&paths) {
+        let wallet = match fs::File::open(&path) {
+            Ok(mut reader) => match Wallet::read(&mut reader) {
+                Ok(wallet) => wallet,
+                Err(_) => continue,
+            },
+            Err(_) => continue,
+        };
+        if wallet.pubkey_bin == *address {
+            let keyfile = env::var_os("HELIUM_WALLET_CUSTODIAN_KEYFILE").map(PathBuf::from);
+            let secret = get_wallet_secret_with(&wallet, &keyfile, || {
+                let password = read_password(
+                    "HELIUM_WALLET_CUSTODIAN_PASSWORD",
+                    &format!("Custodian Password ({})", address),
+                    false,
+                )?;
+                Ok(Secret::new(password.into_bytes()))
+            })?;
+            return secret
+                .decrypt(&wallet)
+                .map_err(|err| format!("Custodian wallet {}: {}", path.display(), err).into());
+        }
+    }
+    Err(not_found().into())
+}
+
+/// Loads the signer for the given wallet files. The signer is
+/// selected by the signer reference file in HELIUM_WALLET_SIGNER if
+/// set, or by the first given file if that is a signer reference
+/// file. Otherwise the files are encrypted wallet files, which are
+/// decrypted with the wallet password to sign in-process.
+fn load_signer(files: Vec<PathBuf>, keyfile: &Option<PathBuf>) -> Result<Box<dyn Signer>> {
+    let config = match env::var("HELIUM_WALLET_SIGNER") {
+        Ok(path) => Some(SignerConfig::load(&PathBuf::from(path))?),
+        Err(_) => match files.first() {
+            Some(path) if SignerConfig::is_reference(path)? => Some(SignerConfig::load(path)?),
+            _ => None,
+        },
+    };
+    let files = match config {
+        Some(SignerConfig::Wallet { files }) => files,
+        Some(SignerConfig::Pkcs11(config)) => return pkcs11::open(&config, &get_pin()?),
+        Some(SignerConfig::Remote(config)) => return remote::open(&config),
+        Some(SignerConfig::Frost(config)) => {
+            return crate::signer::frost::open(&config, Box::new(wait_for_participants))
+        }
+        None => files,
+    };
+    let wallet = load_wallet(files)?;
+    if let Some(signer) = agent_signer(&wallet) {
+        return Ok(signer);
+    }
+    let secret = get_wallet_secret(&wallet, keyfile)?;
+    Ok(Box::new(secret.decrypt(&wallet)?))
+}
+
+/// Returns the agent in HELIUM_WALLET_AGENT_SOCK as the signer for
+/// the given wallet if the agent is running and holds the wallet's
+/// key
+fn agent_signer(wallet: &Wallet) -> Option<Box<dyn Signer>> {
+    let socket = env::var_os("HELIUM_WALLET_AGENT_SOCK")?;
+    let config = remote::Config {
+        socket: PathBuf::from(socket),
+        address: Some(wallet.address().ok()?),
+    };
+    remote::open(&config).ok()
+}
+
+/// Shows what a FROST coordinator waits for and waits for the user to
+/// continue
+fn wait_for_participants(instructions: &str) -> Result {
+    let term = console::Term::stderr();
+    term.write_line(instructions)?;
+    term.write_line("Press enter to continue")?;
+    let mut line = String::new();
+    if io::stdin().read_line(&mut line)? == 0 {
+        return Err("Signing aborted".into());
+    }
+    Ok(())
+}
+
+fn get_password(confirm: bool) -> std::io::Result<Secret<Vec<u8>>> {
+    let password = read_password("HELIUM_WALLET_PASSWORD", "Password", confirm)?;
+    Ok(Secret::new(password.into_bytes()))
+}
+
+/// The secrets a wallet is encrypted with
+struct WalletSecret {
+    /// The password, or the keyfile contents for a keyfile unlock slot
+    password: Secret<Vec<u8>>,
+    /// The keyfile contents for a wallet that needs a keyfile besides
+    /// the password
+    keyfile: Option<Secret<Vec<u8>>>,
+}
+
+impl WalletSecret {
+    fn keyfile(&self) -> Option<&[u8]> {
+        self.keyfile.as_ref().map(|keyfile| &keyfile[..])
+    }
+
+    fn decrypt(&self, wallet: &Wallet) -> Result<Keypair> {
+        wallet.decrypt_with_keyfile(&self.password, self.keyfile())
+    }
+
+    fn encrypt(&self, keypair: &Keypair, format: Format, metadata: Metadata) -> Result<Wallet> {
+        Wallet::encrypt_with_metadata(keypair, &self.password, self.keyfile(), format, metadata)
+    }
+}
+
+/// Returns the secret to decrypt a wallet with. For wallets with
+/// unlock slots this is the contents of the given keyfile or the
+/// password. Other wallets take the password, and the keyfile if the
+/// wallet needs one.
+fn get_wallet_secret(wallet: &Wallet, keyfile: &Option<PathBuf>) -> Result<WalletSecret> {
+    get_wallet_secret_with(wallet, keyfile, || get_password(false))
+}
+
+/// Returns the secret to decrypt a wallet with like
+/// `get_wallet_secret`, asking for the password with the given
+/// function
+fn get_wallet_secret_with<F>(
+    wallet: &Wallet,
+    keyfile: &Option<PathBuf>,
+    get_password: F,
+) -> Result<WalletSecret>
+where
+    F: FnOnce() -> io::Result<Secret<Vec<u8>>>,
+{
+    let keyfile = match keyfile {
+        Some(path) => Some(read_keyfile(path)?),
+        None => None,
+    };
+    if wallet.slots().is_ok() {
+        if let Some(keyfile) = keyfile {
+            return Ok(WalletSecret {
+                password: keyfile,
+                keyfile: None,
+            });
+        }
+    } else {
+        match (wallet.needs_keyfile(), &keyfile) {
+            (true, None) => return Err("Wallet needs a keyfile, give it with --keyfile".into()),
+            (false, Some(_)) => return Err("Wallet does not use a keyfile".into()),
+            _ => (),
+        }
+    }
+    Ok(WalletSecret {
+        password: get_password()?,
+        keyfile,
+    })
+}
+
+/// Returns the secret to encrypt a new wallet with, which is a new
+/// password and the given keyfile
+fn get_new_wallet_secret(keyfile: &Option<PathBuf>) -> Result<WalletSecret> {
+    let keyfile = match keyfile {
+        Some(path) => Some(read_keyfile(path)?),
+        None => None,
+    };
+    Ok(WalletSecret {
+        password: get_password(true)?,
+        keyfile,
+    })
+}
+
+fn read_keyfile(path: &Path) -> Result<Secret<Vec<u8>>> {
+    let data = Secret::new(fs::read(path).map_err(|err| format!("{}: {}", path.display(), err))?);
+    if data.is_empty() {
+        return Err(format!("{}: keyfile is empty", path.display()).into());
     }
+    Ok(data)
+}
 
-    Ok(first_wallet)
+fn get_pin() -> std::io::Result<Secret<Vec<u8>>> {
+    let pin = read_password("HELIUM_WALLET_PKCS11_PIN", "Token PIN", false)?;
+    Ok(Secret::new(pin.into_bytes()))
 }
 
-fn get_password(confirm: bool) -> std::io::Result<String> {
-    match env::var("HELIUM_WALLET_PASSWORD") {
+fn get_new_password() -> std::io::Result<Secret<Vec<u8>>> {
+    let password = read_password("HELIUM_WALLET_NEW_PASSWORD", "New Password", true)?;
+    Ok(Secret::new(password.into_bytes()))
+}
+
+fn read_password(env_var: &str, prompt: &str, confirm: bool) -> std::io::Result<String> {
+    match env::var(env_var) {
         Ok(str) => Ok(str),
         _ => {
             use dialoguer::Password;
             let mut builder = Password::new();
-            builder.with_prompt("Password");
+            builder.with_prompt(prompt);
             if confirm {
                 builder.with_confirmation("Confirm password", "Passwords do not match");
             };
@@ -88,30 +361,102 @@ fn get_password(confirm: bool) -> std::io::Result<String> {
     }
 }
 
-fn api_url() -> String {
-    env::var("HELIUM_API_URL").unwrap_or_else(|_| helium_api::DEFAULT_BASE_URL.to_string())
+/// The API used for testnet unless overridden with HELIUM_API_URL
+const TESTNET_API_URL: &str = "https://testnet-api.helium.wtf/v1";
+
+/// Returns the URL of the API for the given network. An API given
+/// with HELIUM_API_URL is taken to be for the network in
+/// HELIUM_API_NETWORK, or mainnet if that is not set, and is refused
+/// for any other network.
+fn api_url(network: Network) -> Result<String> {
+    match env::var("HELIUM_API_URL") {
+        Ok(url) => {
+            let api_network = match env::var("HELIUM_API_NETWORK") {
+                Ok(api_network) => api_network.parse()?,
+                Err(_) => Network::Mainnet,
+            };
+            if api_network != network {
+                return Err(format!("API {} is for {}, not {}", url, api_network, network).into());
+            }
+            Ok(url)
+        }
+        Err(_) => match network {
+            Network::Mainnet => Ok(helium_api::DEFAULT_BASE_URL.to_string()),
+            Network::Testnet => Ok(TESTNET_API_URL.to_string()),
+        },
+    }
+}
+
+/// Returns the network of the given wallet key, checking it against
+/// the network given in the options, if any
+fn wallet_network(pubkey_bin: &PubKeyBin, network: Option<Network>) -> Result<Network> {
+    let wallet_network = pubkey_bin.network()?;
+    match network {
+        Some(network) if network != wallet_network => {
+            Err(format!("Wallet is for {}, not {}", wallet_network, network).into())
+        }
+        _ => Ok(wallet_network),
+    }
+}
+
+/// Returns the network of a transaction from the key of its signer or
+/// payer, checking it against the network given in the options, if
+/// any. The network in the options, or mainnet, is used for
+/// transactions without a key.
+fn txn_network(key: &[u8], network: Option<Network>) -> Result<Network> {
+    if key.is_empty() {
+        return Ok(network.unwrap_or_default());
+    }
+    if key.len() != PubKeyBin::default().0.len() {
+        return Err("Invalid key in transaction".into());
+    }
+    let txn_network = PubKeyBin::from_vec(key).network()?;
+    match network {
+        Some(network) if network != txn_network => {
+            Err(format!("Transaction is for {}, not {}", txn_network, network).into())
+        }
+        _ => Ok(txn_network),
+    }
+}
+
+/// Checks that a payee is on the same network as the paying wallet
+/// unless cross network transactions are explicitly allowed
+fn check_payee_network(network: Network, payee: &PubKeyBin, allow_cross_network: bool) -> Result {
+    let payee_network = payee.network()?;
+    if payee_network != network && !allow_cross_network {
+        return Err(format!(
+            "Payee {} is on {}, not {}. Use --allow-cross-network to override",
+            payee, payee_network, network
+        )
+        .into());
+    }
+    Ok(())
 }
 
 fn collect_addresses(files: Vec<PathBuf>, mut addresses: Vec<String>) -> Result<Vec<String>> {
     // Any given addresses override _all_ the file parameters
     if addresses.is_empty() {
         for file in files {
-            let mut reader = fs::File::open(&file)?;
-            let enc_wallet = Wallet::read(&mut reader)?;
-            addresses.push(enc_wallet.address()?);
+            let data = fs::read(&file)?;
+            let pubkey_bin = if SealedWallet::is_sealed(&data) {
+                SealedWallet::read(&mut &data[..])?.pubkey_bin
+            } else {
+                Wallet::read(&mut &data[..])?.pubkey_bin
+            };
+            addresses.push(pubkey_bin.to_b58()?);
         }
     }
     Ok(addresses)
 }
 
-fn get_seed_words() -> Result<Vec<String>> {
+fn get_seed_words(seed_type: SeedType, language: Option<Language>) -> Result<Vec<String>> {
     use dialoguer::Input;
     let split_str = |s: String| s.split_whitespace().map(|w| w.to_string()).collect();
     let word_string = Input::<String>::new()
         .with_prompt("Seed Words")
         .validate_with(move |v: &str| {
-            let word_list = split_str(v.to_string());
-            match mnemonic::mnemonic_to_entropy(word_list) {
+            let word_list: Vec<String> = split_str(v.to_string());
+            match mnemonic::mnemonic_to_seed(word_list, seed_type, language, "") {
                 Ok(_) => Ok(()),
                 Err(err) => Err(err),
             }
@@ -123,6 +468,88 @@ fn get_seed_words() -> Result<Vec<String>> {
         .collect())
 }
 
+fn get_seed_passphrase() -> std::io::Result<Secret<Vec<u8>>> {
+    use dialoguer::Password;
+    let passphrase = Password::new()
+        .with_prompt("Seed Passphrase")
+        .with_confirmation("Confirm passphrase", "Passphrases do not match")
+        .allow_empty_password(true)
+        .interact()?;
+    Ok(Secret::new(passphrase.into_bytes()))
+}
+
+/// The number of generated seed words the user is asked to repeat
+const SEED_CONFIRM_WORDS: usize = 3;
+
+/// The number of BIP39 seed words generated unless another number is
+/// given
+const BIP39_DEFAULT_WORDS: usize = 24;
+
+fn gen_seed_words(
+    seed_type: SeedType,
+    language: Language,
+    word_count: Option<usize>,
+) -> Result<Vec<String>> {
+    use dialoguer::{Confirm, Input};
+    let words = match seed_type {
+        SeedType::Mobile if language != Language::English => {
+            return Err("Mobile seed words are only supported in english".into())
+        }
+        SeedType::Mobile if word_count.unwrap_or(12) != 12 => {
+            return Err("Mobile seed words are always 12 words".into())
+        }
+        SeedType::Mobile => {
+            let mut entropy = Secret::<[u8; 16]>::zeroed();
+            randombytes::randombytes_into(&mut *entropy);
+            mnemonic::entropy_to_mnemonic(&entropy)
+        }
+        SeedType::Bip39 => {
+            // Every 3 words encode 4 bytes of entropy
+            let mut entropy =
+                Secret::new(vec![0u8; word_count.unwrap_or(BIP39_DEFAULT_WORDS) / 3 * 4]);
+            randombytes::randombytes_into(&mut entropy);
+            mnemonic::entropy_to_bip39_mnemonic(&entropy, language)?
+        }
+    };
+
+    let term = console::Term::stderr();
+    term.write_line("Seed words, write these down to recover the wallet:")?;
+    for (row, row_words) in words.chunks(4).enumerate() {
+        let line: Vec<String> = row_words
+            .iter()
+            .enumerate()
+            .map(|(i, word)| format!("{:>2}. {:<10}", row * 4 + i + 1, word))
+            .collect();
+        term.write_line(&line.join(" "))?;
+    }
+    let confirmed = Confirm::new()
+        .with_prompt("Have you written down the seed words?")
+        .interact()?;
+    // Remove the seed words from the screen
+    term.clear_last_lines(words.len() / 4 + 2)?;
+    if !confirmed {
+        return Err("Seed words not written down".into());
+    }
+
+    let mut positions = Vec::with_capacity(SEED_CONFIRM_WORDS);
+    while positions.len() < SEED_CONFIRM_WORDS {
+        let position = randombytes::randombytes_uniform(words.len() as u32) as usize;
+        if !positions.contains(&position) {
+            positions.push(position);
+        }
+    }
+    positions.sort_unstable();
+    for position in positions {
+        let word = Input::<String>::new()
+            .with_prompt(format!("Seed word #{}", position + 1))
+            .interact()?;
+        if mnemonic::normalize_word(word.trim()) != words[position] {
+            return Err(format!("Seed word #{} does not match", position + 1).into());
+        }
+    }
+    Ok(words)
+}
+
 pub fn get_payer(staking_address: PubKeyBin, payer: &Option<String>) -> Result<Option<PubKeyBin>> {
     match payer {
         Some(s) if s == "staking" => Ok(Some(staking_address)),
@@ -149,14 +576,199 @@ pub fn get_txn_fees(client: &Client) -> Result<TxnFeeConfig> {
     }
 }
 
+/// Opens a file to write output to. An existing file is truncated when
+/// it may be overwritten, so that no trailing bytes of its old content
+/// are left behind.
 pub fn open_output_file(filename: &PathBuf, create: bool) -> io::Result<fs::File> {
     fs::OpenOptions::new()
         .write(true)
         .create(true)
+        .truncate(true)
         .create_new(create)
         .open(filename)
 }
 
+/// Opens a file for the secret that is only readable by the current
+/// user
+pub fn open_secret_file(filename: &PathBuf, create: bool) -> io::Result<fs::File> {
+    let mut options = fs::OpenOptions::new();
+    options
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .create_new(create);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    options.open(filename)
+}
+
+/// Writes the given wallet to a temporary file next to the given
+/// filename and then renames it into place. A failure part way
+/// through leaves any existing file at `filename` untouched. The
+/// temporary file, and with it the wallet, is only readable by the
+/// current user.
+pub fn write_wallet_atomic(wallet: &Wallet, filename: &PathBuf) -> Result {
+    let tmp_filename = tmp_filename(filename);
+    if let Err(err) = write_synced(&tmp_filename, |writer| wallet.write(writer)) {
+        let _ = fs::remove_file(&tmp_filename);
+        return Err(err);
+    }
+    fs::rename(&tmp_filename, filename)?;
+    sync_dir(filename)
+}
+
+/// Returns a unique name for a temporary file next to the given file,
+/// so that a temporary file left behind by an earlier run never gets
+/// in the way
+fn tmp_filename(filename: &Path) -> PathBuf {
+    let mut tmp_filename = filename.as_os_str().to_owned();
+    tmp_filename.push(format!(
+        ".{}-{}.tmp",
+        std::process::id(),
+        hex::encode(sodiumoxide::randombytes::randombytes(8))
+    ));
+    PathBuf::from(tmp_filename)
+}
+
+fn write_synced(filename: &PathBuf, write: impl FnOnce(&mut dyn io::Write) -> Result) -> Result {
+    let mut writer = open_secret_file(filename, true)?;
+    write(&mut writer)?;
+    writer.sync_all()?;
+    Ok(())
+}
+
+/// Syncs the directory of the given file so that a file renamed into
+/// it survives a crash
+#[cfg_attr(not(unix), allow(unused_variables))]
+fn sync_dir(filename: &Path) -> Result {
+    #[cfg(unix)]
+    {
+        let dir = match filename.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+        fs::File::open(dir)?.sync_all()?;
+    }
+    Ok(())
+}
+
+/// Returns the public keys of the custodians to seal shards to. Either
+/// no custodians or one custodian per shard is expected.
+pub fn get_custodian_keys(
+    custodians: &[PubKeyBin],
+    key_share_count: u8,
+) -> Result<Vec<Option<PublicKey>>> {
+    if !custodians.is_empty() && custodians.len() != key_share_count as usize {
+        return Err(format!(
+            "{} custodians given for {} shards, one custodian per shard is expected",
+            custodians.len(),
+            key_share_count
+        )
+        .into());
+    }
+    custodians
+        .iter()
+        .map(|custodian| Ok(Some(custodian_key(custodian)?)))
+        .collect()
+}
+
+/// Returns the public keys of the custodians to seal a new set of
+/// shards to, so that every new shard is sealed like the given shard
+/// of the current set with the same number. When any given shard is
+/// sealed, the sealing of every new shard has to be known.
+pub fn current_custodian_keys(
+    custodians: &ShardCustodians,
+    key_share_count: u8,
+) -> Result<Vec<Option<PublicKey>>> {
+    if custodians.values().all(Option::is_none) {
+        return Ok(vec![]);
+    }
+    (1..=key_share_count)
+        .map(|number| match custodians.get(&number) {
+            Some(Some(custodian)) => Ok(Some(custodian_key(custodian)?)),
+            Some(None) => Ok(None),
+            None => Err(format!(
+                "Shard {} is not given, but sealed shards are. All shards are needed to keep them sealed to their custodians",
+                number
+            )
+            .into()),
+        })
+        .collect()
+}
+
+fn custodian_key(custodian: &PubKeyBin) -> Result<PublicKey> {
+    let public_key = PublicKey::try_from(*custodian)?;
+    // Sealing needs the custodian key as a curve25519 key
+    public_key
+        .to_curve25519()
+        .map_err(|_| format!("Custodian {} is not an ed25519 address", custodian))?;
+    Ok(public_key)
+}
+
+/// Writes the shards of a sharded wallet to files named after the
+/// output file with the shard number added to the extension, and
+/// returns the file names. Shard N is sealed to custodian N if there
+/// is one. Every shard is written to a temporary file first
+/// and the shards are only renamed into place once all of them are
+/// written, so a failure while writing leaves any existing set of
+/// shard files untouched. The renames replace existing shard files one
+/// file at a time, and shard files numbered above the number of shards
+/// are left in place, see `stale_shards`.
+pub fn write_shards(
+    wallet: &Wallet,
+    output: &PathBuf,
+    force: bool,
+    custodians: &[Option<PublicKey>],
+) -> Result<Vec<PathBuf>> {
+    let extension = get_file_extension(output);
+    let shards = wallet.shards()?;
+    let mut filenames = vec![];
+    for i in 0..shards.len() {
+        let mut filename = output.clone();
+        let share_extension = format!("{}.{}", extension, (i + 1).to_string());
+        filename.set_extension(share_extension);
+        if !force && filename.exists() {
+            return Err(format!("{}: file exists", filename.display()).into());
+        }
+        filenames.push(filename);
+    }
+
+    let mut tmp_filenames = vec![];
+    for (i, (shard, filename)) in shards.iter().zip(&filenames).enumerate() {
+        let tmp_filename = tmp_filename(filename);
+        let result = write_synced(&tmp_filename, |writer| match custodians.get(i) {
+            Some(Some(custodian)) => SealedWallet::seal(shard, custodian)?.write(writer),
+            _ => shard.write(writer),
+        });
+        tmp_filenames.push(tmp_filename);
+        if let Err(err) = result {
+            for tmp_filename in &tmp_filenames {
+                let _ = fs::remove_file(tmp_filename);
+            }
+            return Err(err);
+        }
+    }
+    for (tmp_filename, filename) in tmp_filenames.iter().zip(&filenames) {
+        fs::rename(tmp_filename, filename)?;
+    }
+    sync_dir(output)?;
+    Ok(filenames)
+}
+
+/// Returns the shard files named after the output file that are
+/// numbered above the given number of shards. These are left over from
+/// an earlier set of shards with more shards.
+pub fn stale_shards(output: &PathBuf, shard_count: usize) -> Vec<PathBuf> {
+    let extension = get_file_extension(output);
+    (shard_count + 1..=u8::MAX as usize)
+        .map(|i| output.with_extension(format!("{}.{}", extension, i)))
+        .filter(|filename| filename.exists())
+        .collect()
+}
+
 pub fn get_file_extension(filename: &PathBuf) -> String {
     use std::ffi::OsStr;
     filename
@@ -174,6 +786,20 @@ pub fn print_footer(status: &Option<PendingTxnStatus>) -> Result {
     Ok(())
 }
 
+/// Reads the input of a command from the given argument, the given
+/// file, or stdin if neither is given
+pub fn read_input(argument: &Option<String>, input: &Option<PathBuf>) -> Result<Vec<u8>> {
+    match (argument, input) {
+        (Some(argument), _) => Ok(argument.as_bytes().to_vec()),
+        (None, Some(path)) => Ok(fs::read(path)?),
+        (None, None) => {
+            let mut buffer = vec![];
+            io::stdin().read_to_end(&mut buffer)?;
+            Ok(buffer)
+        }
+    }
+}
+
 pub fn print_json<T: ?Sized + serde::Serialize>(value: &T) -> Result {
     println!("{}", serde_json::to_string_pretty(value)?);
     Ok(())
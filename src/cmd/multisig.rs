@@ -1,7 +1,7 @@
 use crate::{
-    cmd::{api_url, get_password, load_wallet, print_json, status_json, Opts},
-    keypair::Keypair,
+    cmd::{api_url, load_signer, print_json, status_json, txn_network, Opts},
     result::Result,
+    signer::Signer,
     traits::{Sign, ToJson, B64},
 };
 use helium_api::{BlockchainTxn, Client, PendingTxnStatus, Txn};
@@ -86,9 +86,7 @@ impl Inspect {
 
 impl Prove {
     pub fn run(&self, opts: Opts) -> Result {
-        let password = get_password(false)?;
-        let wallet = load_wallet(opts.files)?;
-        let keypair = wallet.decrypt(password.as_bytes())?;
+        let signer = load_signer(opts.files, &opts.keyfile)?;
 
         let txn = Artifact::load_txn(&self.artifact)?;
         let mut proofs = Proofs::new();
@@ -97,13 +95,13 @@ impl Prove {
         } else {
             ProofType::Proof
         };
-        proofs.add_proof(&keypair, &txn, proof_type)?;
+        proofs.add_proof(signer.as_ref(), &txn, proof_type)?;
         print_json(&proofs)
     }
 }
 
 impl Combine {
-    pub fn run(&self, _opts: Opts) -> Result {
+    pub fn run(&self, opts: Opts) -> Result {
         let mut envelope = Artifact::load_txn(&self.artifact)?;
         // Load proofs and key_proof maps from txn
         let mut combined_proofs = Proofs::from_txn(&envelope)?;
@@ -113,7 +111,8 @@ impl Combine {
         }
         combined_proofs.apply(&mut envelope)?;
         let status = if self.commit {
-            let client = Client::new_with_base_url(api_url());
+            let network = txn_network(&vars_key(&envelope)?, opts.network)?;
+            let client = Client::new_with_base_url(api_url(network)?);
             Some(client.submit_txn(&envelope)?)
         } else {
             None
@@ -122,6 +121,17 @@ impl Combine {
     }
 }
 
+/// Returns the key that signs a vars transaction, which is the master
+/// key or the first of the multi keys. Vars transactions that do not
+/// change keys have no key.
+fn vars_key(envelope: &BlockchainTxn) -> Result<Vec<u8>> {
+    match &envelope.txn {
+        Some(Txn::Vars(t)) if !t.master_key.is_empty() => Ok(t.master_key.clone()),
+        Some(Txn::Vars(t)) => Ok(t.multi_keys.first().cloned().unwrap_or_default()),
+        _ => Err("Unsupported transaction for multisig".into()),
+    }
+}
+
 fn print_txn(envelope: &BlockchainTxn, status: &Option<PendingTxnStatus>) -> Result {
     let mut json = match &envelope.txn {
         Some(Txn::Vars(t)) => t.to_json()?,
@@ -182,13 +192,13 @@ impl Proofs {
 
     fn add_proof(
         &mut self,
-        keypair: &Keypair,
+        signer: &dyn Signer,
         envelope: &BlockchainTxn,
         proof_type: ProofType,
     ) -> Result {
         match &envelope.txn {
             Some(Txn::Vars(t)) => {
-                let signature = t.sign(&keypair)?.to_b64()?;
+                let signature = t.sign(signer)?.to_b64()?;
                 match proof_type {
                     ProofType::KeyProof => self.key_proofs.push(signature),
                     ProofType::Proof => self.proofs.push(signature),
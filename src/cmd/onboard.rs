@@ -1,6 +1,7 @@
 use crate::{
     cmd::{
-        api_url, get_password, get_payer, load_wallet, print_json, status_json, Opts, OutputFormat,
+        api_url, get_payer, load_signer, print_json, status_json, wallet_network, Opts,
+        OutputFormat,
     },
     result::Result,
     staking,
@@ -33,28 +34,27 @@ pub struct Cmd {
 
 impl Cmd {
     pub fn run(&self, opts: Opts) -> Result {
-        let password = get_password(false)?;
-        let wallet = load_wallet(opts.files)?;
-        let keypair = wallet.decrypt(password.as_bytes())?;
+        let signer = load_signer(opts.files, &opts.keyfile)?;
         // let staking_address = get_staking_address()?;
         // Now decode the given transaction
         let mut envelope = BlockchainTxn::from_b64(&self.read_txn()?)?;
 
         match &mut envelope.txn {
             Some(Txn::AddGateway(t)) => {
-                t.owner_signature = t.sign(&keypair)?;
+                t.owner_signature = t.sign(signer.as_ref())?;
             }
             Some(Txn::AssertLocation(t)) => {
-                t.owner_signature = t.sign(&keypair)?;
+                t.owner_signature = t.sign(signer.as_ref())?;
             }
             _ => return Err("Unsupported transaction for onboarding".into()),
         }
 
         // Check staking address
         let staking_client = staking::Client::default();
-        let api_client = helium_api::Client::new_with_base_url(api_url());
+        let network = wallet_network(&signer.pubkey_bin(), opts.network)?;
+        let api_client = helium_api::Client::new_with_base_url(api_url(network)?);
 
-        let wallet_key = keypair.pubkey_bin();
+        let wallet_key = signer.pubkey_bin();
         let staking_key = staking_client.address()?;
 
         let payer = get_payer(staking_key, &envelope.payer()?.map(|k| k.to_string()))?;
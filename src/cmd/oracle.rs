@@ -1,6 +1,6 @@
 use crate::{
     cmd::{
-        api_url, get_password, load_wallet, print_footer, print_json, status_json, status_str,
+        api_url, load_signer, print_footer, print_json, status_json, status_str, wallet_network,
         Opts, OutputFormat,
     },
     result::Result,
@@ -48,19 +48,18 @@ impl Cmd {
 
 impl Report {
     pub fn run(&self, opts: Opts) -> Result {
-        let password = get_password(false)?;
-        let wallet = load_wallet(opts.files)?;
-        let keypair = wallet.decrypt(password.as_bytes())?;
+        let signer = load_signer(opts.files, &opts.keyfile)?;
 
-        let client = Client::new_with_base_url(api_url());
+        let network = wallet_network(&signer.pubkey_bin(), opts.network)?;
+        let client = Client::new_with_base_url(api_url(network)?);
 
         let mut txn = BlockchainTxnPriceOracleV1 {
-            public_key: keypair.pubkey_bin().into(),
+            public_key: signer.pubkey_bin().into(),
             price: self.price.to_millis(),
-            block_height: self.block.to_block(),
+            block_height: self.block.to_block(&client)?,
             signature: Vec::new(),
         };
-        txn.signature = txn.sign(&keypair)?;
+        txn.signature = txn.sign(signer.as_ref())?;
         let envelope = txn.in_envelope();
         let status = if self.commit {
             Some(client.submit_txn(&envelope)?)
@@ -103,25 +102,30 @@ fn print_txn(
 }
 
 #[derive(Clone, Copy, Debug, Serialize)]
-struct Block(u64);
+enum Block {
+    Auto,
+    Height(u64),
+}
 
 impl FromStr for Block {
     type Err = Box<dyn std::error::Error>;
 
     fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
         match s {
-            "auto" => {
-                let client = Client::new_with_base_url(api_url());
-                Ok(Block(client.get_height()?))
-            }
-            _ => Ok(Block(s.parse()?)),
+            "auto" => Ok(Block::Auto),
+            _ => Ok(Block::Height(s.parse()?)),
         }
     }
 }
 
 impl Block {
-    fn to_block(self) -> u64 {
-        self.0
+    /// Returns the block height, looking up the latest known height
+    /// with the given client for "auto"
+    fn to_block(self, client: &Client) -> Result<u64> {
+        match self {
+            Block::Auto => Ok(client.get_height()?),
+            Block::Height(height) => Ok(height),
+        }
     }
 }
 
@@ -1,7 +1,7 @@
 use crate::{
     cmd::{
-        api_url, get_password, get_payer, get_txn_fees, load_wallet, print_footer, print_json,
-        status_json, status_str, Opts, OutputFormat,
+        api_url, get_payer, get_txn_fees, load_signer, print_footer, print_json, status_json,
+        status_str, txn_network, wallet_network, Opts, OutputFormat,
     },
     keypair::PubKeyBin,
     result::Result,
@@ -78,15 +78,14 @@ impl Cmd {
 
 impl Create {
     pub fn run(&self, opts: Opts) -> Result {
-        let password = get_password(false)?;
-        let wallet = load_wallet(opts.files)?;
-        let keypair = wallet.decrypt(password.as_bytes())?;
+        let signer = load_signer(opts.files, &opts.keyfile)?;
 
-        let api_client = Client::new_with_base_url(api_url());
+        let network = wallet_network(&signer.pubkey_bin(), opts.network)?;
+        let api_client = Client::new_with_base_url(api_url(network)?);
         let staking_client = staking::Client::default();
 
         let staking_key = staking_client.address()?;
-        let wallet_key = keypair.pubkey_bin();
+        let wallet_key = signer.pubkey_bin();
 
         let payer = get_payer(staking_key, &self.payer)?;
 
@@ -97,7 +96,7 @@ impl Create {
                 .into_iter()
                 .map(|s| s.to_vec())
                 .collect(),
-            owner: keypair.pubkey_bin().into(),
+            owner: signer.pubkey_bin().into(),
             payer: payer.map_or(vec![], |v| v.to_vec()),
             oui: api_client.get_last_oui()?,
             fee: 0,
@@ -109,7 +108,7 @@ impl Create {
         };
         txn.fee = txn.txn_fee(&get_txn_fees(&api_client)?)?;
         txn.staking_fee = txn.txn_staking_fee(&get_txn_fees(&api_client)?)?;
-        txn.owner_signature = txn.sign(&keypair)?;
+        txn.owner_signature = txn.sign(signer.as_ref())?;
         let envelope = txn.in_envelope();
 
         match payer {
@@ -135,7 +134,8 @@ impl Submit {
     pub fn run(&self, opts: Opts) -> Result {
         let envelope = BlockchainTxn::from_b64(&self.transaction)?;
         if let Some(Txn::Oui(t)) = envelope.txn.clone() {
-            let api_client = helium_api::Client::new_with_base_url(api_url());
+            let network = txn_network(&t.owner, opts.network)?;
+            let api_client = helium_api::Client::new_with_base_url(api_url(network)?);
             let status = if self.commit {
                 Some(api_client.submit_txn(&envelope)?)
             } else {
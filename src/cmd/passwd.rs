@@ -0,0 +1,68 @@
+use crate::{
+    cmd::{
+        current_custodian_keys, get_new_password, get_wallet_secret, load_wallet_custodians,
+        verify, write_shards, write_wallet_atomic, Opts,
+    },
+    result::Result,
+    wallet::{Metadata, Wallet},
+};
+use std::path::PathBuf;
+use structopt::StructOpt;
+
+#[derive(Debug, StructOpt)]
+/// Change the password of a wallet without changing its key. The
+/// wallet is decrypted with the current password and re-encrypted
+/// with a new password in the same format. Sharded wallets are
+/// written out as a complete new set of shards with the same number
+/// of shards and required shards, each sealed to the same custodian as
+/// the shard it replaces. The current shard files are only replaced
+/// once every new shard is written.
+pub struct Cmd {
+    #[structopt(short, long)]
+    /// Output file to store the key in. Defaults to the given wallet
+    /// file for a basic wallet, and to the given shard file name
+    /// without its shard extension for a sharded wallet.
+    output: Option<PathBuf>,
+}
+
+impl Cmd {
+    pub fn run(&self, opts: Opts) -> Result {
+        let output = match &self.output {
+            Some(output) => output.clone(),
+            None => opts.files[0].clone(),
+        };
+        let (wallet, custodians) = load_wallet_custodians(opts.files)?;
+        if wallet.slots().is_ok() {
+            return Err("Use slot add and slot remove to change the passwords of a wallet with unlock slots".into());
+        }
+        let secret = get_wallet_secret(&wallet, &opts.keyfile)?;
+        let keypair = secret.decrypt(&wallet)?;
+
+        // The new wallet needs the same keyfile as the current one
+        let new_password = get_new_password()?;
+        let metadata = wallet
+            .metadata
+            .clone()
+            .unwrap_or_else(|| Metadata::new(keypair.network(), None));
+        let new_wallet = Wallet::encrypt_with_metadata(
+            &keypair,
+            &new_password,
+            secret.keyfile(),
+            wallet.format.regenerate()?,
+            metadata,
+        )?;
+
+        if new_wallet.is_sharded() {
+            let output = if self.output.is_some() {
+                output
+            } else {
+                output.with_extension("")
+            };
+            let custodians = current_custodian_keys(&custodians, new_wallet.shards()?.len() as u8)?;
+            write_shards(&new_wallet, &output, true, &custodians)?;
+        } else {
+            write_wallet_atomic(&new_wallet, &output)?;
+        }
+        verify::print_result(&new_wallet, true, opts.format)
+    }
+}
@@ -1,7 +1,7 @@
 use crate::{
     cmd::{
-        api_url, get_password, get_txn_fees, load_wallet, print_footer, print_json, print_table,
-        status_json, status_str, Opts, OutputFormat,
+        api_url, check_payee_network, get_txn_fees, load_signer, print_footer, print_json,
+        print_table, status_json, status_str, wallet_network, Opts, OutputFormat,
     },
     keypair::PubKeyBin,
     result::Result,
@@ -29,32 +29,38 @@ pub struct Cmd {
     /// Commit the payment to the API
     #[structopt(long)]
     commit: bool,
+
+    /// Allow payees on a different network than the wallet
+    #[structopt(long)]
+    allow_cross_network: bool,
 }
 
 impl Cmd {
     pub fn run(&self, opts: Opts) -> Result {
-        let password = get_password(false)?;
-        let wallet = load_wallet(opts.files)?;
-
-        let client = Client::new_with_base_url(api_url());
-
-        let keypair = wallet.decrypt(password.as_bytes())?;
-        let account = client.get_account(&keypair.public.to_b58()?)?;
+        let signer = load_signer(opts.files, &opts.keyfile)?;
 
+        let network = wallet_network(&signer.pubkey_bin(), opts.network)?;
+        let client = Client::new_with_base_url(api_url(network)?);
         let payments: Result<Vec<Payment>> = self
             .payees
             .iter()
             .map(|p| {
+                let payee = PubKeyBin::from_b58(&p.address)?;
+                check_payee_network(network, &payee, self.allow_cross_network)?;
                 Ok(Payment {
-                    payee: PubKeyBin::from_b58(&p.address)?.into(),
+                    payee: payee.into(),
                     amount: p.amount.to_bones(),
                 })
             })
             .collect();
+        let payments = payments?;
+
+        let account = client.get_account(&signer.pubkey_bin().to_b58()?)?;
+
         let mut txn = BlockchainTxnPaymentV2 {
             fee: 0,
-            payments: payments?,
-            payer: keypair.pubkey_bin().into(),
+            payments,
+            payer: signer.pubkey_bin().into(),
             nonce: account.speculative_nonce + 1,
             signature: Vec::new(),
         };
@@ -64,7 +70,7 @@ impl Cmd {
         } else {
             txn.txn_fee(&get_txn_fees(&client)?)?
         };
-        txn.signature = txn.sign(&keypair)?;
+        txn.signature = txn.sign(signer.as_ref())?;
         let envelope = txn.in_envelope();
         let status = if self.commit {
             Some(client.submit_txn(&envelope)?)
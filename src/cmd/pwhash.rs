@@ -0,0 +1,171 @@
+use crate::{
+    cmd::{print_json, print_table, Opts, OutputFormat},
+    pwhash::{Argon2id13, PWHash, MIB, PBKDF2_DEFAULT_ITERATIONS},
+    result::Result,
+};
+use prettytable::{format, Table};
+use serde_json::{json, Value};
+use std::time::Duration;
+use structopt::{clap::arg_enum, StructOpt};
+
+arg_enum! {
+    #[derive(Debug, Clone, Copy)]
+    pub enum PWHashKind {
+        Argon2id13,
+        Pbkdf2,
+    }
+}
+
+#[derive(Debug, StructOpt)]
+/// Password hashing commands
+pub enum Cmd {
+    Calibrate(Calibrate),
+}
+
+#[derive(Debug, StructOpt)]
+/// Benchmark password hashing on this machine and suggest parameters
+/// that unlock a wallet in about the given time. The suggested options
+/// can be given to create and upgrade.
+pub struct Calibrate {
+    #[structopt(long,
+                possible_values = &["argon2id13", "pbkdf2"],
+                case_insensitive = true,
+                default_value = "argon2id13")]
+    /// The password hash to calibrate
+    pwhash: PWHashKind,
+
+    #[structopt(long, default_value = "1")]
+    /// Target unlock time in seconds
+    time: f64,
+
+    #[structopt(long, default_value = "1024")]
+    /// Largest Argon2id13 memory limit in MiB to suggest. The memory
+    /// limit is halved until a single pass fits in the target time
+    mem_limit: usize,
+}
+
+#[derive(Debug, StructOpt)]
+// Options for the password hash of a new wallet. Not a doc comment,
+// since structopt would show it as the about text of every command
+// that flattens these options in.
+pub struct PWHashOpts {
+    #[structopt(long,
+                possible_values = &["argon2id13", "pbkdf2"],
+                case_insensitive = true,
+                default_value = "argon2id13")]
+    /// The password hash to derive the wallet key with. Use
+    /// "pwhash calibrate" to find parameters for this machine
+    pwhash: PWHashKind,
+
+    #[structopt(long)]
+    /// Number of Argon2id13 passes. Defaults to 4
+    ops_limit: Option<usize>,
+
+    #[structopt(long)]
+    /// Argon2id13 memory limit in MiB. Defaults to 1024
+    mem_limit: Option<usize>,
+
+    #[structopt(long)]
+    /// Number of PBKDF2 iterations. Defaults to 1000000
+    iterations: Option<u32>,
+}
+
+impl PWHashOpts {
+    pub fn pwhash(&self) -> Result<PWHash> {
+        match self.pwhash {
+            PWHashKind::Argon2id13 => {
+                if self.iterations.is_some() {
+                    return Err("--iterations is only for pbkdf2".into());
+                }
+                let default = Argon2id13::default();
+                PWHash::argon2id13(
+                    self.ops_limit.unwrap_or_else(|| default.ops_limit()),
+                    self.mem_limit
+                        .map(|mem_limit| mem_limit.saturating_mul(MIB))
+                        .unwrap_or_else(|| default.mem_limit()),
+                )
+            }
+            PWHashKind::Pbkdf2 => {
+                if self.ops_limit.is_some() || self.mem_limit.is_some() {
+                    return Err("--ops-limit and --mem-limit are only for argon2id13".into());
+                }
+                match self.iterations.unwrap_or(PBKDF2_DEFAULT_ITERATIONS) {
+                    0 => Err("PBKDF2 needs at least one iteration".into()),
+                    iterations => Ok(PWHash::pbkdf2(iterations)),
+                }
+            }
+        }
+    }
+}
+
+impl Cmd {
+    pub fn run(&self, opts: Opts) -> Result {
+        match self {
+            Cmd::Calibrate(cmd) => cmd.run(opts),
+        }
+    }
+}
+
+impl Calibrate {
+    pub fn run(&self, opts: Opts) -> Result {
+        if !self.time.is_finite() || self.time <= 0.0 {
+            return Err("Target time must be a number of seconds above 0".into());
+        }
+        let target = Duration::from_secs_f64(self.time);
+        let pwhash = match self.pwhash {
+            PWHashKind::Argon2id13 => {
+                PWHash::calibrate_argon2id13(target, self.mem_limit.saturating_mul(MIB))?
+            }
+            PWHashKind::Pbkdf2 => PWHash::calibrate_pbkdf2(target)?,
+        };
+        // Time the suggested parameters, since the estimate is not exact
+        let time = pwhash.time()?;
+        let options = pwhash_options(&pwhash);
+        match opts.format {
+            OutputFormat::Table => {
+                let mut table = Table::new();
+                table.set_format(*format::consts::FORMAT_NO_LINESEP_WITH_TITLE);
+                table.set_titles(row!["PWHash", "Params", "Time", "Options"]);
+                table.add_row(row![
+                    pwhash,
+                    pwhash.params(),
+                    format!("{:.2}s", time.as_secs_f64()),
+                    options
+                ]);
+                print_table(&table)
+            }
+            OutputFormat::Json => print_json(&json!({
+                "pwhash": pwhash.to_string(),
+                "params": pwhash_params(&pwhash),
+                "time": time.as_secs_f64(),
+                "options": options,
+            })),
+        }
+    }
+}
+
+/// Returns the parameters of a password hash as JSON, in the units of
+/// the create and upgrade options
+pub fn pwhash_params(pwhash: &PWHash) -> Value {
+    match pwhash {
+        PWHash::PBKDF2(hasher) => json!({
+            "iterations": hasher.iterations(),
+        }),
+        PWHash::Argon2id13(hasher) => json!({
+            "ops_limit": hasher.ops_limit(),
+            "mem_limit": hasher.mem_limit() / MIB,
+        }),
+    }
+}
+
+/// Returns the create and upgrade options for a password hash
+fn pwhash_options(pwhash: &PWHash) -> String {
+    match pwhash {
+        PWHash::PBKDF2(hasher) => format!("--pwhash pbkdf2 --iterations {}", hasher.iterations()),
+        PWHash::Argon2id13(hasher) => format!(
+            "--pwhash argon2id13 --ops-limit {} --mem-limit {}",
+            hasher.ops_limit(),
+            hasher.mem_limit() / MIB
+        ),
+    }
+}
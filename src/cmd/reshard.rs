@@ -0,0 +1,165 @@
+use crate::{
+    cmd::{
+        current_custodian_keys, get_custodian_keys, get_wallet_secret, load_wallet_custodians,
+        print_json, print_table, stale_shards, write_shards, Opts, OutputFormat,
+    },
+    format::Format,
+    keypair::PubKeyBin,
+    result::Result,
+    wallet::{Metadata, Wallet},
+};
+use prettytable::{format, Table};
+use serde_json::json;
+use std::path::{Path, PathBuf};
+use structopt::StructOpt;
+
+#[derive(Debug, StructOpt)]
+/// Replace the shards of a sharded wallet with a new generation of
+/// shards. At least the required number of current shards must be
+/// given. The new shards can change the number of shards and required
+/// shards, or keep them the same to make lost or compromised shards
+/// unusable. Shards of the new generation can not be combined with
+/// shards of an older generation. New shards are sealed to the given
+/// custodians, or to the custodians of the current shards with the
+/// same number if none are given.
+pub struct Cmd {
+    #[structopt(short, long)]
+    /// Output file to store the new shards in. Defaults to
+    /// wallet-GEN.key in the directory of the first given shard, where
+    /// GEN is the start of the generation tag of the new shards, so
+    /// that the new shards are written next to the current ones instead
+    /// of over them
+    output: Option<PathBuf>,
+
+    #[structopt(long)]
+    /// Overwrite existing files. Existing shard files are replaced one
+    /// file at a time, and shard files numbered above the new number of
+    /// shards are left in place and reported
+    force: bool,
+
+    #[structopt(short = "n", long = "shards")]
+    /// Number of shards to break the key into. Defaults to the
+    /// current number of shards
+    key_share_count: Option<u8>,
+
+    #[structopt(short = "k", long = "required-shards")]
+    /// Number of shards required to recover the key. Defaults to the
+    /// current number of required shards
+    recovery_threshold: Option<u8>,
+
+    #[structopt(long = "custodian", number_of_values(1))]
+    /// Address of a custodian to seal a new shard to. Give one
+    /// custodian per shard, in shard order. A sealed shard can only be
+    /// used with the custodian's wallet
+    custodians: Vec<PubKeyBin>,
+}
+
+impl Cmd {
+    pub fn run(&self, opts: Opts) -> Result {
+        let dir = match opts.files.first().and_then(|file| file.parent()) {
+            Some(dir) => dir.to_path_buf(),
+            None => PathBuf::new(),
+        };
+        let (wallet, current_custodians) = load_wallet_custodians(opts.files)?;
+        let (key_share_count, recovery_threshold) = match &wallet.format {
+            Format::Sharded(format) => (
+                self.key_share_count.unwrap_or(format.key_share_count),
+                self.recovery_threshold.unwrap_or(format.recovery_threshold),
+            ),
+            _ => return Err("Wallet not sharded".into()),
+        };
+        if recovery_threshold == 0 || recovery_threshold > key_share_count {
+            return Err("Required shards must be between 1 and the number of shards".into());
+        }
+        let custodians = if self.custodians.is_empty() {
+            current_custodian_keys(&current_custodians, key_share_count)?
+        } else {
+            get_custodian_keys(&self.custodians, key_share_count)?
+        };
+        let secret = get_wallet_secret(&wallet, &opts.keyfile)?;
+        let keypair = secret.decrypt(&wallet)?;
+
+        let format = Format::sharded(
+            key_share_count,
+            recovery_threshold,
+            wallet.pwhash()?.with_new_salt(),
+        );
+        let metadata = wallet
+            .metadata
+            .clone()
+            .unwrap_or_else(|| Metadata::new(keypair.network(), None));
+        let new_wallet = secret.encrypt(&keypair, format, metadata)?;
+
+        let output = match &self.output {
+            Some(output) => output.clone(),
+            None => generation_output(&new_wallet, &dir)?,
+        };
+        let filenames = write_shards(&new_wallet, &output, self.force, &custodians)?;
+        let stale_filenames = stale_shards(&output, filenames.len());
+        print_result(
+            &new_wallet,
+            recovery_threshold,
+            &filenames,
+            &stale_filenames,
+            opts.format,
+        )
+    }
+}
+
+/// Returns the default output file for the given new shards in the
+/// given directory, which is named after their generation
+fn generation_output(wallet: &Wallet, dir: &Path) -> Result<PathBuf> {
+    match &wallet.format {
+        Format::Sharded(sharded) => match &sharded.generation {
+            Some(generation) => {
+                Ok(dir.join(format!("wallet-{}.key", hex::encode(&generation[..4]))))
+            }
+            None => Err("New shards have no generation".into()),
+        },
+        _ => Err("Wallet not sharded".into()),
+    }
+}
+
+fn print_result(
+    wallet: &Wallet,
+    recovery_threshold: u8,
+    filenames: &[PathBuf],
+    stale_filenames: &[PathBuf],
+    format: OutputFormat,
+) -> Result {
+    let address = wallet.address()?;
+    let to_strings = |filenames: &[PathBuf]| -> Vec<String> {
+        filenames
+            .iter()
+            .map(|f| f.to_string_lossy().to_string())
+            .collect()
+    };
+    let filenames = to_strings(filenames);
+    let stale_filenames = to_strings(stale_filenames);
+    match format {
+        OutputFormat::Table => {
+            let mut table = Table::new();
+            table.set_format(*format::consts::FORMAT_NO_LINESEP_WITH_TITLE);
+            table.set_titles(row!["Address", "Required Shards", "New Shard Files"]);
+            table.add_row(row![address, recovery_threshold, filenames.join("\n")]);
+            print_table(&table)?;
+            if !stale_filenames.is_empty() {
+                println!(
+                    "\nOld shard files that were not replaced, and can not be combined \
+                     with the new shards: {}",
+                    stale_filenames.join(", ")
+                );
+            }
+            Ok(())
+        }
+        OutputFormat::Json => {
+            let table = json!({
+                "address": address,
+                "required_shards": recovery_threshold,
+                "shard_files": filenames,
+                "stale_shard_files": stale_filenames,
+            });
+            print_json(&table)
+        }
+    }
+}
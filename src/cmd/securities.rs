@@ -1,7 +1,7 @@
 use crate::{
     cmd::{
-        api_url, get_password, get_txn_fees, load_wallet, print_footer, print_json, status_json,
-        status_str, Opts, OutputFormat,
+        api_url, check_payee_network, get_txn_fees, load_signer, print_footer, print_json,
+        status_json, status_str, wallet_network, Opts, OutputFormat,
     },
     keypair::PubKeyBin,
     result::Result,
@@ -29,6 +29,10 @@ pub struct Transfer {
     /// Commit the transfter to the API
     #[structopt(long)]
     commit: bool,
+
+    /// Allow payees on a different network than the wallet
+    #[structopt(long)]
+    allow_cross_network: bool,
 }
 
 impl Cmd {
@@ -41,24 +45,25 @@ impl Cmd {
 
 impl Transfer {
     pub fn run(&self, opts: Opts) -> Result {
-        let password = get_password(false)?;
-        let wallet = load_wallet(opts.files)?;
+        let signer = load_signer(opts.files, &opts.keyfile)?;
 
-        let client = Client::new_with_base_url(api_url());
+        let network = wallet_network(&signer.pubkey_bin(), opts.network)?;
+        let client = Client::new_with_base_url(api_url(network)?);
+        let payee = PubKeyBin::from_b58(&self.payee)?;
+        check_payee_network(network, &payee, self.allow_cross_network)?;
 
-        let keypair = wallet.decrypt(password.as_bytes())?;
-        let account = client.get_account(&keypair.public.to_b58()?)?;
+        let account = client.get_account(&signer.pubkey_bin().to_b58()?)?;
 
         let mut txn = BlockchainTxnSecurityExchangeV1 {
-            payer: keypair.pubkey_bin().into(),
-            payee: PubKeyBin::from_b58(&self.payee)?.into(),
+            payer: signer.pubkey_bin().into(),
+            payee: payee.into(),
             amount: self.amount.to_bones(),
             nonce: account.speculative_sec_nonce + 1,
             fee: 0,
             signature: vec![],
         };
         txn.fee = txn.txn_fee(&get_txn_fees(&client)?)?;
-        txn.signature = txn.sign(&keypair)?;
+        txn.signature = txn.sign(signer.as_ref())?;
         let envelope = txn.in_envelope();
         let status = if self.commit {
             Some(client.submit_txn(&envelope)?)
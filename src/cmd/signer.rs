@@ -0,0 +1,79 @@
+use crate::{
+    cmd::{load_signer, Opts},
+    result::Result,
+    signer::remote,
+};
+use std::path::PathBuf;
+use structopt::StructOpt;
+
+#[derive(Debug, StructOpt)]
+/// Run a signing service for remote signers
+pub enum Cmd {
+    Serve(Serve),
+}
+
+#[derive(Debug, StructOpt)]
+/// Sign requests from remote signers with the wallet. Requests are read
+/// from a Unix socket that only the current user can connect to, and
+/// the type and summary of each transaction is logged before it is
+/// signed. Requests for raw data that is not a transaction are refused
+/// unless --allow-raw or --confirm is given.
+pub struct Serve {
+    /// Path of the Unix socket to listen on
+    #[structopt(long)]
+    socket: PathBuf,
+
+    /// Ask on the terminal before signing each request
+    #[structopt(long)]
+    confirm: bool,
+
+    /// Sign requests for raw data without asking
+    #[structopt(long)]
+    allow_raw: bool,
+
+    /// Replace an existing socket at the given path
+    #[structopt(long)]
+    force: bool,
+}
+
+impl Cmd {
+    pub fn run(&self, opts: Opts) -> Result {
+        match self {
+            Cmd::Serve(cmd) => cmd.run(opts),
+        }
+    }
+}
+
+impl Serve {
+    pub fn run(&self, opts: Opts) -> Result {
+        let signer = load_signer(opts.files, &opts.keyfile)?;
+        let term = console::Term::stderr();
+        term.write_line(&format!(
+            "Signing for {} on {}",
+            signer.pubkey_bin(),
+            self.socket.display()
+        ))?;
+        remote::serve(
+            &self.socket,
+            self.force,
+            None,
+            signer.as_ref(),
+            |txn_type, summary| {
+                term.write_line(&format!("{}: {}", txn_type, summary))?;
+                if txn_type == remote::RAW_TXN_TYPE && !(self.allow_raw || self.confirm) {
+                    return Err("Raw signing requests need --allow-raw or --confirm".into());
+                }
+                if !self.confirm {
+                    return Ok(true);
+                }
+                let approved = dialoguer::Confirm::new()
+                    .with_prompt("Sign this transaction?")
+                    .interact()?;
+                if !approved {
+                    term.write_line("Refused")?;
+                }
+                Ok(approved)
+            },
+        )
+    }
+}
@@ -0,0 +1,140 @@
+use crate::{
+    cmd::{
+        get_new_password, get_wallet_secret, load_wallet, print_json, print_table,
+        pwhash::PWHashOpts, read_keyfile, write_wallet_atomic, Opts, OutputFormat,
+    },
+    result::Result,
+    wallet::Wallet,
+};
+use prettytable::{format, Table};
+use serde_json::json;
+use std::path::PathBuf;
+use structopt::StructOpt;
+
+#[derive(Debug, StructOpt)]
+/// List, add and remove the unlock slots of a wallet created or
+/// upgraded with slots. Each slot unlocks the wallet with its own
+/// password or keyfile. The slot table is authenticated with a MAC
+/// keyed from the data key, which is updated whenever the slots change.
+/// The encrypted key is left as it is, and the wallet file is updated
+/// in place.
+pub enum Cmd {
+    List(List),
+    Add(Add),
+    Remove(Remove),
+}
+
+#[derive(Debug, StructOpt)]
+/// List the unlock slots of the wallet
+pub struct List {}
+
+#[derive(Debug, StructOpt)]
+/// Add an unlock slot. The wallet is unlocked with the password, or
+/// the keyfile given with the global --keyfile option, of an existing
+/// slot. The new slot is for a new password, or for a keyfile if one
+/// is given, hashed with the given password hash options.
+pub struct Add {
+    /// Name of the new slot
+    #[structopt(name = "NAME")]
+    name: String,
+
+    #[structopt(flatten)]
+    pwhash: PWHashOpts,
+
+    /// Keyfile to unlock the new slot with instead of a password
+    #[structopt(long)]
+    keyfile: Option<PathBuf>,
+}
+
+#[derive(Debug, StructOpt)]
+/// Remove an unlock slot. The password or keyfile of any slot is
+/// needed to remove a slot, and the last slot can not be removed.
+pub struct Remove {
+    /// Name of the slot to remove
+    #[structopt(name = "NAME")]
+    name: String,
+}
+
+impl Cmd {
+    pub fn run(&self, opts: Opts) -> Result {
+        match self {
+            Cmd::List(cmd) => cmd.run(opts),
+            Cmd::Add(cmd) => cmd.run(opts),
+            Cmd::Remove(cmd) => cmd.run(opts),
+        }
+    }
+}
+
+impl List {
+    pub fn run(&self, opts: Opts) -> Result {
+        let wallet = load_wallet(opts.files)?;
+        print_slots(&wallet, opts.format)
+    }
+}
+
+impl Add {
+    pub fn run(&self, opts: Opts) -> Result {
+        let filename = wallet_file(&opts.files)?;
+        let mut wallet = load_wallet(opts.files)?;
+        wallet.slots()?;
+        let secret = get_wallet_secret(&wallet, &opts.keyfile)?;
+        let new_secret = match &self.keyfile {
+            Some(path) => read_keyfile(path)?,
+            None => get_new_password()?,
+        };
+        wallet.add_slot(
+            &secret.password,
+            &self.name,
+            &new_secret,
+            self.pwhash.pwhash()?,
+        )?;
+        write_wallet_atomic(&wallet, &filename)?;
+        print_slots(&wallet, opts.format)
+    }
+}
+
+impl Remove {
+    pub fn run(&self, opts: Opts) -> Result {
+        let filename = wallet_file(&opts.files)?;
+        let mut wallet = load_wallet(opts.files)?;
+        wallet.slots()?;
+        let secret = get_wallet_secret(&wallet, &opts.keyfile)?;
+        wallet.remove_slot(&secret.password, &self.name)?;
+        write_wallet_atomic(&wallet, &filename)?;
+        print_slots(&wallet, opts.format)
+    }
+}
+
+/// Slots are changed in place, which only works for a single wallet
+/// file
+fn wallet_file(files: &[PathBuf]) -> Result<PathBuf> {
+    match files {
+        [file] => Ok(file.clone()),
+        _ => Err("Slots are changed in a single wallet file".into()),
+    }
+}
+
+fn print_slots(wallet: &Wallet, format: OutputFormat) -> Result {
+    let slots = wallet.slots()?;
+    match format {
+        OutputFormat::Table => {
+            let mut table = Table::new();
+            table.set_format(*format::consts::FORMAT_NO_LINESEP_WITH_TITLE);
+            table.set_titles(row!["Slot", "PWHash"]);
+            for slot in slots {
+                table.add_row(row![slot.name, slot.pwhash]);
+            }
+            print_table(&table)
+        }
+        OutputFormat::Json => print_json(&json!({
+            "address": wallet.address()?,
+            "slots": slots
+                .iter()
+                .map(|slot| json!({
+                    "name": slot.name,
+                    "pwhash": slot.pwhash.to_string(),
+                }))
+                .collect::<Vec<_>>(),
+        })),
+    }
+}
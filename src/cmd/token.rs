@@ -0,0 +1,102 @@
+use crate::{
+    cmd::{load_signer, print_json, Opts},
+    result::Result,
+    token,
+};
+use serde_json::json;
+use std::time::Duration;
+use structopt::StructOpt;
+
+#[derive(Debug, StructOpt)]
+/// Issue and verify sign-in tokens. Tokens are EdDSA signed JSON Web
+/// Tokens that prove control of the wallet address to a service.
+pub enum Cmd {
+    Issue(Issue),
+    Verify(Verify),
+}
+
+#[derive(Debug, StructOpt)]
+/// Issue a token for a service, signed by the wallet key
+pub struct Issue {
+    /// The service the token is for
+    #[structopt(long)]
+    audience: String,
+
+    /// Number of seconds the token is valid for
+    #[structopt(long, default_value = "300")]
+    ttl: u64,
+
+    /// The challenge nonce given by the service, if any
+    #[structopt(long)]
+    nonce: Option<String>,
+}
+
+#[derive(Debug, StructOpt)]
+/// Verify that a token is signed by an address for a service and has
+/// not expired
+pub struct Verify {
+    #[structopt(name = "TOKEN")]
+    token: String,
+
+    /// The address the token has to be signed by
+    #[structopt(long)]
+    address: String,
+
+    /// The service the token has to be for
+    #[structopt(long)]
+    audience: String,
+
+    /// The nonce the token has to carry
+    #[structopt(long)]
+    nonce: Option<String>,
+}
+
+impl Cmd {
+    pub fn run(&self, opts: Opts) -> Result {
+        match self {
+            Cmd::Issue(cmd) => cmd.run(opts),
+            Cmd::Verify(cmd) => cmd.run(opts),
+        }
+    }
+}
+
+impl Issue {
+    pub fn run(&self, opts: Opts) -> Result {
+        let signer = load_signer(opts.files, &opts.keyfile)?;
+        let token = token::issue(
+            signer.as_ref(),
+            &self.audience,
+            Duration::from_secs(self.ttl),
+            self.nonce.clone(),
+        )?;
+        let claims = token::decode(&token)?;
+        print_json(&json!({
+            "address": claims.sub,
+            "audience": claims.aud,
+            "expires": claims.exp,
+            "token": token,
+        }))
+    }
+}
+
+impl Verify {
+    pub fn run(&self, _opts: Opts) -> Result {
+        let result = token::verify(
+            &self.token,
+            &self.address,
+            &self.audience,
+            self.nonce.as_deref(),
+        );
+        let json = match result {
+            Ok(claims) => json!({
+                "verify": true,
+                "claims": claims,
+            }),
+            Err(err) => json!({
+                "verify": false,
+                "error": err.to_string(),
+            }),
+        };
+        print_json(&json)
+    }
+}
@@ -1,9 +1,12 @@
 use crate::{
-    cmd::{get_file_extension, get_password, load_wallet, open_output_file, verify, Opts},
+    cmd::{
+        get_custodian_keys, get_wallet_secret, load_wallet, open_output_file, pwhash::PWHashOpts,
+        read_keyfile, verify, write_shards, Opts, WalletSecret,
+    },
     format::{self, Format},
-    pwhash::PWHash,
+    keypair::{Keypair, PubKeyBin},
     result::Result,
-    wallet::Wallet,
+    wallet::{Metadata, Wallet},
 };
 use std::path::PathBuf;
 use structopt::StructOpt;
@@ -11,10 +14,11 @@ use structopt::StructOpt;
 #[derive(Debug, StructOpt)]
 /// Upgrade a wallet to the latest supported version of the given
 /// format. The same password is used to decrypt the old and encrypt
-/// the new wallet.
+/// the new wallet, and the same keyfile unless another one is given.
 pub enum Cmd {
     Basic(Basic),
     Sharded(Sharded),
+    Slotted(Slotted),
 }
 
 #[derive(Debug, StructOpt)]
@@ -27,6 +31,18 @@ pub struct Basic {
     #[structopt(long)]
     /// Overwrite an existing file
     force: bool,
+
+    #[structopt(long)]
+    /// A label to store in the wallet. Defaults to the current label
+    label: Option<String>,
+
+    #[structopt(long)]
+    /// Keyfile the new wallet needs besides the password. Defaults to
+    /// the keyfile of the current wallet
+    keyfile: Option<PathBuf>,
+
+    #[structopt(flatten)]
+    pwhash: PWHashOpts,
 }
 
 #[derive(Debug, StructOpt)]
@@ -47,6 +63,48 @@ pub struct Sharded {
     #[structopt(short = "k", long = "required-shards", default_value = "3")]
     /// Number of shards required to recover the key
     recovery_threshold: u8,
+
+    #[structopt(long = "custodian", number_of_values(1))]
+    /// Address of a custodian to seal a shard to. Give one custodian
+    /// per shard, in shard order. A sealed shard can only be used with
+    /// the custodian's wallet
+    custodians: Vec<PubKeyBin>,
+
+    #[structopt(long)]
+    /// A label to store in the wallet. Defaults to the current label
+    label: Option<String>,
+
+    #[structopt(long)]
+    /// Keyfile the new wallet needs besides the password. Defaults to
+    /// the keyfile of the current wallet
+    keyfile: Option<PathBuf>,
+
+    #[structopt(flatten)]
+    pwhash: PWHashOpts,
+}
+
+#[derive(Debug, StructOpt)]
+/// Upgrade to a wallet with unlock slots, with one slot for the
+/// current password
+pub struct Slotted {
+    #[structopt(short, long, default_value = "wallet.key")]
+    /// Output file to store the key in
+    output: PathBuf,
+
+    #[structopt(long)]
+    /// Overwrite an existing file
+    force: bool,
+
+    #[structopt(long, default_value = "password")]
+    /// Name of the slot for the password
+    slot: String,
+
+    #[structopt(long)]
+    /// A label to store in the wallet. Defaults to the current label
+    label: Option<String>,
+
+    #[structopt(flatten)]
+    pwhash: PWHashOpts,
 }
 
 impl Cmd {
@@ -54,20 +112,24 @@ impl Cmd {
         match self {
             Cmd::Basic(cmd) => cmd.run(opts),
             Cmd::Sharded(cmd) => cmd.run(opts),
+            Cmd::Slotted(cmd) => cmd.run(opts),
         }
     }
 }
 
 impl Basic {
     pub fn run(&self, opts: Opts) -> Result {
-        let password = get_password(false)?;
+        let pwhash = self.pwhash.pwhash()?;
         let wallet = load_wallet(opts.files)?;
-        let keypair = wallet.decrypt(password.as_bytes())?;
+        let secret = get_wallet_secret(&wallet, &opts.keyfile)?;
+        let keypair = secret.decrypt(&wallet)?;
 
-        let format = format::Basic {
-            pwhash: PWHash::argon2id13_default(),
-        };
-        let new_wallet = Wallet::encrypt(&keypair, password.as_bytes(), Format::Basic(format))?;
+        let format = format::Basic { pwhash };
+        let new_wallet = upgrade_secret(secret, &self.keyfile)?.encrypt(
+            &keypair,
+            Format::Basic(format),
+            upgrade_metadata(&wallet, &keypair, &self.label),
+        )?;
         let mut writer = open_output_file(&self.output, !self.force)?;
         new_wallet.write(&mut writer)?;
         verify::print_result(&new_wallet, true, opts.format)
@@ -76,26 +138,66 @@ impl Basic {
 
 impl Sharded {
     pub fn run(&self, opts: Opts) -> Result {
-        let password = get_password(false)?;
+        let custodians = get_custodian_keys(&self.custodians, self.key_share_count)?;
+        let pwhash = self.pwhash.pwhash()?;
         let wallet = load_wallet(opts.files)?;
-        let keypair = wallet.decrypt(password.as_bytes())?;
+        let secret = get_wallet_secret(&wallet, &opts.keyfile)?;
+        let keypair = secret.decrypt(&wallet)?;
 
         let format = format::Sharded {
             key_share_count: self.key_share_count,
             recovery_threshold: self.recovery_threshold,
-            pwhash: PWHash::argon2id13_default(),
+            pwhash,
             key_shares: vec![],
+            generation: None,
         };
-        let new_wallet = Wallet::encrypt(&keypair, password.as_bytes(), Format::Sharded(format))?;
-
-        let extension = get_file_extension(&self.output);
-        for (i, shard) in new_wallet.shards()?.iter().enumerate() {
-            let mut filename = self.output.clone();
-            let share_extension = format!("{}.{}", extension, (i + 1).to_string());
-            filename.set_extension(share_extension);
-            let mut writer = open_output_file(&filename, !self.force)?;
-            shard.write(&mut writer)?;
-        }
+        let new_wallet = upgrade_secret(secret, &self.keyfile)?.encrypt(
+            &keypair,
+            Format::Sharded(format),
+            upgrade_metadata(&wallet, &keypair, &self.label),
+        )?;
+        write_shards(&new_wallet, &self.output, self.force, &custodians)?;
+        verify::print_result(&new_wallet, true, opts.format)
+    }
+}
+
+impl Slotted {
+    pub fn run(&self, opts: Opts) -> Result {
+        let pwhash = self.pwhash.pwhash()?;
+        let wallet = load_wallet(opts.files)?;
+        let secret = get_wallet_secret(&wallet, &opts.keyfile)?;
+        let keypair = secret.decrypt(&wallet)?;
+
+        let new_wallet = secret.encrypt(
+            &keypair,
+            Format::slotted(&self.slot, pwhash),
+            upgrade_metadata(&wallet, &keypair, &self.label),
+        )?;
+        let mut writer = open_output_file(&self.output, !self.force)?;
+        new_wallet.write(&mut writer)?;
         verify::print_result(&new_wallet, true, opts.format)
     }
 }
+
+/// Returns the secret for the upgraded wallet, which keeps the keyfile
+/// of the current wallet unless another keyfile is given
+fn upgrade_secret(secret: WalletSecret, keyfile: &Option<PathBuf>) -> Result<WalletSecret> {
+    let keyfile = match keyfile {
+        Some(path) => Some(read_keyfile(path)?),
+        None => secret.keyfile,
+    };
+    Ok(WalletSecret { keyfile, ..secret })
+}
+
+/// Returns the metadata for the upgraded wallet. The creation time and
+/// label of the wallet are kept unless a new label is given.
+fn upgrade_metadata(wallet: &Wallet, keypair: &Keypair, label: &Option<String>) -> Metadata {
+    let mut metadata = wallet
+        .metadata
+        .clone()
+        .unwrap_or_else(|| Metadata::new(keypair.network(), None));
+    if label.is_some() {
+        metadata.label = label.clone();
+    }
+    metadata
+}
@@ -1,6 +1,6 @@
 use crate::{
     cmd::{api_url, multisig::Artifact, print_json, Opts},
-    keypair::PubKeyBin,
+    keypair::{Network, PubKeyBin},
     result::Result,
     traits::{ToJson, TxnEnvelope},
 };
@@ -57,19 +57,19 @@ impl Cmd {
 }
 
 impl Current {
-    pub fn run(&self, _opts: Opts) -> Result {
-        print_json(&get_vars()?)
+    pub fn run(&self, opts: Opts) -> Result {
+        print_json(&get_vars(opts.network.unwrap_or_default())?)
     }
 }
 
-fn get_vars() -> Result<serde_json::Map<String, serde_json::Value>> {
-    let client = Client::new_with_base_url(api_url());
+fn get_vars(network: Network) -> Result<serde_json::Map<String, serde_json::Value>> {
+    let client = Client::new_with_base_url(api_url(network)?);
     client.get_vars()
 }
 
 impl Create {
-    pub fn run(&self, _opts: Opts) -> Result {
-        let client = Client::new_with_base_url(api_url());
+    pub fn run(&self, opts: Opts) -> Result {
+        let client = Client::new_with_base_url(api_url(opts.network.unwrap_or_default())?);
         let vars = client.get_vars()?;
         let mut txn = BlockchainTxnVarsV1 {
             version_predicate: 0,
@@ -1,5 +1,8 @@
 use crate::{
-    cmd::{get_password, load_wallet, print_json, print_table, Opts, OutputFormat},
+    cmd::{
+        get_wallet_secret, load_wallet, print_json, print_table, pwhash::pwhash_params, Opts,
+        OutputFormat,
+    },
     result::Result,
     wallet::Wallet,
 };
@@ -13,29 +16,46 @@ pub struct Cmd {}
 
 impl Cmd {
     pub fn run(&self, opts: Opts) -> Result {
-        let password = get_password(false)?;
         let wallet = load_wallet(opts.files)?;
-        let result = wallet.decrypt(password.as_bytes());
+        let result = get_wallet_secret(&wallet, &opts.keyfile)?.decrypt(&wallet);
         print_result(&wallet, result.is_ok(), opts.format)
     }
 }
 
 pub fn print_result(wallet: &Wallet, result: bool, format: OutputFormat) -> Result {
     let address = wallet.address().unwrap_or_else(|_| "unknown".to_string());
+    let network = wallet
+        .network()
+        .map(|network| network.to_string())
+        .unwrap_or_else(|_| "unknown".to_string());
     match format {
         OutputFormat::Table => {
             let mut table = Table::new();
             table.set_format(*format::consts::FORMAT_NO_LINESEP_WITH_TITLE);
-            table.set_titles(row!["Address", "Sharded", "Verify", "PWHash"]);
-            table.add_row(row![address, wallet.is_sharded(), result, wallet.pwhash()]);
+            table.set_titles(row![
+                "Address", "Label", "Network", "Sharded", "Verify", "PWHash"
+            ]);
+            table.add_row(row![
+                address,
+                wallet.label().unwrap_or(""),
+                network,
+                wallet.is_sharded(),
+                result,
+                wallet.pwhash()?
+            ]);
             print_table(&table)
         }
         OutputFormat::Json => {
             let table = json!({
                 "address": address,
+                "label": wallet.label(),
+                "created": wallet.created(),
+                "network": network,
                 "sharded": wallet.is_sharded(),
+                "keyfile": wallet.needs_keyfile(),
                 "verify": result,
-                "pwhash": wallet.pwhash().to_string()
+                "pwhash": wallet.pwhash()?.to_string(),
+                "pwhash_params": pwhash_params(wallet.pwhash()?),
             });
             print_json(&table)
         }
@@ -0,0 +1,160 @@
+//! Encryption of data to a Helium address.
+//!
+//! Ed25519 addresses are converted to X25519 keys, and data is
+//! encrypted to them with libsodium. Anonymous envelopes are sealed
+//! boxes that anyone can create. Authenticated envelopes are boxes
+//! between the sender's and the recipient's keys, which prove to the
+//! recipient that the sender's key made them.
+//!
+//! An envelope starts with a kind byte followed by the recipient's
+//! binary address:
+//!
+//! ```text
+//! anonymous:     0x01 | recipient (33) | sealed box
+//! authenticated: 0x02 | recipient (33) | sender (33) | nonce (24) | box
+//! ```
+
+use crate::{
+    keypair::{Keypair, PubKeyBin, PublicKey},
+    result::Result,
+};
+use sodiumoxide::crypto::{box_, sealedbox};
+use std::convert::TryFrom;
+
+const KIND_ANONYMOUS: u8 = 0x01;
+const KIND_AUTHENTICATED: u8 = 0x02;
+const ADDRESS_LEN: usize = 33;
+
+/// The result of decrypting an envelope
+pub struct Decrypted {
+    /// The sender of an authenticated envelope
+    pub sender: Option<PubKeyBin>,
+    pub data: Vec<u8>,
+}
+
+/// Encrypts data to the given recipient. If a sender keypair is given
+/// the envelope is authenticated by the sender's key.
+pub fn encrypt(data: &[u8], recipient: &PublicKey, sender: Option<&Keypair>) -> Result<Vec<u8>> {
+    sodiumoxide::init().expect("Failed to intialize sodium");
+    let recipient_key = recipient.to_curve25519()?;
+    let mut envelope = vec![];
+    match sender {
+        None => {
+            envelope.push(KIND_ANONYMOUS);
+            envelope.extend_from_slice(&PubKeyBin::from(recipient).to_vec());
+            envelope.extend_from_slice(&sealedbox::seal(data, &recipient_key));
+        }
+        Some(sender) => {
+            let nonce = box_::gen_nonce();
+            envelope.push(KIND_AUTHENTICATED);
+            envelope.extend_from_slice(&PubKeyBin::from(recipient).to_vec());
+            envelope.extend_from_slice(&sender.pubkey_bin().to_vec());
+            envelope.extend_from_slice(&nonce.0);
+            envelope.extend_from_slice(&box_::seal(
+                data,
+                &nonce,
+                &recipient_key,
+                &sender.to_curve25519()?,
+            ));
+        }
+    }
+    Ok(envelope)
+}
+
+/// Returns the recipient of an envelope
+pub fn recipient(envelope: &[u8]) -> Result<PubKeyBin> {
+    if envelope.len() < 1 + ADDRESS_LEN {
+        return Err("Invalid encrypted data".into());
+    }
+    Ok(PubKeyBin::from_vec(&envelope[1..1 + ADDRESS_LEN]))
+}
+
+/// Decrypts an envelope with the recipient's keypair
+pub fn decrypt(envelope: &[u8], keypair: &Keypair) -> Result<Decrypted> {
+    let recipient = recipient(envelope)?;
+    if recipient != keypair.pubkey_bin() {
+        return Err(format!("Data is encrypted to {}, not this wallet", recipient).into());
+    }
+    let secret_key = keypair.to_curve25519()?;
+    let public_key = keypair.public.to_curve25519()?;
+    let body = &envelope[1 + ADDRESS_LEN..];
+    match envelope[0] {
+        KIND_ANONYMOUS => {
+            let data = sealedbox::open(body, &public_key, &secret_key)
+                .map_err(|_| "Failed to decrypt data")?;
+            Ok(Decrypted { sender: None, data })
+        }
+        KIND_AUTHENTICATED => {
+            if body.len() < ADDRESS_LEN + box_::NONCEBYTES {
+                return Err("Invalid encrypted data".into());
+            }
+            let sender = PubKeyBin::from_vec(&body[..ADDRESS_LEN]);
+            let sender_key = PublicKey::try_from(sender)?.to_curve25519()?;
+            let nonce = box_::Nonce::from_slice(&body[ADDRESS_LEN..ADDRESS_LEN + box_::NONCEBYTES])
+                .ok_or("Invalid nonce")?;
+            let data = box_::open(
+                &body[ADDRESS_LEN + box_::NONCEBYTES..],
+                &nonce,
+                &sender_key,
+                &secret_key,
+            )
+            .map_err(|_| "Failed to decrypt data")?;
+            Ok(Decrypted {
+                sender: Some(sender),
+                data,
+            })
+        }
+        kind => Err(format!("Unsupported encrypted data kind {}", kind).into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keypair::KeyType;
+
+    #[test]
+    fn anonymous() {
+        let recipient = Keypair::gen_keypair().expect("keypair");
+        let envelope = encrypt(b"hello world", &recipient.public, None).expect("envelope");
+        assert_eq!(
+            recipient.pubkey_bin(),
+            super::recipient(&envelope).expect("recipient")
+        );
+        let decrypted = decrypt(&envelope, &recipient).expect("decrypted");
+        assert_eq!(b"hello world".to_vec(), decrypted.data);
+        assert!(decrypted.sender.is_none());
+
+        assert!(decrypt(&envelope, &Keypair::gen_keypair().expect("keypair")).is_err());
+        let mut tampered = envelope;
+        let last = tampered.len() - 1;
+        tampered[last] ^= 1;
+        assert!(decrypt(&tampered, &recipient).is_err());
+    }
+
+    #[test]
+    fn authenticated() {
+        let sender = Keypair::gen_keypair().expect("keypair");
+        let recipient = Keypair::gen_keypair().expect("keypair");
+        let envelope = encrypt(b"hello world", &recipient.public, Some(&sender)).expect("envelope");
+        let decrypted = decrypt(&envelope, &recipient).expect("decrypted");
+        assert_eq!(b"hello world".to_vec(), decrypted.data);
+        assert_eq!(Some(sender.pubkey_bin()), decrypted.sender);
+
+        // Claiming another sender breaks the box
+        let mut forged = envelope;
+        forged[1 + ADDRESS_LEN..1 + 2 * ADDRESS_LEN].copy_from_slice(
+            &Keypair::gen_keypair()
+                .expect("keypair")
+                .pubkey_bin()
+                .to_vec(),
+        );
+        assert!(decrypt(&forged, &recipient).is_err());
+    }
+
+    #[test]
+    fn ecc_compact() {
+        let recipient = Keypair::gen_keypair_with_type(KeyType::EccCompact).expect("keypair");
+        assert!(encrypt(b"hello world", &recipient.public, None).is_err());
+    }
+}
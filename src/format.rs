@@ -1,4 +1,11 @@
-use crate::{pwhash::PWHash, result::Result};
+use crate::{
+    pwhash::PWHash,
+    result::Result,
+    secret::Secret,
+    wallet::{AESKey, Tag, IV},
+};
+use aead::NewAead;
+use aes_gcm::Aes256Gcm;
 use byteorder::{ReadBytesExt, WriteBytesExt};
 use hmac::{Hmac, Mac};
 use sha2::Sha256;
@@ -10,6 +17,7 @@ use std::{fmt, io};
 pub enum Format {
     Basic(Basic),
     Sharded(Sharded),
+    Slotted(Slotted),
 }
 
 impl Format {
@@ -17,20 +25,25 @@ impl Format {
         match self {
             Format::Basic(derive) => derive.derive_key(password, key),
             Format::Sharded(derive) => derive.derive_key(password, key),
+            Format::Slotted(derive) => derive.derive_key(password, key),
         }
     }
 
-    pub fn mut_pwhash(&mut self) -> &mut PWHash {
+    /// Returns the password hash of the format. For slotted formats
+    /// this is the password hash of the first slot.
+    pub fn mut_pwhash(&mut self) -> Result<&mut PWHash> {
         match self {
-            Format::Basic(derive) => derive.mut_pwhash(),
-            Format::Sharded(derive) => derive.mut_pwhash(),
+            Format::Basic(derive) => Ok(derive.mut_pwhash()),
+            Format::Sharded(derive) => Ok(derive.mut_pwhash()),
+            Format::Slotted(derive) => derive.mut_pwhash(),
         }
     }
 
-    pub fn pwhash(&self) -> &PWHash {
+    pub fn pwhash(&self) -> Result<&PWHash> {
         match self {
-            Format::Basic(derive) => derive.pwhash(),
-            Format::Sharded(derive) => derive.pwhash(),
+            Format::Basic(derive) => Ok(derive.pwhash()),
+            Format::Sharded(derive) => Ok(derive.pwhash()),
+            Format::Slotted(derive) => derive.pwhash(),
         }
     }
 
@@ -38,6 +51,7 @@ impl Format {
         match self {
             Format::Basic(derive) => derive.read(reader),
             Format::Sharded(derive) => derive.read(reader),
+            Format::Slotted(derive) => derive.read(reader),
         }
     }
 
@@ -45,6 +59,7 @@ impl Format {
         match self {
             Format::Basic(derive) => derive.write(writer),
             Format::Sharded(derive) => derive.write(writer),
+            Format::Slotted(derive) => derive.write(writer),
         }
     }
 
@@ -58,12 +73,55 @@ impl Format {
             recovery_threshold,
             key_shares: Vec::new(),
             pwhash,
+            generation: None,
+        })
+    }
+
+    /// A slotted format for a new wallet, with one slot of the given
+    /// name for the password the wallet is encrypted with.
+    pub fn slotted(name: &str, pwhash: PWHash) -> Self {
+        Format::Slotted(Slotted {
+            slots: vec![Slot::new(name, pwhash)],
+            table_mac: SlotTableMac::default(),
         })
     }
 
     pub fn sharded_default(pwhash: PWHash) -> Self {
         Self::sharded(5, 3, pwhash)
     }
+
+    /// A default sharded format for reading shards that carry a
+    /// generation tag.
+    pub fn sharded_generation_default(pwhash: PWHash) -> Self {
+        Format::Sharded(Sharded {
+            key_share_count: 5,
+            recovery_threshold: 3,
+            key_shares: Vec::new(),
+            pwhash,
+            generation: Some(Generation::default()),
+        })
+    }
+
+    /// Returns a format of the same kind and with the same parameters
+    /// as this one, with a freshly salted password hash and, for
+    /// sharded formats, no key shares so that a new set of shares is
+    /// generated on the next encrypt. A new wallet is encrypted with a
+    /// single password, so only slotted formats with a single slot can
+    /// be regenerated.
+    pub fn regenerate(&self) -> Result<Self> {
+        match self {
+            Format::Basic(derive) => Ok(Self::basic(derive.pwhash.with_new_salt())),
+            Format::Sharded(derive) => Ok(Self::sharded(
+                derive.key_share_count,
+                derive.recovery_threshold,
+                derive.pwhash.with_new_salt(),
+            )),
+            Format::Slotted(derive) => match &derive.slots[..] {
+                [slot] => Ok(Self::slotted(&slot.name, slot.pwhash.with_new_salt())),
+                _ => Err("Only a wallet with a single unlock slot can be encrypted again".into()),
+            },
+        }
+    }
 }
 
 #[derive(Clone)]
@@ -93,7 +151,7 @@ impl Basic {
     }
 }
 
-#[derive(Clone)]
+#[derive(Clone, PartialEq)]
 pub struct KeyShare(pub(crate) [u8; 33]);
 
 impl Default for KeyShare {
@@ -120,30 +178,39 @@ impl KeyShare {
     }
 }
 
+/// Random tag shared by all shards created by one encryption of a
+/// wallet. Shards written before generation tags were introduced have
+/// no generation.
+pub type Generation = [u8; 16];
+
 #[derive(Clone, Debug)]
 pub struct Sharded {
     pub key_share_count: u8,
     pub recovery_threshold: u8,
     pub key_shares: Vec<KeyShare>,
     pub pwhash: PWHash,
+    pub generation: Option<Generation>,
 }
 
 impl Sharded {
     pub fn derive_key(&mut self, password: &[u8], key: &mut [u8]) -> Result {
         self.pwhash.pwhash(password, key)?;
 
-        let mut sss_key: [u8; 32] = [0; 32];
+        let mut sss_key = Secret::<[u8; 32]>::zeroed();
 
         if self.key_shares.is_empty() {
             // Generate the keyhares when we have none
-            randombytes::randombytes_into(&mut sss_key);
+            randombytes::randombytes_into(&mut *sss_key);
             let key_share_vecs =
-                create_keyshares(&sss_key, self.key_share_count, self.recovery_threshold)?;
+                create_keyshares(&sss_key[..], self.key_share_count, self.recovery_threshold)?;
             let mut key_shares = vec![];
             for share_vec in key_share_vecs {
                 key_shares.push(KeyShare::from_slice(&share_vec));
             }
             self.key_shares = key_shares;
+            let mut generation = Generation::default();
+            randombytes::randombytes_into(&mut generation);
+            self.generation = Some(generation);
         } else if self.key_shares.len() < self.recovery_threshold as usize {
             // Otherwise validate that we can reconstruct the key
             return Err("not enouth keyshares to recover key".into());
@@ -152,20 +219,19 @@ impl Sharded {
             let key_share_vecs: Vec<Vec<u8>> =
                 self.key_shares.iter().map(|sh| sh.to_vec()).collect();
             match combine_keyshares(&key_share_vecs) {
-                Ok(k) => sss_key.copy_from_slice(&k),
+                Ok(k) => sss_key.copy_from_slice(&Secret::new(k)),
                 Err(_) => return Err("Failed to combine keyshares".into()),
             }
         }
 
         // Now go derive the encryption key from the sharded key
         // source and the stretched key
-        let mut hmac = match Hmac::<Sha256>::new_varkey(&sss_key) {
+        let mut hmac = match Hmac::<Sha256>::new_varkey(&sss_key[..]) {
             Err(_) => return Err("Failed to initialize hmac".into()),
             Ok(m) => m,
         };
         hmac.input(key);
-        let code: [u8; 32] = hmac.result().code().into();
-        key.copy_from_slice(&code);
+        key.copy_from_slice(&hmac.result().code());
         Ok(())
     }
 
@@ -194,6 +260,19 @@ impl Sharded {
         {
             return Err("Shards are not congruent".into());
         }
+        if self.generation != other.generation {
+            return Err("Shard is from a different generation of the wallet".into());
+        }
+        // Shards without a generation tag were all written with the
+        // same password hash salt
+        if self.pwhash != other.pwhash {
+            return Err("Shard is from a different generation of the wallet".into());
+        }
+        for share in &other.key_shares {
+            if self.key_shares.contains(share) {
+                return Err("Duplicate shard".into());
+            }
+        }
 
         self.key_shares.extend_from_slice(&other.key_shares);
         Ok(())
@@ -205,6 +284,9 @@ impl Sharded {
         let mut key_share = KeyShare::default();
         reader.read_exact(&mut key_share.0)?;
         self.key_shares.push(key_share);
+        if let Some(generation) = &mut self.generation {
+            reader.read_exact(generation)?;
+        }
         Ok(())
     }
 
@@ -215,6 +297,248 @@ impl Sharded {
         writer.write_u8(self.key_share_count)?;
         writer.write_u8(self.recovery_threshold)?;
         writer.write_all(&self.key_shares[0].0)?;
+        if let Some(generation) = &self.generation {
+            writer.write_all(generation)?;
+        }
+        Ok(())
+    }
+}
+
+/// An unlock slot of a slotted wallet. A slot holds the data key of the
+/// wallet wrapped with a key derived from the slot's own secret, which
+/// is a password or the contents of a keyfile.
+#[derive(Clone, Debug)]
+pub struct Slot {
+    pub name: String,
+    pub pwhash: PWHash,
+    wrapped: Option<WrappedKey>,
+}
+
+#[derive(Clone, Debug)]
+struct WrappedKey {
+    iv: IV,
+    tag: Tag,
+    key: AESKey,
+}
+
+impl Slot {
+    fn new(name: &str, pwhash: PWHash) -> Self {
+        Slot {
+            name: name.to_string(),
+            pwhash,
+            wrapped: None,
+        }
+    }
+
+    fn wrap(&mut self, secret: &[u8], data_key: &AESKey) -> Result {
+        let mut slot_key = Secret::<AESKey>::zeroed();
+        self.pwhash.pwhash(secret, &mut *slot_key)?;
+        let mut iv = IV::default();
+        randombytes::randombytes_into(&mut iv);
+
+        use aead::generic_array::GenericArray;
+        let aead = Aes256Gcm::new(*GenericArray::from_slice(&slot_key[..]));
+        // The key is encrypted in place, so it starts out as a secret
+        let mut key = Secret::<AESKey>::zeroed();
+        key.copy_from_slice(data_key);
+        match aead.encrypt_in_place_detached(
+            iv.as_ref().into(),
+            &self.associated_data()?,
+            &mut *key,
+        ) {
+            Err(_) => Err("Failed to wrap wallet key".into()),
+            Ok(gtag) => {
+                self.wrapped = Some(WrappedKey {
+                    iv,
+                    tag: gtag.into(),
+                    key: *key,
+                });
+                Ok(())
+            }
+        }
+    }
+
+    fn unwrap(&self, secret: &[u8]) -> Result<Secret<AESKey>> {
+        let wrapped = self.wrapped.as_ref().ok_or("Slot holds no key")?;
+        let mut slot_key = Secret::<AESKey>::zeroed();
+        self.pwhash.pwhash(secret, &mut *slot_key)?;
+
+        use aead::generic_array::GenericArray;
+        let aead = Aes256Gcm::new(*GenericArray::from_slice(&slot_key[..]));
+        // The key is decrypted in place, so it starts out as a secret
+        let mut key = Secret::<AESKey>::zeroed();
+        key.copy_from_slice(&wrapped.key);
+        match aead.decrypt_in_place_detached(
+            wrapped.iv.as_ref().into(),
+            &self.associated_data()?,
+            &mut *key,
+            wrapped.tag.as_ref().into(),
+        ) {
+            Err(_) => Err("Failed to unwrap wallet key".into()),
+            Ok(_) => Ok(key),
+        }
+    }
+
+    /// The name and password hash of the slot are authenticated with
+    /// the wrapped key
+    fn associated_data(&self) -> Result<Vec<u8>> {
+        let mut data = vec![];
+        self.write_header(&mut data)?;
+        Ok(data)
+    }
+
+    fn write_header(&self, writer: &mut dyn io::Write) -> Result {
+        if self.name.len() > u8::MAX as usize {
+            return Err("Slot name is too long".into());
+        }
+        writer.write_u8(self.name.len() as u8)?;
+        writer.write_all(self.name.as_bytes())?;
+        self.pwhash.write_kind(writer)?;
+        self.pwhash.write(writer)?;
+        Ok(())
+    }
+
+    pub fn read(reader: &mut dyn io::Read) -> Result<Self> {
+        let mut name = vec![0; reader.read_u8()? as usize];
+        reader.read_exact(&mut name)?;
+        let mut pwhash = PWHash::read_kind(reader)?;
+        pwhash.read(reader)?;
+        let mut wrapped = WrappedKey {
+            iv: IV::default(),
+            tag: Tag::default(),
+            key: AESKey::default(),
+        };
+        reader.read_exact(&mut wrapped.iv)?;
+        reader.read_exact(&mut wrapped.tag)?;
+        reader.read_exact(&mut wrapped.key)?;
+        Ok(Slot {
+            name: String::from_utf8(name)?,
+            pwhash,
+            wrapped: Some(wrapped),
+        })
+    }
+
+    pub fn write(&self, writer: &mut dyn io::Write) -> Result {
+        let wrapped = self.wrapped.as_ref().ok_or("Slot holds no key")?;
+        self.write_header(writer)?;
+        writer.write_all(&wrapped.iv)?;
+        writer.write_all(&wrapped.tag)?;
+        writer.write_all(&wrapped.key)?;
+        Ok(())
+    }
+}
+
+/// The HMAC-SHA256 that authenticates the slot table of a slotted
+/// wallet
+pub type SlotTableMac = [u8; 32];
+
+/// A format where a random data key encrypts the wallet and is wrapped
+/// once per unlock slot. Any one slot secret unlocks the wallet, and
+/// slots are added and removed with the data key of an existing slot.
+/// The slot table is authenticated on its own with a MAC keyed from the
+/// data key, so changing the slots leaves the encrypted key as it is.
+#[derive(Clone, Debug, Default)]
+pub struct Slotted {
+    pub slots: Vec<Slot>,
+    pub table_mac: SlotTableMac,
+}
+
+impl Slotted {
+    pub fn derive_key(&mut self, password: &[u8], key: &mut [u8]) -> Result {
+        if self.slots.iter().all(|slot| slot.wrapped.is_none()) {
+            // Generate the data key and wrap it in the first slot when
+            // no slot holds it yet
+            let mut data_key = Secret::<AESKey>::zeroed();
+            randombytes::randombytes_into(&mut *data_key);
+            self.slots
+                .first_mut()
+                .ok_or("Wallet has no slots")?
+                .wrap(password, &data_key)?;
+            key.copy_from_slice(&data_key[..]);
+            return Ok(());
+        }
+        key.copy_from_slice(&self.unlock(password)?[..]);
+        Ok(())
+    }
+
+    /// Returns the data key from the first slot the given secret
+    /// unlocks
+    pub fn unlock(&self, secret: &[u8]) -> Result<Secret<AESKey>> {
+        for slot in &self.slots {
+            if let Ok(data_key) = slot.unwrap(secret) {
+                return Ok(data_key);
+            }
+        }
+        Err("No slot unlocks with the given password or keyfile".into())
+    }
+
+    /// Adds a slot for a new secret that wraps the given data key,
+    /// which is unlocked from an existing slot
+    pub fn add(
+        &mut self,
+        data_key: &AESKey,
+        name: &str,
+        new_secret: &[u8],
+        pwhash: PWHash,
+    ) -> Result {
+        if self.slots.iter().any(|slot| slot.name == name) {
+            return Err(format!("Slot {} already exists", name).into());
+        }
+        if self.slots.len() >= u8::MAX as usize {
+            return Err("Too many slots".into());
+        }
+        let mut slot = Slot::new(name, pwhash);
+        slot.wrap(new_secret, data_key)?;
+        self.slots.push(slot);
+        Ok(())
+    }
+
+    /// Removes a slot. The last slot of a wallet can not be removed.
+    pub fn remove(&mut self, name: &str) -> Result {
+        let index = self
+            .slots
+            .iter()
+            .position(|slot| slot.name == name)
+            .ok_or_else(|| format!("No slot named {}", name))?;
+        if self.slots.len() == 1 {
+            return Err("The last slot of a wallet can not be removed".into());
+        }
+        self.slots.remove(index);
+        Ok(())
+    }
+
+    pub fn mut_pwhash(&mut self) -> Result<&mut PWHash> {
+        match self.slots.first_mut() {
+            Some(slot) => Ok(&mut slot.pwhash),
+            None => Err("Wallet has no slots".into()),
+        }
+    }
+
+    pub fn pwhash(&self) -> Result<&PWHash> {
+        match self.slots.first() {
+            Some(slot) => Ok(&slot.pwhash),
+            None => Err("Wallet has no slots".into()),
+        }
+    }
+
+    pub fn read(&mut self, reader: &mut dyn io::Read) -> Result {
+        let count = reader.read_u8()?;
+        if count == 0 {
+            return Err("Wallet has no slots".into());
+        }
+        for _ in 0..count {
+            self.slots.push(Slot::read(reader)?);
+        }
+        reader.read_exact(&mut self.table_mac)?;
+        Ok(())
+    }
+
+    pub fn write(&self, writer: &mut dyn io::Write) -> Result {
+        writer.write_u8(self.slots.len() as u8)?;
+        for slot in &self.slots {
+            slot.write(writer)?;
+        }
+        writer.write_all(&self.table_mac)?;
         Ok(())
     }
 }
@@ -0,0 +1,395 @@
+//! Distributed key generation for a FROST group, as the Pedersen key
+//! generation with proofs of knowledge from the FROST paper.
+//!
+//! Key generation takes three parts. In part 1 every participant picks
+//! a random polynomial and publishes commitments to its coefficients
+//! with a proof that it knows the secret constant term. In part 2 every
+//! participant evaluates its polynomial at every other participant's
+//! identifier and sends them the result, encrypted to a key published
+//! in part 1. In part 3 every participant checks what it received
+//! against the commitments and adds it up into its key share.
+//!
+//! Part 1 packages are broadcast without a trusted channel, so every
+//! part 2 package carries a digest of the part 1 packages its sender
+//! received, and part 3 refuses to continue unless every participant
+//! received the same ones.
+
+use super::{
+    evaluate, evaluate_commitments, hash_to_scalar, sha512, Group, Identifier, KeyShare, Point,
+    Scalar,
+};
+use crate::{keypair::Network, result::Result, secret::Secret, traits::B64};
+use serde_derive::{Deserialize, Serialize};
+use sodiumoxide::crypto::{box_, sealedbox};
+use std::collections::BTreeMap;
+
+/// The secret state a participant keeps between the parts of a key
+/// generation
+#[derive(Deserialize, Serialize)]
+pub struct State {
+    identifier: Identifier,
+    threshold: u16,
+    participants: u16,
+    network: Network,
+    coefficients: Vec<Scalar>,
+    encryption_key: String,
+    /// Our own part 1 package, which the part 1 digest covers
+    package: Round1Package,
+}
+
+/// The public output of part 1, which is sent to every other
+/// participant
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub struct Round1Package {
+    pub identifier: Identifier,
+    pub threshold: u16,
+    pub participants: u16,
+    pub network: Network,
+    pub commitments: Vec<Point>,
+    pub proof_r: Point,
+    pub proof_mu: Scalar,
+    /// The public key that part 2 shares are encrypted to
+    pub encryption_key: String,
+}
+
+/// The output of part 2, holding a share for every other participant
+/// that only that participant can decrypt
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub struct Round2Package {
+    pub identifier: Identifier,
+    /// The hex encoded digest of all part 1 packages the participant
+    /// received
+    pub round1: String,
+    pub shares: BTreeMap<Identifier, String>,
+}
+
+/// Starts a key generation for the participant with the given
+/// identifier in a group of the given size and threshold
+pub fn part1(
+    identifier: Identifier,
+    threshold: u16,
+    participants: u16,
+    network: Network,
+) -> Result<(State, Round1Package)> {
+    if threshold < 2 || threshold > participants {
+        return Err("The threshold has to be between 2 and the number of participants".into());
+    }
+    if identifier == 0 || identifier > participants {
+        return Err(format!("The identifier has to be between 1 and {}", participants).into());
+    }
+    let coefficients: Vec<Scalar> = (0..threshold).map(|_| Scalar::random()).collect();
+    let commitments: Vec<Point> = coefficients
+        .iter()
+        .map(Point::base)
+        .collect::<Result<_>>()?;
+
+    let k = Scalar::random();
+    let proof_r = Point::base(&k)?;
+    let proof_mu = k + &coefficients[0] * proof_challenge(identifier, &commitments[0], &proof_r);
+
+    let (public_key, secret_key) = box_::gen_keypair();
+    let package = Round1Package {
+        identifier,
+        threshold,
+        participants,
+        network,
+        commitments,
+        proof_r,
+        proof_mu,
+        encryption_key: public_key.0.to_vec().to_b64()?,
+    };
+    let state = State {
+        identifier,
+        threshold,
+        participants,
+        network,
+        coefficients,
+        encryption_key: secret_key.0.to_vec().to_b64()?,
+        package: package.clone(),
+    };
+    Ok((state, package))
+}
+
+/// Checks the part 1 packages of all other participants and encrypts a
+/// share for each of them
+pub fn part2(state: &State, packages: &[Round1Package]) -> Result<Round2Package> {
+    let packages = state.check_packages(packages)?;
+    let mut shares = BTreeMap::new();
+    for package in &packages {
+        let public_key =
+            box_::PublicKey::from_slice(&Vec::<u8>::from_b64(&package.encryption_key)?)
+                .ok_or("Invalid encryption key")?;
+        let share = evaluate(&state.coefficients, package.identifier);
+        let mut plaintext = Secret::<[u8; 36]>::zeroed();
+        plaintext[..2].copy_from_slice(&state.identifier.to_le_bytes());
+        plaintext[2..4].copy_from_slice(&package.identifier.to_le_bytes());
+        plaintext[4..].copy_from_slice(&share.0);
+        shares.insert(
+            package.identifier,
+            sealedbox::seal(&plaintext[..], &public_key).to_b64()?,
+        );
+    }
+    Ok(Round2Package {
+        identifier: state.identifier,
+        round1: state.round1_digest(&packages)?,
+        shares,
+    })
+}
+
+/// Checks the shares received from all other participants and combines
+/// them into this participant's key share
+pub fn part3(
+    state: &State,
+    round1: &[Round1Package],
+    round2: &[Round2Package],
+) -> Result<KeyShare> {
+    let round1 = state.check_packages(round1)?;
+    let round1_digest = state.round1_digest(&round1)?;
+    let secret_key =
+        box_::SecretKey::from_slice(&Secret::new(Vec::<u8>::from_b64(&state.encryption_key)?))
+            .ok_or("Invalid encryption key")?;
+    let public_key = secret_key.public_key();
+
+    let mut secret = evaluate(&state.coefficients, state.identifier);
+    for package in &round1 {
+        let sender = package.identifier;
+        let round2 = match round2
+            .iter()
+            .filter(|round2| round2.identifier == sender)
+            .collect::<Vec<_>>()[..]
+        {
+            [round2] => round2,
+            [] => return Err(format!("Missing part 2 package of participant {}", sender).into()),
+            _ => return Err(format!("Multiple part 2 packages of participant {}", sender).into()),
+        };
+        if round2.round1 != round1_digest {
+            return Err(format!(
+                "Participant {} received different part 1 packages than we did",
+                sender
+            )
+            .into());
+        }
+        let sealed = round2
+            .shares
+            .get(&state.identifier)
+            .ok_or_else(|| format!("Participant {} sent no share for us", sender))?;
+        let plaintext = sealedbox::open(&Vec::<u8>::from_b64(sealed)?, &public_key, &secret_key)
+            .map(Secret::new)
+            .map_err(|_| format!("Failed to decrypt the share of participant {}", sender))?;
+        if plaintext.len() != 36
+            || plaintext[..2] != sender.to_le_bytes()
+            || plaintext[2..4] != state.identifier.to_le_bytes()
+        {
+            return Err(format!("Invalid share from participant {}", sender).into());
+        }
+        let share = Scalar::from_bytes(&plaintext[4..])?;
+        if Point::base(&share)? != evaluate_commitments(&package.commitments, state.identifier)? {
+            return Err(format!(
+                "Share of participant {} does not match its commitments",
+                sender
+            )
+            .into());
+        }
+        secret = secret + share;
+    }
+
+    // Every participant's public share and the group key follow from
+    // the commitments of all participants, including our own
+    let mut commitments: Vec<&[Point]> = round1
+        .iter()
+        .map(|package| package.commitments.as_slice())
+        .collect();
+    commitments.push(&state.package.commitments);
+    let group_key = commitments
+        .iter()
+        .try_fold(Point::IDENTITY, |key, commitments| key.add(&commitments[0]))?;
+    let mut verifying_shares = BTreeMap::new();
+    for identifier in 1..=state.participants {
+        let share = commitments
+            .iter()
+            .try_fold(Point::IDENTITY, |share, commitments| {
+                share.add(&evaluate_commitments(commitments, identifier)?)
+            })?;
+        verifying_shares.insert(identifier, share);
+    }
+    let share = KeyShare {
+        identifier: state.identifier,
+        group: Group {
+            network: state.network,
+            threshold: state.threshold,
+            group_key,
+            verifying_shares,
+        },
+        secret,
+    };
+    if Point::base(&share.secret)? != share.group.verifying_share(state.identifier)? {
+        return Err("Key share does not match the group".into());
+    }
+    Ok(share)
+}
+
+/// The challenge of the proof of knowledge of a participant's secret
+fn proof_challenge(identifier: Identifier, commitment: &Point, r: &Point) -> Scalar {
+    hash_to_scalar(
+        b"dkg",
+        &[
+            &Scalar::from_identifier(identifier).to_bytes(),
+            &commitment.to_bytes(),
+            &r.to_bytes(),
+        ],
+    )
+}
+
+impl State {
+    pub fn identifier(&self) -> Identifier {
+        self.identifier
+    }
+
+    /// Checks that the given packages are from exactly the other
+    /// participants of this key generation, one each, with the same
+    /// parameters, and that their proofs are valid. Our own package may
+    /// be given, but has to be the one we sent.
+    fn check_packages<'a>(&self, packages: &'a [Round1Package]) -> Result<Vec<&'a Round1Package>> {
+        if packages
+            .iter()
+            .any(|package| package.identifier == self.identifier && *package != self.package)
+        {
+            return Err("Part 1 package with our identifier is not ours".into());
+        }
+        let mut others: Vec<&Round1Package> = packages
+            .iter()
+            .filter(|package| package.identifier != self.identifier)
+            .collect();
+        others.sort_by_key(|package| package.identifier);
+        if let Some(pair) = others
+            .windows(2)
+            .find(|pair| pair[0].identifier == pair[1].identifier)
+        {
+            return Err(format!(
+                "Multiple part 1 packages of participant {}",
+                pair[0].identifier
+            )
+            .into());
+        }
+        let expected: Vec<Identifier> = (1..=self.participants)
+            .filter(|identifier| *identifier != self.identifier)
+            .collect();
+        let found: Vec<Identifier> = others.iter().map(|package| package.identifier).collect();
+        if found != expected {
+            return Err(format!(
+                "Expected part 1 packages of participants {:?}, found {:?}",
+                expected, found
+            )
+            .into());
+        }
+        for package in &others {
+            if package.threshold != self.threshold
+                || package.participants != self.participants
+                || package.network != self.network
+                || package.commitments.len() != self.threshold as usize
+            {
+                return Err(format!(
+                    "Participant {} uses different group parameters",
+                    package.identifier
+                )
+                .into());
+            }
+            let challenge = proof_challenge(
+                package.identifier,
+                &package.commitments[0],
+                &package.proof_r,
+            );
+            if Point::base(&package.proof_mu)?
+                != package
+                    .proof_r
+                    .add(&package.commitments[0].mul(&challenge)?)?
+            {
+                return Err(
+                    format!("Invalid proof from participant {}", package.identifier).into(),
+                );
+            }
+        }
+        Ok(others)
+    }
+
+    /// The digest of our own and the given checked part 1 packages
+    fn round1_digest(&self, others: &[&Round1Package]) -> Result<String> {
+        let mut packages: Vec<&Round1Package> = others.to_vec();
+        packages.push(&self.package);
+        packages.sort_by_key(|package| package.identifier);
+        Ok(hex::encode(sha512(&[&serde_json::to_vec(&packages)?])))
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// Runs a key generation for all participants
+    pub(crate) fn keygen(threshold: u16, participants: u16) -> Vec<KeyShare> {
+        let (states, round1): (Vec<State>, Vec<Round1Package>) = (1..=participants)
+            .map(|identifier| part1(identifier, threshold, participants, Network::Testnet))
+            .collect::<Result<Vec<_>>>()
+            .expect("part 1")
+            .into_iter()
+            .unzip();
+        let round2: Vec<Round2Package> = states
+            .iter()
+            .map(|state| part2(state, &round1).expect("part 2"))
+            .collect();
+        states
+            .iter()
+            .map(|state| part3(state, &round1, &round2).expect("part 3"))
+            .collect()
+    }
+
+    #[test]
+    fn key_generation() {
+        let shares = keygen(2, 3);
+        for share in &shares {
+            assert_eq!(shares[0].group, share.group);
+        }
+        assert_eq!(3, shares[0].group.verifying_shares.len());
+        assert_eq!(Network::Testnet, shares[0].group.network);
+    }
+
+    #[test]
+    fn bad_packages() {
+        assert!(part1(1, 1, 3, Network::Mainnet).is_err());
+        assert!(part1(4, 2, 3, Network::Mainnet).is_err());
+
+        let (state, _) = part1(1, 2, 3, Network::Mainnet).expect("part 1");
+        let (_, package2) = part1(2, 2, 3, Network::Mainnet).expect("part 1");
+        let (_, package3) = part1(3, 2, 3, Network::Mainnet).expect("part 1");
+        // A package is missing
+        assert!(part2(&state, std::slice::from_ref(&package2)).is_err());
+        // A proof that does not match the commitments
+        let mut forged = package3.clone();
+        forged.commitments[0] = package2.commitments[0];
+        assert!(part2(&state, &[package2.clone(), forged]).is_err());
+        // A conflicting second package of a participant
+        let (_, other3) = part1(3, 2, 3, Network::Mainnet).expect("part 1");
+        assert!(part2(&state, &[package2.clone(), package3.clone(), other3]).is_err());
+        assert!(part2(&state, &[package2, package3]).is_ok());
+    }
+
+    #[test]
+    fn different_broadcasts() {
+        let (states, mut round1): (Vec<State>, Vec<Round1Package>) = (1..=3)
+            .map(|identifier| part1(identifier, 2, 3, Network::Mainnet).expect("part 1"))
+            .unzip();
+        let round2: Vec<Round2Package> = states
+            .iter()
+            .map(|state| part2(state, &round1).expect("part 2"))
+            .collect();
+        assert!(part3(&states[0], &round1, &round2).is_ok());
+
+        // Participant 3 sent participant 1 a different, valid package
+        // than the others received
+        let (_, other3) = part1(3, 2, 3, Network::Mainnet).expect("part 1");
+        round1[2] = other3;
+        let mut round2 = round2;
+        round2[0] = part2(&states[0], &round1).expect("part 2");
+        assert!(part3(&states[0], &round1, &round2).is_err());
+    }
+}
@@ -0,0 +1,492 @@
+//! Threshold Ed25519 signatures with FROST, following RFC 9591 with the
+//! FROST(Ed25519, SHA-512) ciphersuite.
+//!
+//! A group of participants generates a key with a distributed key
+//! generation in which every participant ends up with a share of the
+//! secret key, and the full secret key never exists anywhere. Any
+//! threshold number of participants can then produce a signature in
+//! two rounds. The result is a plain Ed25519 signature that verifies
+//! against the group public key.
+
+use crate::{
+    keypair::{Network, PubKeyBin, PublicKey, KEYTYPE_ED25519},
+    pwhash::Argon2id13,
+    result::Result,
+    secret::Secret,
+    traits::{B58, B64},
+};
+use libsodium_sys as ffi;
+use serde::{de, ser, Deserializer, Serializer};
+use serde_derive::{Deserialize, Serialize};
+use sha2::{Digest, Sha512};
+use sodiumoxide::crypto::secretbox;
+use std::{collections::BTreeMap, convert::TryFrom, fmt, fs, ops, path::Path};
+
+pub mod dkg;
+pub mod sign;
+
+/// The context string of the FROST(Ed25519, SHA-512) ciphersuite
+const CONTEXT: &[u8] = b"FROST-ED25519-SHA512-v1";
+
+/// Participants are identified by a non-zero number
+pub type Identifier = u16;
+
+/// A scalar modulo the order of the Ed25519 group. Scalars are mostly
+/// secrets, so they are not copied implicitly and are zeroed when
+/// dropped.
+#[derive(Clone, PartialEq)]
+pub struct Scalar([u8; 32]);
+
+impl Scalar {
+    pub const ZERO: Scalar = Scalar([0; 32]);
+
+    pub fn random() -> Self {
+        sodiumoxide::init().expect("Failed to intialize sodium");
+        let mut s = Scalar::ZERO;
+        unsafe { ffi::crypto_core_ed25519_scalar_random(s.0.as_mut_ptr()) };
+        s
+    }
+
+    pub fn from_identifier(identifier: Identifier) -> Self {
+        let mut s = Scalar::ZERO;
+        s.0[..2].copy_from_slice(&identifier.to_le_bytes());
+        s
+    }
+
+    /// Reduces a 64 byte little endian number, like a SHA-512 digest
+    fn from_wide(wide: &[u8]) -> Self {
+        let mut s = Scalar::ZERO;
+        unsafe { ffi::crypto_core_ed25519_scalar_reduce(s.0.as_mut_ptr(), wide.as_ptr()) };
+        s
+    }
+
+    /// Decodes a canonical, fully reduced, little endian scalar
+    pub fn from_bytes(data: &[u8]) -> Result<Self> {
+        if data.len() != 32 {
+            return Err("Invalid scalar length".into());
+        }
+        let mut wide = [0u8; 64];
+        wide[..32].copy_from_slice(data);
+        let scalar = Scalar::from_wide(&wide);
+        sodiumoxide::utils::memzero(&mut wide);
+        if scalar.0[..] != data[..] {
+            return Err("Scalar is not canonical".into());
+        }
+        Ok(scalar)
+    }
+
+    pub fn to_bytes(&self) -> [u8; 32] {
+        self.0
+    }
+
+    pub fn invert(&self) -> Result<Scalar> {
+        let mut s = Scalar::ZERO;
+        match unsafe { ffi::crypto_core_ed25519_scalar_invert(s.0.as_mut_ptr(), self.0.as_ptr()) } {
+            0 => Ok(s),
+            _ => Err("Can not invert zero".into()),
+        }
+    }
+}
+
+impl Drop for Scalar {
+    fn drop(&mut self) {
+        sodiumoxide::utils::memzero(&mut self.0);
+    }
+}
+
+/// Implements an arithmetic operator for scalars and references to
+/// scalars with the given libsodium function
+macro_rules! impl_scalar_op {
+    ($op:ident, $method:ident, $ffi:ident) => {
+        impl ops::$op<&Scalar> for &Scalar {
+            type Output = Scalar;
+            fn $method(self, other: &Scalar) -> Scalar {
+                let mut s = Scalar::ZERO;
+                unsafe { ffi::$ffi(s.0.as_mut_ptr(), self.0.as_ptr(), other.0.as_ptr()) };
+                s
+            }
+        }
+
+        impl ops::$op<Scalar> for &Scalar {
+            type Output = Scalar;
+            fn $method(self, other: Scalar) -> Scalar {
+                ops::$op::$method(self, &other)
+            }
+        }
+
+        impl ops::$op<&Scalar> for Scalar {
+            type Output = Scalar;
+            fn $method(self, other: &Scalar) -> Scalar {
+                ops::$op::$method(&self, other)
+            }
+        }
+
+        impl ops::$op<Scalar> for Scalar {
+            type Output = Scalar;
+            fn $method(self, other: Scalar) -> Scalar {
+                ops::$op::$method(&self, &other)
+            }
+        }
+    };
+}
+
+impl_scalar_op!(Add, add, crypto_core_ed25519_scalar_add);
+impl_scalar_op!(Sub, sub, crypto_core_ed25519_scalar_sub);
+impl_scalar_op!(Mul, mul, crypto_core_ed25519_scalar_mul);
+
+/// Scalars are mostly secrets and are never printed
+impl fmt::Debug for Scalar {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("Scalar(..)")
+    }
+}
+
+/// A point on the Ed25519 curve in its 32 byte compressed form
+#[derive(Clone, Copy, PartialEq)]
+pub struct Point([u8; 32]);
+
+impl Point {
+    pub const IDENTITY: Point = Point([
+        1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        0, 0,
+    ]);
+
+    /// Multiplies the base point by the given scalar. Fails when the
+    /// result is the identity.
+    pub fn base(scalar: &Scalar) -> Result<Point> {
+        let mut p = [0u8; 32];
+        match unsafe {
+            ffi::crypto_scalarmult_ed25519_base_noclamp(p.as_mut_ptr(), scalar.0.as_ptr())
+        } {
+            0 => Ok(Point(p)),
+            _ => Err("Scalar multiplication of the base point failed".into()),
+        }
+    }
+
+    /// Decodes a point, which has to be a canonical encoding of a
+    /// point in the prime order subgroup other than the identity
+    pub fn from_bytes(data: &[u8]) -> Result<Self> {
+        if data.len() != 32
+            || unsafe { ffi::crypto_core_ed25519_is_valid_point(data.as_ptr()) } != 1
+        {
+            return Err("Invalid Ed25519 point".into());
+        }
+        let mut p = [0u8; 32];
+        p.copy_from_slice(data);
+        Ok(Point(p))
+    }
+
+    pub fn to_bytes(&self) -> [u8; 32] {
+        self.0
+    }
+
+    /// Adds two points. Fails when either is not on the curve.
+    pub fn add(&self, other: &Point) -> Result<Point> {
+        let mut p = [0u8; 32];
+        match unsafe {
+            ffi::crypto_core_ed25519_add(p.as_mut_ptr(), self.0.as_ptr(), other.0.as_ptr())
+        } {
+            0 => Ok(Point(p)),
+            _ => Err("Point addition failed".into()),
+        }
+    }
+
+    /// Multiplies the point by the given scalar. Fails for the
+    /// identity, either as the point or as the result.
+    pub fn mul(&self, scalar: &Scalar) -> Result<Point> {
+        let mut p = [0u8; 32];
+        match unsafe {
+            ffi::crypto_scalarmult_ed25519_noclamp(
+                p.as_mut_ptr(),
+                scalar.0.as_ptr(),
+                self.0.as_ptr(),
+            )
+        } {
+            0 => Ok(Point(p)),
+            _ => Err("Scalar multiplication failed".into()),
+        }
+    }
+}
+
+impl fmt::Debug for Point {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Point({})", hex::encode(self.0))
+    }
+}
+
+// The hex encoding of a scalar is as secret as the scalar
+impl serde::Serialize for Scalar {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        let encoded = Secret::new(hex::encode(&self.0[..]).into_bytes());
+        serializer.serialize_str(std::str::from_utf8(&encoded).map_err(ser::Error::custom)?)
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for Scalar {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        let encoded =
+            Secret::new(<String as serde::Deserialize>::deserialize(deserializer)?.into_bytes());
+        let data = Secret::new(hex::decode(&*encoded).map_err(de::Error::custom)?);
+        Scalar::from_bytes(&data).map_err(de::Error::custom)
+    }
+}
+
+impl serde::Serialize for Point {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.serialize_str(&hex::encode(self.0))
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for Point {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        let data = hex::decode(<String as serde::Deserialize>::deserialize(deserializer)?)
+            .map_err(de::Error::custom)?;
+        Point::from_bytes(&data).map_err(de::Error::custom)
+    }
+}
+
+fn sha512(parts: &[&[u8]]) -> Vec<u8> {
+    let mut hasher = Sha512::new();
+    for part in parts {
+        hasher.input(part);
+    }
+    hasher.result().to_vec()
+}
+
+/// Hashes to a scalar with the ciphersuite context and the given tag,
+/// as H1 ("rho"), H3 ("nonce") and the key generation proof ("dkg")
+fn hash_to_scalar(tag: &[u8], parts: &[&[u8]]) -> Scalar {
+    let mut input: Vec<&[u8]> = vec![CONTEXT, tag];
+    input.extend_from_slice(parts);
+    Scalar::from_wide(&sha512(&input))
+}
+
+/// Evaluates the polynomial with the given coefficients at the given
+/// identifier
+fn evaluate(coefficients: &[Scalar], identifier: Identifier) -> Scalar {
+    let x = Scalar::from_identifier(identifier);
+    coefficients
+        .iter()
+        .rev()
+        .fold(Scalar::ZERO, |value, coefficient| value * &x + coefficient)
+}
+
+/// Evaluates the polynomial with the given coefficient commitments at
+/// the given identifier
+fn evaluate_commitments(commitments: &[Point], identifier: Identifier) -> Result<Point> {
+    let x = Scalar::from_identifier(identifier);
+    let (last, rest) = commitments.split_last().ok_or("Missing commitments")?;
+    rest.iter()
+        .rev()
+        .try_fold(*last, |value, commitment| value.mul(&x)?.add(commitment))
+}
+
+/// The Lagrange coefficient of the given identifier for interpolating
+/// at zero from the given set of identifiers
+fn lagrange(identifiers: &[Identifier], identifier: Identifier) -> Result<Scalar> {
+    let x = Scalar::from_identifier(identifier);
+    let mut numerator = Scalar::from_identifier(1);
+    let mut denominator = Scalar::from_identifier(1);
+    for other in identifiers.iter().filter(|other| **other != identifier) {
+        let x_other = Scalar::from_identifier(*other);
+        denominator = denominator * (&x_other - &x);
+        numerator = numerator * x_other;
+    }
+    Ok(numerator * denominator.invert()?)
+}
+
+/// The public information of a FROST group: the group key and the
+/// public key of every participant's share
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub struct Group {
+    pub network: Network,
+    pub threshold: u16,
+    pub group_key: Point,
+    pub verifying_shares: BTreeMap<Identifier, Point>,
+}
+
+impl Group {
+    pub fn pubkey_bin(&self) -> PubKeyBin {
+        let mut pubkey_bin = PubKeyBin::default();
+        pubkey_bin.0[0] = self.network.to_byte() | KEYTYPE_ED25519;
+        pubkey_bin.0[1..].copy_from_slice(&self.group_key.0);
+        pubkey_bin
+    }
+
+    pub fn public_key(&self) -> Result<PublicKey> {
+        PublicKey::try_from(self.pubkey_bin())
+    }
+
+    pub fn address(&self) -> Result<String> {
+        self.pubkey_bin().to_b58()
+    }
+
+    fn verifying_share(&self, identifier: Identifier) -> Result<Point> {
+        match self.verifying_shares.get(&identifier) {
+            Some(point) => Ok(*point),
+            None => Err(format!("Participant {} is not in the group", identifier).into()),
+        }
+    }
+}
+
+/// A participant's share of a group key
+pub struct KeyShare {
+    pub identifier: Identifier,
+    pub group: Group,
+    secret: Scalar,
+}
+
+impl KeyShare {
+    pub fn address(&self) -> Result<String> {
+        self.group.address()
+    }
+}
+
+/// A key share as stored in a file. Everything but the secret share
+/// is stored in the clear.
+#[derive(Deserialize, Serialize)]
+pub struct KeyShareFile {
+    pub address: String,
+    pub identifier: Identifier,
+    pub group: Group,
+    secret: Sealed,
+}
+
+impl KeyShareFile {
+    pub fn seal(share: &KeyShare, password: &[u8], pwhash: Argon2id13) -> Result<Self> {
+        Ok(KeyShareFile {
+            address: share.address()?,
+            identifier: share.identifier,
+            group: share.group.clone(),
+            secret: Sealed::seal(&share.secret.0, password, pwhash)?,
+        })
+    }
+
+    pub fn open(&self, password: &[u8]) -> Result<KeyShare> {
+        let secret = Scalar::from_bytes(&self.secret.open(password)?)?;
+        // Catch a secret that does not belong to the share's identifier
+        if Point::base(&secret)? != self.group.verifying_share(self.identifier)? {
+            return Err("Key share does not match the group".into());
+        }
+        Ok(KeyShare {
+            identifier: self.identifier,
+            group: self.group.clone(),
+            secret,
+        })
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let data = fs::read(path)?;
+        Ok(serde_json::from_slice(&data)
+            .map_err(|err| format!("{}: invalid FROST key share: {}", path.display(), err))?)
+    }
+}
+
+/// Data encrypted with a key derived from a password. The password hash
+/// is stored with the data and needs a fresh salt for every seal.
+#[derive(Deserialize, Serialize)]
+pub struct Sealed {
+    pwhash: String,
+    nonce: String,
+    ciphertext: String,
+}
+
+impl Sealed {
+    pub fn seal(data: &[u8], password: &[u8], pwhash: Argon2id13) -> Result<Self> {
+        sodiumoxide::init().expect("Failed to intialize sodium");
+        let mut params = vec![];
+        pwhash.write(&mut params)?;
+        let key = Sealed::derive_key(&pwhash, password)?;
+        let nonce = secretbox::gen_nonce();
+        Ok(Sealed {
+            pwhash: params.to_b64()?,
+            nonce: nonce.0.to_vec().to_b64()?,
+            ciphertext: secretbox::seal(data, &nonce, &key).to_b64()?,
+        })
+    }
+
+    /// Returns the decrypted data as a secret
+    pub fn open(&self, password: &[u8]) -> Result<Secret<Vec<u8>>> {
+        let mut pwhash = Argon2id13::default();
+        pwhash.read(&mut &Vec::<u8>::from_b64(&self.pwhash)?[..])?;
+        let key = Sealed::derive_key(&pwhash, password)?;
+        let nonce = secretbox::Nonce::from_slice(&Vec::<u8>::from_b64(&self.nonce)?)
+            .ok_or("Invalid nonce")?;
+        match secretbox::open(&Vec::<u8>::from_b64(&self.ciphertext)?, &nonce, &key) {
+            Ok(data) => Ok(Secret::new(data)),
+            Err(_) => Err("Failed to decrypt, wrong password?".into()),
+        }
+    }
+
+    fn derive_key(pwhash: &Argon2id13, password: &[u8]) -> Result<secretbox::Key> {
+        let mut key = secretbox::Key([0; secretbox::KEYBYTES]);
+        pwhash.pwhash(password, &mut key.0)?;
+        Ok(key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sodiumoxide::crypto::pwhash::argon2id13;
+
+    #[test]
+    fn scalar_encoding() {
+        let scalar = Scalar::random();
+        assert_eq!(
+            scalar,
+            Scalar::from_bytes(&scalar.to_bytes()).expect("scalar")
+        );
+        // The group order is not a canonical scalar
+        let order = hex::decode("edd3f55c1a631258d69cf7a2def9de1400000000000000000000000000000010")
+            .expect("order");
+        assert!(Scalar::from_bytes(&order).is_err());
+        assert!(Point::from_bytes(&Point::IDENTITY.to_bytes()).is_err());
+    }
+
+    #[test]
+    fn point_errors() {
+        // Operations that would result in the identity fail
+        assert!(Point::base(&Scalar::ZERO).is_err());
+        let point = Point::base(&Scalar::random()).expect("point");
+        assert!(point.mul(&Scalar::ZERO).is_err());
+        assert!(Point::IDENTITY.mul(&Scalar::random()).is_err());
+        // There is no point with y = 2, so it can not be added
+        let mut invalid = [0u8; 32];
+        invalid[0] = 2;
+        assert!(point.add(&Point(invalid)).is_err());
+        assert!(point.add(&point).is_ok());
+    }
+
+    #[test]
+    fn interpolate() {
+        let coefficients = [Scalar::random(), Scalar::random(), Scalar::random()];
+        let identifiers = [1, 3, 4];
+        let secret = identifiers.iter().fold(Scalar::ZERO, |secret, identifier| {
+            let lambda = lagrange(&identifiers, *identifier).expect("lagrange");
+            secret + lambda * evaluate(&coefficients, *identifier)
+        });
+        assert_eq!(coefficients[0], secret);
+
+        let commitments: Vec<Point> = coefficients
+            .iter()
+            .map(Point::base)
+            .collect::<Result<_>>()
+            .expect("commitments");
+        assert_eq!(
+            Point::base(&evaluate(&coefficients, 5)).expect("point"),
+            evaluate_commitments(&commitments, 5).expect("point")
+        );
+    }
+
+    #[test]
+    fn sealed() {
+        let pwhash = Argon2id13::with_limits(
+            argon2id13::OPSLIMIT_INTERACTIVE,
+            argon2id13::MEMLIMIT_INTERACTIVE,
+        );
+        let sealed = Sealed::seal(b"secret", b"password", pwhash).expect("sealed");
+        assert_eq!(b"secret".to_vec(), *sealed.open(b"password").expect("open"));
+        assert!(sealed.open(b"wrong").is_err());
+    }
+}
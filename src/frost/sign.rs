@@ -0,0 +1,350 @@
+//! The two round FROST signing protocol.
+//!
+//! A coordinator publishes a signing request. In round one every
+//! participant that takes part commits to a pair of secret nonces. The
+//! coordinator collects at least a threshold of commitments into a
+//! signing package, and in round two every committed participant signs
+//! the package with its nonces and key share. The coordinator checks
+//! and adds up the signature shares into a single Ed25519 signature.
+
+use super::{
+    hash_to_scalar, lagrange, sha512, Group, Identifier, KeyShare, Point, Scalar, CONTEXT,
+};
+use crate::{keypair::Verify, result::Result, traits::B64};
+use serde_derive::{Deserialize, Serialize};
+use sodiumoxide::randombytes;
+
+/// A request for a signature by a FROST group
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub struct SigningRequest {
+    /// A random identifier of the signing session
+    pub session: String,
+    /// The address of the group
+    pub address: String,
+    pub txn_type: String,
+    pub summary: String,
+    /// The base64 encoded data to sign
+    pub data: String,
+}
+
+impl SigningRequest {
+    pub fn new(group: &Group, txn_type: &str, summary: &str, data: &[u8]) -> Result<Self> {
+        Ok(SigningRequest {
+            session: hex::encode(randombytes::randombytes(16)),
+            address: group.address()?,
+            txn_type: txn_type.to_string(),
+            summary: summary.to_string(),
+            data: data.to_vec().to_b64()?,
+        })
+    }
+
+    pub fn data(&self) -> Result<Vec<u8>> {
+        Vec::<u8>::from_b64(&self.data)
+    }
+}
+
+/// The secret nonces of a participant for one signing session. Nonces
+/// must only ever be used for a single signature.
+#[derive(Deserialize, Serialize)]
+pub struct Nonces {
+    session: String,
+    hiding: Scalar,
+    binding: Scalar,
+}
+
+/// A participant's public commitment to its nonces
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub struct Commitment {
+    pub session: String,
+    pub identifier: Identifier,
+    pub hiding: Point,
+    pub binding: Point,
+}
+
+/// The request and the commitments of the participants that sign it
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub struct SigningPackage {
+    pub request: SigningRequest,
+    pub commitments: Vec<Commitment>,
+}
+
+/// A participant's share of the signature
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub struct SignatureShare {
+    pub session: String,
+    pub identifier: Identifier,
+    pub share: Scalar,
+}
+
+/// Round one: generates nonces for a signing request and the
+/// commitment to publish for them
+pub fn commit(share: &KeyShare, request: &SigningRequest) -> Result<(Nonces, Commitment)> {
+    if request.address != share.address()? {
+        return Err(format!("Signing request is for {}, not this group", request.address).into());
+    }
+    let nonces = Nonces {
+        session: request.session.clone(),
+        hiding: generate_nonce(&share.secret),
+        binding: generate_nonce(&share.secret),
+    };
+    let commitment = Commitment {
+        session: request.session.clone(),
+        identifier: share.identifier,
+        hiding: Point::base(&nonces.hiding)?,
+        binding: Point::base(&nonces.binding)?,
+    };
+    Ok((nonces, commitment))
+}
+
+fn generate_nonce(secret: &Scalar) -> Scalar {
+    let random = randombytes::randombytes(32);
+    hash_to_scalar(b"nonce", &[&random, &secret.0])
+}
+
+impl SigningPackage {
+    /// Builds a package from the commitments for a request. At least a
+    /// threshold of group members have to have committed.
+    pub fn new(
+        group: &Group,
+        request: SigningRequest,
+        mut commitments: Vec<Commitment>,
+    ) -> Result<Self> {
+        commitments.sort_by_key(|commitment| commitment.identifier);
+        commitments.dedup();
+        let package = SigningPackage {
+            request,
+            commitments,
+        };
+        package.check(group)?;
+        Ok(package)
+    }
+
+    fn check(&self, group: &Group) -> Result {
+        if self.request.address != group.address()? {
+            return Err(format!(
+                "Signing request is for {}, not this group",
+                self.request.address
+            )
+            .into());
+        }
+        for (i, commitment) in self.commitments.iter().enumerate() {
+            if commitment.session != self.request.session {
+                return Err(format!(
+                    "Commitment of participant {} is for another session",
+                    commitment.identifier
+                )
+                .into());
+            }
+            group.verifying_share(commitment.identifier)?;
+            if i > 0 && self.commitments[i - 1].identifier >= commitment.identifier {
+                return Err(format!(
+                    "Conflicting commitments of participant {}",
+                    commitment.identifier
+                )
+                .into());
+            }
+        }
+        if self.commitments.len() < group.threshold as usize {
+            return Err(format!(
+                "{} participants committed, at least {} are needed",
+                self.commitments.len(),
+                group.threshold
+            )
+            .into());
+        }
+        Ok(())
+    }
+
+    fn identifiers(&self) -> Vec<Identifier> {
+        self.commitments
+            .iter()
+            .map(|commitment| commitment.identifier)
+            .collect()
+    }
+
+    /// The binding factor of every commitment, in commitment order
+    fn binding_factors(&self, group: &Group, message: &[u8]) -> Vec<Scalar> {
+        let mut encoded = vec![];
+        for commitment in &self.commitments {
+            encoded.extend_from_slice(&Scalar::from_identifier(commitment.identifier).to_bytes());
+            encoded.extend_from_slice(&commitment.hiding.to_bytes());
+            encoded.extend_from_slice(&commitment.binding.to_bytes());
+        }
+        let message_hash = sha512(&[CONTEXT, b"msg", message]);
+        let commitments_hash = sha512(&[CONTEXT, b"com", &encoded]);
+        self.commitments
+            .iter()
+            .map(|commitment| {
+                hash_to_scalar(
+                    b"rho",
+                    &[
+                        &group.group_key.to_bytes(),
+                        &message_hash,
+                        &commitments_hash,
+                        &Scalar::from_identifier(commitment.identifier).to_bytes(),
+                    ],
+                )
+            })
+            .collect()
+    }
+
+    /// The group commitment R and the challenge of the signature
+    fn group_commitment(
+        &self,
+        group: &Group,
+        factors: &[Scalar],
+        message: &[u8],
+    ) -> Result<(Point, Scalar)> {
+        let r = self.commitments.iter().zip(factors).try_fold(
+            Point::IDENTITY,
+            |r, (commitment, factor)| {
+                r.add(&commitment.hiding)?
+                    .add(&commitment.binding.mul(factor)?)
+            },
+        )?;
+        let challenge = Scalar::from_wide(&sha512(&[
+            &r.to_bytes(),
+            &group.group_key.to_bytes(),
+            message,
+        ]));
+        Ok((r, challenge))
+    }
+}
+
+/// Round two: signs a package with the nonces committed to in round
+/// one. The nonces are consumed.
+pub fn sign(share: &KeyShare, nonces: Nonces, package: &SigningPackage) -> Result<SignatureShare> {
+    package.check(&share.group)?;
+    if nonces.session != package.request.session {
+        return Err("Nonces are for another signing session".into());
+    }
+    let index = package
+        .commitments
+        .iter()
+        .position(|commitment| commitment.identifier == share.identifier)
+        .ok_or("Signing package does not include our commitment")?;
+    let commitment = &package.commitments[index];
+    if commitment.hiding != Point::base(&nonces.hiding)?
+        || commitment.binding != Point::base(&nonces.binding)?
+    {
+        return Err("Signing package has a different commitment for us".into());
+    }
+
+    let message = package.request.data()?;
+    let factors = package.binding_factors(&share.group, &message);
+    let (_, challenge) = package.group_commitment(&share.group, &factors, &message)?;
+    let lambda = lagrange(&package.identifiers(), share.identifier)?;
+    Ok(SignatureShare {
+        session: package.request.session.clone(),
+        identifier: share.identifier,
+        share: &nonces.hiding
+            + &nonces.binding * &factors[index]
+            + lambda * &share.secret * challenge,
+    })
+}
+
+/// Checks the signature shares of all committed participants and
+/// combines them into an Ed25519 signature for the group key
+pub fn aggregate(
+    group: &Group,
+    package: &SigningPackage,
+    shares: &[SignatureShare],
+) -> Result<Vec<u8>> {
+    package.check(group)?;
+    let message = package.request.data()?;
+    let factors = package.binding_factors(group, &message);
+    let (r, challenge) = package.group_commitment(group, &factors, &message)?;
+    let identifiers = package.identifiers();
+
+    let mut z = Scalar::ZERO;
+    for (commitment, factor) in package.commitments.iter().zip(&factors) {
+        let share = shares
+            .iter()
+            .find(|share| {
+                share.identifier == commitment.identifier
+                    && share.session == package.request.session
+            })
+            .ok_or_else(|| {
+                format!(
+                    "Missing signature share of participant {}",
+                    commitment.identifier
+                )
+            })?;
+        let lambda = lagrange(&identifiers, commitment.identifier)?;
+        let expected = commitment
+            .hiding
+            .add(&commitment.binding.mul(factor)?)?
+            .add(
+                &group
+                    .verifying_share(commitment.identifier)?
+                    .mul(&(&challenge * lambda))?,
+            )?;
+        if Point::base(&share.share)? != expected {
+            return Err(format!(
+                "Invalid signature share of participant {}",
+                commitment.identifier
+            )
+            .into());
+        }
+        z = z + &share.share;
+    }
+
+    let mut signature = r.to_bytes().to_vec();
+    signature.extend_from_slice(&z.to_bytes());
+    group.public_key()?.verify(&message, &signature)?;
+    Ok(signature)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frost::dkg::tests::keygen;
+
+    fn sign_with(shares: &[&KeyShare], message: &[u8]) -> Result<Vec<u8>> {
+        let group = &shares[0].group;
+        let request = SigningRequest::new(group, "raw", "test", message)?;
+        let mut nonces = vec![];
+        let mut commitments = vec![];
+        for share in shares {
+            let (share_nonces, commitment) = commit(share, &request)?;
+            nonces.push(share_nonces);
+            commitments.push(commitment);
+        }
+        let package = SigningPackage::new(group, request, commitments)?;
+        let mut signature_shares = vec![];
+        for (share, nonces) in shares.iter().zip(nonces) {
+            signature_shares.push(sign(share, nonces, &package)?);
+        }
+        aggregate(group, &package, &signature_shares)
+    }
+
+    #[test]
+    fn threshold_signature() {
+        let shares = keygen(2, 3);
+        let public_key = shares[0].group.public_key().expect("public key");
+        for signers in &[[0, 1], [0, 2], [2, 1]] {
+            let signers: Vec<&KeyShare> = signers.iter().map(|i| &shares[*i]).collect();
+            let signature = sign_with(&signers, b"hello world").expect("signature");
+            assert!(public_key.verify(b"hello world", &signature).is_ok());
+        }
+        let all: Vec<&KeyShare> = shares.iter().collect();
+        assert!(sign_with(&all, b"hello world").is_ok());
+        // One participant is not enough
+        assert!(sign_with(&[&shares[0]], b"hello world").is_err());
+    }
+
+    #[test]
+    fn bad_signature_share() {
+        let shares = keygen(2, 2);
+        let group = &shares[0].group;
+        let request = SigningRequest::new(group, "raw", "test", b"hello world").expect("request");
+        let (nonces1, commitment1) = commit(&shares[0], &request).expect("commit");
+        let (nonces2, commitment2) = commit(&shares[1], &request).expect("commit");
+        let package =
+            SigningPackage::new(group, request, vec![commitment1, commitment2]).expect("package");
+        let share1 = sign(&shares[0], nonces1, &package).expect("share");
+        let mut share2 = sign(&shares[1], nonces2, &package).expect("share");
+        share2.share = &share2.share + Scalar::from_identifier(1);
+        assert!(aggregate(group, &package, &[share1, share2]).is_err());
+    }
+}
@@ -1,15 +1,262 @@
 use crate::{
     result::Result,
+    secret::Secret,
     traits::{ReadWrite, B58},
 };
 use byteorder::ReadBytesExt;
 use ed25519::Signature;
-pub use ed25519::{PublicKey, SecretKey, Seed};
-use sodiumoxide::crypto::sign::ed25519;
-use std::{fmt, io, str::FromStr};
+use libsodium_sys as ffi;
+use p256::{
+    ecdsa::{
+        self,
+        signature::{Signer, Verifier},
+    },
+    elliptic_curve::sec1::ToEncodedPoint,
+};
+use serde_derive::{Deserialize, Serialize};
+use sodiumoxide::{
+    crypto::{box_, sign::ed25519},
+    randombytes,
+};
+use std::{convert::TryFrom, fmt, io, str::FromStr};
 
 static START: std::sync::Once = std::sync::Once::new();
+pub const KEYTYPE_ECC_COMPACT: u8 = 0;
 pub const KEYTYPE_ED25519: u8 = 1;
+pub const NETTYPE_MAIN: u8 = 0x00;
+pub const NETTYPE_TEST: u8 = 0x10;
+
+/// The length of the longest binary keypair, a key byte followed by an
+/// ed25519 secret key and public key
+pub const KEYPAIR_MAX_LENGTH: usize = 1 + 64 + 32;
+
+/// The first byte of a binary public key holds the network in its
+/// upper four bits and the key type in its lower four bits
+const KEYTYPE_MASK: u8 = 0x0f;
+const NETTYPE_MASK: u8 = 0xf0;
+
+/// Half of the P-256 field prime, rounded down. A point is compact if
+/// its y coordinate is not larger than this, which allows it to be
+/// recovered from just its x coordinate.
+const P256_HALF_PRIME: [u8; 32] = [
+    0x7f, 0xff, 0xff, 0xff, 0x80, 0x00, 0x00, 0x00, 0x80, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x7f, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+];
+
+/// The types of keys that are supported
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum KeyType {
+    EccCompact,
+    Ed25519,
+}
+
+impl KeyType {
+    /// Returns the key type stored in the lower bits of a key byte
+    pub fn from_byte(byte: u8) -> Result<Self> {
+        match byte & KEYTYPE_MASK {
+            KEYTYPE_ECC_COMPACT => Ok(KeyType::EccCompact),
+            KEYTYPE_ED25519 => Ok(KeyType::Ed25519),
+            other => Err(format!("Invalid key type {}", other).into()),
+        }
+    }
+
+    pub fn to_byte(self) -> u8 {
+        match self {
+            KeyType::EccCompact => KEYTYPE_ECC_COMPACT,
+            KeyType::Ed25519 => KEYTYPE_ED25519,
+        }
+    }
+}
+
+impl fmt::Display for KeyType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            KeyType::EccCompact => f.write_str("ecc_compact"),
+            KeyType::Ed25519 => f.write_str("ed25519"),
+        }
+    }
+}
+
+impl FromStr for KeyType {
+    type Err = Box<dyn std::error::Error>;
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "ecc_compact" => Ok(KeyType::EccCompact),
+            "ed25519" => Ok(KeyType::Ed25519),
+            _ => Err(format!("Invalid key type {}", s).into()),
+        }
+    }
+}
+
+/// The networks a key can be used on. Keys, and the addresses derived
+/// from them, for one network are not valid on the other.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Network {
+    Mainnet,
+    Testnet,
+}
+
+impl Network {
+    /// Returns the network stored in the upper bits of a key byte
+    pub fn from_byte(byte: u8) -> Result<Self> {
+        match byte & NETTYPE_MASK {
+            NETTYPE_MAIN => Ok(Network::Mainnet),
+            NETTYPE_TEST => Ok(Network::Testnet),
+            other => Err(format!("Invalid network {}", other).into()),
+        }
+    }
+
+    pub fn to_byte(self) -> u8 {
+        match self {
+            Network::Mainnet => NETTYPE_MAIN,
+            Network::Testnet => NETTYPE_TEST,
+        }
+    }
+}
+
+impl Default for Network {
+    fn default() -> Self {
+        Network::Mainnet
+    }
+}
+
+impl fmt::Display for Network {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Network::Mainnet => f.write_str("mainnet"),
+            Network::Testnet => f.write_str("testnet"),
+        }
+    }
+}
+
+impl FromStr for Network {
+    type Err = Box<dyn std::error::Error>;
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "mainnet" => Ok(Network::Mainnet),
+            "testnet" => Ok(Network::Testnet),
+            _ => Err(format!("Invalid network {}", s).into()),
+        }
+    }
+}
+
+/// A public key of one of the supported key types for a network
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PublicKey {
+    pub network: Network,
+    key: Key,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Key {
+    /// A compact P-256 key and its x coordinate
+    EccCompact(p256::PublicKey, [u8; 32]),
+    Ed25519(ed25519::PublicKey),
+}
+
+impl PublicKey {
+    fn new(network: Network, key: Key) -> Self {
+        PublicKey { network, key }
+    }
+
+    pub fn key_type(&self) -> KeyType {
+        match self.key {
+            Key::EccCompact(..) => KeyType::EccCompact,
+            Key::Ed25519(_) => KeyType::Ed25519,
+        }
+    }
+
+    /// Converts an ed25519 key to the X25519 key that data is encrypted
+    /// to for this key
+    pub fn to_curve25519(&self) -> Result<box_::PublicKey> {
+        match &self.key {
+            Key::Ed25519(key) => {
+                let mut curve25519 = box_::PublicKey([0; box_::PUBLICKEYBYTES]);
+                let result = unsafe {
+                    ffi::crypto_sign_ed25519_pk_to_curve25519(
+                        curve25519.0.as_mut_ptr(),
+                        key.0.as_ptr(),
+                    )
+                };
+                if result != 0 {
+                    return Err("Invalid ed25519 public key".into());
+                }
+                Ok(curve25519)
+            }
+            Key::EccCompact(..) => Err("Only ed25519 keys can be encrypted to".into()),
+        }
+    }
+
+    /// The 32 bytes that identify the key. For an ecc_compact key this
+    /// is the x coordinate of the point.
+    fn key_bytes(&self) -> [u8; 32] {
+        let mut buf = [0u8; 32];
+        match &self.key {
+            Key::EccCompact(_, x) => buf.copy_from_slice(x),
+            Key::Ed25519(key) => buf.copy_from_slice(&key.0),
+        }
+        buf
+    }
+
+    /// The first byte of the binary form of the key, combining the
+    /// network and key type
+    fn key_byte(&self) -> u8 {
+        self.network.to_byte() | self.key_type().to_byte()
+    }
+}
+
+/// The secret key of a keypair. The key is kept in a `Secret`, and
+/// the key types of the crypto libraries only exist while they are
+/// used.
+#[derive(PartialEq)]
+pub enum SecretKey {
+    /// The big endian P-256 secret scalar
+    EccCompact(Secret<[u8; 32]>),
+    /// The libsodium secret key, the seed followed by the public key
+    Ed25519(Secret<[u8; 64]>),
+}
+
+// Secret keys never show their bytes
+impl fmt::Debug for SecretKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SecretKey::EccCompact(_) => f.write_str("EccCompact(****)"),
+            SecretKey::Ed25519(_) => f.write_str("Ed25519(****)"),
+        }
+    }
+}
+
+/// Returns whether a P-256 public key is compact, meaning it can be
+/// recovered from its x coordinate
+fn is_compact(key: &p256::PublicKey) -> Result<bool> {
+    let point = key.to_encoded_point(false);
+    let y = point.y().ok_or("Invalid ecc_compact public key")?;
+    Ok(y[..] <= P256_HALF_PRIME[..])
+}
+
+/// Builds the key for a compact P-256 public key
+fn ecc_compact_key(key: p256::PublicKey) -> Result<Key> {
+    let point = key.to_encoded_point(false);
+    let mut x = [0u8; 32];
+    x.copy_from_slice(point.x().ok_or("Invalid ecc_compact public key")?);
+    Ok(Key::EccCompact(key, x))
+}
+
+/// Recovers a compact P-256 public key from its x coordinate
+fn ecc_compact_from_x(x: &[u8]) -> Result<p256::PublicKey> {
+    let mut compressed = [0u8; 33];
+    compressed[1..].copy_from_slice(x);
+    for tag in &[0x02, 0x03] {
+        compressed[0] = *tag;
+        let key = p256::PublicKey::from_sec1_bytes(&compressed)
+            .map_err(|_| "Invalid ecc_compact public key")?;
+        if is_compact(&key)? {
+            return Ok(key);
+        }
+    }
+    Err("Invalid ecc_compact public key".into())
+}
 
 // Newtype to allow us to `impl Default` on a 33 element array.
 #[derive(Clone, Copy)]
@@ -24,8 +271,8 @@ impl Default for PubKeyBin {
 impl From<&PublicKey> for PubKeyBin {
     fn from(pubkey: &PublicKey) -> Self {
         let mut buf = PubKeyBin::default();
-        buf.0[0] = KEYTYPE_ED25519;
-        buf.0[1..].copy_from_slice(&pubkey.0);
+        buf.0[0] = pubkey.key_byte();
+        buf.0[1..].copy_from_slice(&pubkey.key_bytes());
         buf
     }
 }
@@ -40,14 +287,35 @@ impl PubKeyBin {
         result.0.copy_from_slice(&data);
         result
     }
+
+    pub fn key_type(&self) -> Result<KeyType> {
+        KeyType::from_byte(self.0[0])
+    }
+
+    pub fn network(&self) -> Result<Network> {
+        Network::from_byte(self.0[0])
+    }
+
+    /// Returns whether both addresses are for the same key, whatever
+    /// network they are for
+    pub fn same_key(&self, other: &PubKeyBin) -> Result<bool> {
+        Ok(self.key_type()? == other.key_type()? && self.0[1..] == other.0[1..])
+    }
 }
 
-impl From<PubKeyBin> for PublicKey {
-    fn from(pkb: PubKeyBin) -> PublicKey {
-        assert!(pkb.0[0] == KEYTYPE_ED25519);
-        let mut buf = [0u8; 32];
-        buf.copy_from_slice(&pkb.0[1..]);
-        Self(buf)
+impl TryFrom<PubKeyBin> for PublicKey {
+    type Error = Box<dyn std::error::Error>;
+    fn try_from(pkb: PubKeyBin) -> Result<PublicKey> {
+        let network = pkb.network()?;
+        let key = match pkb.key_type()? {
+            KeyType::EccCompact => ecc_compact_key(ecc_compact_from_x(&pkb.0[1..])?)?,
+            KeyType::Ed25519 => {
+                let mut buf = [0u8; 32];
+                buf.copy_from_slice(&pkb.0[1..]);
+                Key::Ed25519(ed25519::PublicKey(buf))
+            }
+        };
+        Ok(PublicKey::new(network, key))
     }
 }
 
@@ -100,41 +368,175 @@ fn init() {
 }
 
 impl Keypair {
-    pub fn gen_keypair() -> Keypair {
+    pub fn gen_keypair() -> Result<Keypair> {
         init();
-        let (pk, sk) = ed25519::gen_keypair();
-        Keypair {
-            public: pk,
-            secret: sk,
+        let mut pk = ed25519::PublicKey([0; 32]);
+        let mut sk = Secret::<[u8; 64]>::zeroed();
+        // The secret key is generated in place
+        if unsafe { ffi::crypto_sign_keypair(pk.0.as_mut_ptr(), sk.as_mut_ptr()) } != 0 {
+            return Err("Failed to generate keypair".into());
         }
+        Ok(Keypair {
+            public: PublicKey::new(Network::Mainnet, Key::Ed25519(pk)),
+            secret: SecretKey::Ed25519(sk),
+        })
     }
 
-    pub fn gen_keypair_from_seed(seed: &Seed) -> Keypair {
+    /// Generates a new keypair of the given key type. For ecc_compact
+    /// keys, keys are generated until one is found with a compact
+    /// public key.
+    pub fn gen_keypair_with_type(key_type: KeyType) -> Result<Keypair> {
+        match key_type {
+            KeyType::Ed25519 => Keypair::gen_keypair(),
+            KeyType::EccCompact => loop {
+                init();
+                let mut bytes = Secret::<[u8; 32]>::zeroed();
+                randombytes::randombytes_into(&mut *bytes);
+                if let Ok(keypair) = Keypair::from_ecc_compact_secret(&bytes[..]) {
+                    return Ok(keypair);
+                }
+            },
+        }
+    }
+
+    pub fn gen_keypair_from_seed(seed: &[u8; 32]) -> Result<Keypair> {
         init();
-        let (pk, sk) = ed25519::keypair_from_seed(seed);
-        Keypair {
-            public: pk,
-            secret: sk,
+        let mut pk = ed25519::PublicKey([0; 32]);
+        let mut sk = Secret::<[u8; 64]>::zeroed();
+        let result = unsafe {
+            ffi::crypto_sign_seed_keypair(pk.0.as_mut_ptr(), sk.as_mut_ptr(), seed.as_ptr())
+        };
+        if result != 0 {
+            return Err("Failed to generate keypair from seed".into());
+        }
+        Ok(Keypair {
+            public: PublicKey::new(Network::Mainnet, Key::Ed25519(pk)),
+            secret: SecretKey::Ed25519(sk),
+        })
+    }
+
+    /// Builds a keypair from a 64 byte libsodium secret key, which is
+    /// the seed followed by the public key. The public key is checked
+    /// against the one derived from the seed.
+    pub fn from_secret_key(data: &[u8]) -> Result<Keypair> {
+        if data.len() != 64 {
+            return Err("Invalid secret key length, expected 64 bytes".into());
+        }
+        let mut seed = Secret::<[u8; 32]>::zeroed();
+        seed.copy_from_slice(&data[..32]);
+        let keypair = Keypair::gen_keypair_from_seed(&seed)?;
+        if keypair.public.key_bytes()[..] != data[32..] {
+            return Err("Secret key does not match its public key".into());
+        }
+        Ok(keypair)
+    }
+
+    /// Builds an ecc_compact keypair from a 32 byte P-256 secret
+    /// scalar. Returns an error if the public key is not compact.
+    pub fn from_ecc_compact_secret(data: &[u8]) -> Result<Keypair> {
+        let public = p256::SecretKey::from_be_bytes(data)
+            .map_err(|_| "Invalid secret key")?
+            .public_key();
+        if !is_compact(&public)? {
+            return Err("Secret key does not have a compact public key".into());
+        }
+        let mut secret = Secret::<[u8; 32]>::zeroed();
+        secret.copy_from_slice(data);
+        Ok(Keypair {
+            public: PublicKey::new(Network::Mainnet, ecc_compact_key(public)?),
+            secret: SecretKey::EccCompact(secret),
+        })
+    }
+
+    /// Returns the keypair with its public key moved to the given
+    /// network
+    pub fn with_network(mut self, network: Network) -> Keypair {
+        self.public.network = network;
+        self
+    }
+
+    pub fn key_type(&self) -> KeyType {
+        self.public.key_type()
+    }
+
+    pub fn network(&self) -> Network {
+        self.public.network
+    }
+
+    /// Returns the seed an ed25519 keypair was generated from
+    pub fn seed(&self) -> Result<Secret<[u8; 32]>> {
+        match &self.secret {
+            SecretKey::Ed25519(secret) => {
+                let mut seed = Secret::<[u8; 32]>::zeroed();
+                seed.copy_from_slice(&secret[..32]);
+                Ok(seed)
+            }
+            _ => Err("Only ed25519 keys have a seed".into()),
         }
     }
 
-    pub fn sign(&self, data: &[u8]) -> Vec<u8> {
-        ed25519::sign_detached(data, &self.secret).0.to_vec()
+    /// Signs the given data. Ed25519 signatures are 64 bytes,
+    /// ecc_compact signatures are DER encoded ECDSA signatures over
+    /// the SHA-256 digest of the data.
+    pub fn sign(&self, data: &[u8]) -> Result<Vec<u8>> {
+        match &self.secret {
+            SecretKey::Ed25519(secret) => {
+                let mut signature = vec![0; ed25519::SIGNATUREBYTES];
+                let result = unsafe {
+                    ffi::crypto_sign_detached(
+                        signature.as_mut_ptr(),
+                        std::ptr::null_mut(),
+                        data.as_ptr(),
+                        data.len() as u64,
+                        secret.as_ptr(),
+                    )
+                };
+                if result != 0 {
+                    return Err("Failed to sign".into());
+                }
+                Ok(signature)
+            }
+            SecretKey::EccCompact(secret) => {
+                let secret = p256::SecretKey::from_be_bytes(&secret[..])
+                    .map_err(|_| "Invalid secret key")?;
+                let signature: ecdsa::Signature = ecdsa::SigningKey::from(&secret)
+                    .try_sign(data)
+                    .map_err(|_| "Failed to sign")?;
+                Ok(signature.to_der().as_bytes().to_vec())
+            }
+        }
     }
 
     pub fn pubkey_bin(&self) -> PubKeyBin {
         PubKeyBin::from(&self.public)
     }
+
+    /// Converts an ed25519 secret key to the X25519 key that decrypts
+    /// data encrypted to this keypair
+    pub fn to_curve25519(&self) -> Result<box_::SecretKey> {
+        match &self.secret {
+            SecretKey::Ed25519(secret) => {
+                let mut curve25519 = box_::SecretKey([0; box_::SECRETKEYBYTES]);
+                let result = unsafe {
+                    ffi::crypto_sign_ed25519_sk_to_curve25519(
+                        curve25519.0.as_mut_ptr(),
+                        secret.as_ptr(),
+                    )
+                };
+                if result != 0 {
+                    return Err("Invalid ed25519 secret key".into());
+                }
+                Ok(curve25519)
+            }
+            SecretKey::EccCompact(_) => Err("Only ed25519 keys can decrypt".into()),
+        }
+    }
 }
 
+/// Shows the address of the keypair, never the secret key
 impl fmt::Display for Keypair {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(
-            f,
-            "Keypair({:?}, {:?})",
-            self.public.to_b58().unwrap(),
-            self.secret
-        )
+        write!(f, "Keypair({})", self.pubkey_bin())
     }
 }
 
@@ -146,29 +548,47 @@ impl fmt::Debug for Keypair {
 
 impl ReadWrite for Keypair {
     fn write(&self, writer: &mut dyn io::Write) -> Result {
-        writer.write_all(&[KEYTYPE_ED25519])?;
-        writer.write_all(&self.secret.0)?;
-        writer.write_all(&self.public.0)?;
+        writer.write_all(&[self.public.key_byte()])?;
+        match &self.secret {
+            SecretKey::Ed25519(secret) => writer.write_all(&secret[..])?,
+            SecretKey::EccCompact(secret) => writer.write_all(&secret[..])?,
+        }
+        writer.write_all(&self.public.key_bytes())?;
         Ok(())
     }
 
     fn read(reader: &mut dyn io::Read) -> Result<Keypair> {
         init();
-        let key_type = reader.read_u8()?;
-        if key_type != KEYTYPE_ED25519 {
-            return Err(format!("Invalid key type {}", key_type).into());
-        }
-
-        let mut sk_buf = [0; 64];
-        reader.read_exact(&mut sk_buf)?;
-
-        let mut pk_buf = [0; 32];
-        reader.read_exact(&mut pk_buf)?;
-
-        Ok(Keypair {
-            public: PublicKey(pk_buf),
-            secret: SecretKey(sk_buf),
-        })
+        let key_byte = reader.read_u8()?;
+        let network = Network::from_byte(key_byte)?;
+        let keypair = match KeyType::from_byte(key_byte)? {
+            KeyType::Ed25519 => {
+                let mut secret = Secret::<[u8; 64]>::zeroed();
+                reader.read_exact(&mut *secret)?;
+
+                let mut pk_buf = [0; 32];
+                reader.read_exact(&mut pk_buf)?;
+
+                Keypair {
+                    public: PublicKey::new(network, Key::Ed25519(ed25519::PublicKey(pk_buf))),
+                    secret: SecretKey::Ed25519(secret),
+                }
+            }
+            KeyType::EccCompact => {
+                let mut sk_buf = Secret::<[u8; 32]>::zeroed();
+                reader.read_exact(&mut *sk_buf)?;
+
+                let mut pk_buf = [0; 32];
+                reader.read_exact(&mut pk_buf)?;
+
+                let keypair = Keypair::from_ecc_compact_secret(&sk_buf[..])?;
+                if keypair.public.key_bytes() != pk_buf {
+                    return Err("Secret key does not match its public key".into());
+                }
+                keypair
+            }
+        };
+        Ok(keypair.with_network(network))
     }
 }
 
@@ -178,14 +598,24 @@ pub trait Verify {
 
 impl Verify for PublicKey {
     fn verify(&self, message: &[u8], signature: &[u8]) -> Result {
-        if let Some(sig) = Signature::from_slice(signature) {
-            if ed25519::verify_detached(&sig, message, self) {
-                Ok(())
-            } else {
-                Err("Sigmature does not verify".into())
+        match &self.key {
+            Key::Ed25519(key) => {
+                if let Some(sig) = Signature::from_slice(signature) {
+                    if ed25519::verify_detached(&sig, message, key) {
+                        Ok(())
+                    } else {
+                        Err("Sigmature does not verify".into())
+                    }
+                } else {
+                    Err("Invalid signature".into())
+                }
+            }
+            Key::EccCompact(key, _) => {
+                let sig = ecdsa::Signature::from_der(signature).map_err(|_| "Invalid signature")?;
+                ecdsa::VerifyingKey::from(key)
+                    .verify(message, &sig)
+                    .map_err(|_| "Sigmature does not verify".into())
             }
-        } else {
-            Err("Invalid signature".into())
         }
     }
 }
@@ -195,34 +625,213 @@ mod tests {
     use super::*;
     use std::io::Cursor;
 
+    const KEY_TYPES: [KeyType; 2] = [KeyType::Ed25519, KeyType::EccCompact];
+
     #[test]
     fn roundtrip_keypair() {
-        let keypair = Keypair::gen_keypair();
-        let mut buffer = Vec::new();
-        keypair
-            .write(&mut buffer)
-            .expect("Failed to encode keypair");
+        for key_type in KEY_TYPES.iter() {
+            let keypair = Keypair::gen_keypair_with_type(*key_type).expect("keypair");
+            assert_eq!(*key_type, keypair.key_type());
+            let mut buffer = Vec::new();
+            keypair
+                .write(&mut buffer)
+                .expect("Failed to encode keypair");
+
+            let decoded =
+                Keypair::read(&mut Cursor::new(buffer)).expect("Failed to decode keypair");
+            assert_eq!(keypair, decoded);
+        }
+    }
 
-        let decoded = Keypair::read(&mut Cursor::new(buffer)).expect("Failed to decode keypair");
-        assert_eq!(keypair, decoded);
+    #[test]
+    fn sign_verify() {
+        for key_type in KEY_TYPES.iter() {
+            let keypair = Keypair::gen_keypair_with_type(*key_type).expect("keypair");
+            let signature = keypair.sign(b"hello world").expect("signature");
+            assert!(keypair.public.verify(b"hello world", &signature).is_ok());
+            assert!(keypair.public.verify(b"hello world!", &signature).is_err());
+        }
     }
 
     #[test]
-    fn roundtrip_public_key() {
-        let pk = Keypair::gen_keypair().public;
+    fn ed25519_vector() {
+        // Test 1 from RFC 8032
+        let mut seed = [0; 32];
+        hex::decode_to_slice(
+            "9d61b19deffd5a60ba844af492ec2cc44449c5697b326919703bac031cae7f60",
+            &mut seed,
+        )
+        .expect("seed");
+        let keypair = Keypair::gen_keypair_from_seed(&seed).expect("keypair");
+        assert_eq!(
+            "14ab6w719xfTgeZeaLkg4nUUuTDJBDJp4xUVzqkkYB3c5amgUz6",
+            keypair.public.to_b58().expect("address")
+        );
+        let signature = keypair.sign(b"").expect("signature");
+        assert_eq!(
+            "e5564300c360ac729086e2cc806e828a84877f1eb8e5d974d873e065224901555fb8821590a33bac\
+             c61e39701cf9b46bd25bf5f0595bbe24655141438e7a100b",
+            hex::encode(&signature)
+        );
+        assert!(keypair.public.verify(b"", &signature).is_ok());
+    }
+
+    #[test]
+    fn network_address() {
+        let mut seed = [0; 32];
+        hex::decode_to_slice(
+            "9d61b19deffd5a60ba844af492ec2cc44449c5697b326919703bac031cae7f60",
+            &mut seed,
+        )
+        .expect("seed");
+        let keypair = Keypair::gen_keypair_from_seed(&seed)
+            .expect("keypair")
+            .with_network(Network::Testnet);
+        let address = "1bgVveHWnmV5qmrw5cgfKv4sZH4naUnpfBcDuEm6Sf9oyZE26J8";
+        assert_eq!(address, keypair.public.to_b58().expect("address"));
+        assert_eq!(0x11, keypair.pubkey_bin().to_vec()[0]);
+
+        let pubkey_bin = PubKeyBin::from_b58(address).expect("pubkey bin");
+        assert_eq!(Network::Testnet, pubkey_bin.network().expect("network"));
+        assert_eq!(KeyType::Ed25519, pubkey_bin.key_type().expect("key type"));
+        let mainnet_pubkey_bin = Keypair::gen_keypair_from_seed(&seed)
+            .expect("keypair")
+            .pubkey_bin();
+        assert!(pubkey_bin != mainnet_pubkey_bin);
+        assert!(pubkey_bin.same_key(&mainnet_pubkey_bin).expect("same key"));
+        assert!(!pubkey_bin
+            .same_key(&Keypair::gen_keypair().expect("keypair").pubkey_bin())
+            .expect("same key"));
+        assert_eq!(
+            keypair.public,
+            PublicKey::from_b58(address).expect("public key")
+        );
+
         let mut buffer = Vec::new();
-        pk.write(&mut buffer).expect("Failed to encode public key");
+        keypair.write(&mut buffer).expect("keypair written");
+        let decoded = Keypair::read(&mut Cursor::new(buffer)).expect("keypair read");
+        assert_eq!(Network::Testnet, decoded.network());
+        assert_eq!(keypair, decoded);
 
-        let decoded =
-            PublicKey::read(&mut Cursor::new(buffer)).expect("Failed to decode public key");
-        assert_eq!(pk, decoded);
+        // Same key with an unknown network in the upper bits
+        assert!(
+            PubKeyBin::from_b58("128nQkMU2RaJhzuADatceb3fGD6vGykGqFQjwodmSM9G1sVWz7V7").is_err()
+        );
+    }
+
+    #[test]
+    fn ecc_compact_vector() {
+        let secret =
+            hex::decode("fa9a7346dc0b3a817820bfdcdb49b16233976225d43db3da3ca73019c857f5c8")
+                .expect("secret");
+        let keypair = Keypair::from_ecc_compact_secret(&secret).expect("keypair");
+        let address = "11aKAxbDbHxQ3mvxrj3zrs7GbVFwYADkyihcFF4GjePesCCw2d8";
+        assert_eq!(address, keypair.public.to_b58().expect("address"));
+        assert_eq!(
+            "004ba455d397234a08ab90eaead632f62f02615ba1b06cd3631e4931b0ad4df1d6",
+            hex::encode(keypair.pubkey_bin().to_vec())
+        );
+
+        // The full public key is recovered from the address
+        let public = PublicKey::from_b58(address).expect("public key");
+        assert_eq!(keypair.public, public);
+
+        // A DER encoded ECDSA signature over the SHA-256 digest of the
+        // message
+        let signature = hex::decode(
+            "304402207370bd46d7cfc031ce3fb64fc55f9c5e93013fd64e7b4a96f1257b6ccd9fdfc50220\
+             07ae5b041e0a7ec01ebf7d1ec2fa64a6b95ddd988e22028bbc90403ed4fa3206",
+        )
+        .expect("signature");
+        assert!(public.verify(b"hello world", &signature).is_ok());
+        assert!(public.verify(b"hello world!", &signature).is_err());
+
+        // Negating the secret key gives the point with the other y
+        // coordinate, which is not compact
+        let order = hex::decode("ffffffff00000000ffffffffffffffffbce6faada7179e84f3b9cac2fc632551")
+            .expect("order");
+        let mut negated = [0u8; 32];
+        let mut borrow = 0i16;
+        for i in (0..32).rev() {
+            let diff = order[i] as i16 - secret[i] as i16 - borrow;
+            negated[i] = diff.rem_euclid(256) as u8;
+            borrow = if diff < 0 { 1 } else { 0 };
+        }
+        assert!(Keypair::from_ecc_compact_secret(&negated).is_err());
+    }
+
+    #[test]
+    fn invalid_key_type() {
+        let mut pubkey_bin = Keypair::gen_keypair().expect("keypair").pubkey_bin();
+        pubkey_bin.0[0] = 2;
+        assert!(PublicKey::try_from(pubkey_bin).is_err());
+    }
+
+    #[test]
+    fn keypair_seed() {
+        let keypair = Keypair::gen_keypair().expect("keypair");
+        assert_eq!(
+            keypair,
+            Keypair::gen_keypair_from_seed(&keypair.seed().expect("seed")).expect("keypair")
+        );
+    }
+
+    #[test]
+    fn keypair_from_secret_key() {
+        let keypair = Keypair::gen_keypair().expect("keypair");
+        let mut secret = match &keypair.secret {
+            SecretKey::Ed25519(secret) => secret.to_vec(),
+            _ => panic!("expected ed25519 key"),
+        };
+        assert_eq!(keypair, Keypair::from_secret_key(&secret).expect("keypair"));
+        assert!(Keypair::from_secret_key(&secret[..32]).is_err());
+        secret[63] ^= 1;
+        assert!(Keypair::from_secret_key(&secret).is_err());
+    }
+
+    #[test]
+    fn secret_hidden() {
+        for key_type in KEY_TYPES.iter() {
+            let keypair = Keypair::gen_keypair_with_type(*key_type).expect("keypair");
+            let mut buffer = Vec::new();
+            keypair.write(&mut buffer).expect("keypair write");
+            let secret = &buffer[1..buffer.len() - 32];
+            let address = keypair.pubkey_bin().to_string();
+
+            for shown in &[format!("{}", keypair), format!("{:?}", keypair)] {
+                assert_eq!(&format!("Keypair({})", address), shown);
+            }
+            let shown = format!("{:?}", keypair.secret);
+            assert!(shown.ends_with("(****)"));
+            assert!(!shown.contains(&hex::encode(secret)));
+            assert!(!shown.contains(&format!("{:?}", secret)));
+        }
+    }
+
+    #[test]
+    fn roundtrip_public_key() {
+        for key_type in KEY_TYPES.iter() {
+            let pk = Keypair::gen_keypair_with_type(*key_type)
+                .expect("keypair")
+                .public;
+            let mut buffer = Vec::new();
+            pk.write(&mut buffer).expect("Failed to encode public key");
+
+            let decoded =
+                PublicKey::read(&mut Cursor::new(buffer)).expect("Failed to decode public key");
+            assert_eq!(pk, decoded);
+        }
     }
 
     #[test]
     fn roundtrip_b58_public_key() {
-        let pk = Keypair::gen_keypair().public;
-        let encoded = pk.to_b58().expect("Failed to encode public key");
-        let decoded = PublicKey::from_b58(&encoded).expect("Failed to decode public key");
-        assert_eq!(pk, decoded);
+        for key_type in KEY_TYPES.iter() {
+            let pk = Keypair::gen_keypair_with_type(*key_type)
+                .expect("keypair")
+                .public;
+            let encoded = pk.to_b58().expect("Failed to encode public key");
+            let decoded = PublicKey::from_b58(&encoded).expect("Failed to decode public key");
+            assert_eq!(pk, decoded);
+        }
     }
 }
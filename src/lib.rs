@@ -8,11 +8,18 @@ extern crate lazy_static;
 extern crate serde_json;
 
 pub mod cmd;
+pub mod encrypt;
 pub mod format;
+pub mod frost;
 pub mod keypair;
+pub mod message;
 pub mod mnemonic;
 pub mod pwhash;
 pub mod result;
+pub mod secret;
+pub mod signer;
 pub mod staking;
+pub mod token;
 pub mod traits;
+pub mod vanity;
 pub mod wallet;
@@ -1,7 +1,8 @@
 use helium_wallet::{
     cmd::{
-        balance, burn, create, hotspots, htlc, info, multisig, onboard, oracle, oui, pay, request,
-        securities, upgrade, vars, verify, Opts,
+        agent, balance, burn, create, encrypt, export, frost, hotspots, htlc, info, message,
+        multisig, onboard, oracle, oui, passwd, pay, pwhash, request, reshard, securities, signer,
+        slot, token, upgrade, vars, verify, Opts,
     },
     result::Result,
 };
@@ -21,10 +22,21 @@ pub struct Cli {
 pub enum Cmd {
     Info(info::Cmd),
     Verify(verify::Cmd),
+    SignMessage(message::SignCmd),
+    VerifyMessage(message::VerifyCmd),
+    Token(token::Cmd),
+    Encrypt(encrypt::EncryptCmd),
+    Decrypt(encrypt::DecryptCmd),
     Balance(balance::Cmd),
     Hotspots(hotspots::Cmd),
     Create(create::Cmd),
     Upgrade(upgrade::Cmd),
+    #[structopt(visible_alias = "rekey")]
+    Passwd(passwd::Cmd),
+    Reshard(reshard::Cmd),
+    Slot(slot::Cmd),
+    Pwhash(pwhash::Cmd),
+    Export(export::Cmd),
     Pay(pay::Cmd),
     Htlc(htlc::Cmd),
     Oui(oui::Cmd),
@@ -35,6 +47,9 @@ pub enum Cmd {
     Multisig(multisig::Cmd),
     Request(request::Cmd),
     Vars(vars::Cmd),
+    Signer(signer::Cmd),
+    Agent(agent::Cmd),
+    Frost(frost::Cmd),
 }
 
 fn main() {
@@ -49,10 +64,20 @@ fn run(cli: Cli) -> Result {
     match cli.cmd {
         Cmd::Info(cmd) => cmd.run(cli.opts),
         Cmd::Verify(cmd) => cmd.run(cli.opts),
+        Cmd::SignMessage(cmd) => cmd.run(cli.opts),
+        Cmd::VerifyMessage(cmd) => cmd.run(cli.opts),
+        Cmd::Token(cmd) => cmd.run(cli.opts),
+        Cmd::Encrypt(cmd) => cmd.run(cli.opts),
+        Cmd::Decrypt(cmd) => cmd.run(cli.opts),
         Cmd::Balance(cmd) => cmd.run(cli.opts),
         Cmd::Hotspots(cmd) => cmd.run(cli.opts),
         Cmd::Create(cmd) => cmd.run(cli.opts),
         Cmd::Upgrade(cmd) => cmd.run(cli.opts),
+        Cmd::Passwd(cmd) => cmd.run(cli.opts),
+        Cmd::Reshard(cmd) => cmd.run(cli.opts),
+        Cmd::Slot(cmd) => cmd.run(cli.opts),
+        Cmd::Pwhash(cmd) => cmd.run(cli.opts),
+        Cmd::Export(cmd) => cmd.run(cli.opts),
         Cmd::Pay(cmd) => cmd.run(cli.opts),
         Cmd::Htlc(cmd) => cmd.run(cli.opts),
         Cmd::Oui(cmd) => cmd.run(cli.opts),
@@ -63,5 +88,8 @@ fn run(cli: Cli) -> Result {
         Cmd::Multisig(cmd) => cmd.run(cli.opts),
         Cmd::Request(cmd) => cmd.run(cli.opts),
         Cmd::Vars(cmd) => cmd.run(cli.opts),
+        Cmd::Signer(cmd) => cmd.run(cli.opts),
+        Cmd::Agent(cmd) => cmd.run(cli.opts),
+        Cmd::Frost(cmd) => cmd.run(cli.opts),
     }
 }
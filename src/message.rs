@@ -0,0 +1,126 @@
+//! Signing of arbitrary messages, to prove control of a wallet address
+//! off-chain.
+//!
+//! A message is never signed directly. The signature is over a fixed
+//! domain prefix followed by the SHA-256 hash of the message. The
+//! prefix starts with a zero byte, which is an invalid protobuf tag, so
+//! signed message bytes never decode as a transaction and a message
+//! signature can not be replayed as a transaction signature.
+
+use crate::{
+    keypair::{PubKeyBin, PublicKey, Verify},
+    result::Result,
+    signer::Signer,
+    traits::{B58, B64},
+};
+use serde_derive::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::convert::TryFrom;
+
+/// The prefix of all signed message bytes
+pub const MESSAGE_DOMAIN: &[u8] = b"\x00Helium Signed Message v1\n";
+
+/// The transaction type signers see for message signatures
+pub const MESSAGE_TXN_TYPE: &str = "message";
+
+/// A message signature as output by `sign-message` and accepted by
+/// `verify-message`
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub struct SignedMessage {
+    pub address: String,
+    /// The hex encoded SHA-256 hash of the message
+    pub hash: String,
+    /// The base64 encoded signature
+    pub signature: String,
+}
+
+/// The SHA-256 hash of a message
+pub fn message_hash(message: &[u8]) -> Vec<u8> {
+    Sha256::digest(message).to_vec()
+}
+
+/// The bytes that are signed for a message with the given hash
+pub fn signing_bytes(hash: &[u8]) -> Vec<u8> {
+    let mut bytes = MESSAGE_DOMAIN.to_vec();
+    bytes.extend_from_slice(hash);
+    bytes
+}
+
+/// Returns the summary signers show for the given signing bytes, or an
+/// error if they are not the signing bytes of a message
+pub fn summary(data: &[u8]) -> Result<String> {
+    if data.len() != MESSAGE_DOMAIN.len() + 32 || !data.starts_with(MESSAGE_DOMAIN) {
+        return Err("Not a signed message".into());
+    }
+    Ok(format!(
+        "Sign message with hash {}",
+        hex::encode(&data[MESSAGE_DOMAIN.len()..])
+    ))
+}
+
+/// Signs a message with the given signer
+pub fn sign_message(signer: &dyn Signer, message: &[u8]) -> Result<SignedMessage> {
+    let hash = message_hash(message);
+    let data = signing_bytes(&hash);
+    let signature = signer.sign_txn(MESSAGE_TXN_TYPE, &summary(&data)?, &data)?;
+    Ok(SignedMessage {
+        address: signer.pubkey_bin().to_b58()?,
+        hash: hex::encode(hash),
+        signature: signature.to_b64()?,
+    })
+}
+
+impl SignedMessage {
+    /// Checks that this is a signature of the given message by the key
+    /// of its address
+    pub fn verify(&self, message: &[u8]) -> Result {
+        let hash = message_hash(message);
+        if hex::decode(&self.hash)? != hash {
+            return Err("Message hash does not match the message".into());
+        }
+        let public_key = PublicKey::try_from(PubKeyBin::from_b58(&self.address)?)?;
+        let signature = Vec::<u8>::from_b64(&self.signature)?;
+        public_key.verify(&signing_bytes(&hash), &signature)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keypair::{KeyType, Keypair};
+    use helium_api::{BlockchainTxn, Message};
+
+    #[test]
+    fn sign_verify() {
+        for key_type in &[KeyType::Ed25519, KeyType::EccCompact] {
+            let keypair = Keypair::gen_keypair_with_type(*key_type).expect("keypair");
+            let signed = sign_message(&keypair, b"hello world").expect("signed message");
+            assert_eq!(
+                keypair.pubkey_bin().to_b58().expect("address"),
+                signed.address
+            );
+            assert_eq!(
+                "b94d27b9934d3e08a52e52d7da7dabfac484efe37a5380ee9088f7ace2efcde9",
+                signed.hash
+            );
+            assert!(signed.verify(b"hello world").is_ok());
+            assert!(signed.verify(b"hello there").is_err());
+
+            let mut other = signed.clone();
+            other.address = Keypair::gen_keypair()
+                .expect("keypair")
+                .pubkey_bin()
+                .to_b58()
+                .expect("address");
+            assert!(other.verify(b"hello world").is_err());
+        }
+    }
+
+    #[test]
+    fn not_a_transaction() {
+        let data = signing_bytes(&message_hash(b"hello world"));
+        assert!(BlockchainTxn::decode(&data[..]).is_err());
+        assert!(summary(&data).is_ok());
+        assert!(summary(b"hello world").is_err());
+    }
+}
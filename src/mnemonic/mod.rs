@@ -1,36 +1,242 @@
-use crate::result::Result;
+use crate::{
+    result::Result,
+    secret::{self, Secret},
+};
+use hmac::{Hmac, Mac};
 use regex::Regex;
+use sha2::{Digest, Sha256, Sha512};
+use std::{fmt, ops::Deref, str, str::FromStr};
+use structopt::clap::arg_enum;
+use unicode_normalization::UnicodeNormalization;
 include!(concat!(env!("OUT_DIR"), "/english.rs"));
+include!(concat!(env!("OUT_DIR"), "/spanish.rs"));
+include!(concat!(env!("OUT_DIR"), "/french.rs"));
+include!(concat!(env!("OUT_DIR"), "/italian.rs"));
+include!(concat!(env!("OUT_DIR"), "/portuguese.rs"));
+include!(concat!(env!("OUT_DIR"), "/czech.rs"));
+include!(concat!(env!("OUT_DIR"), "/japanese.rs"));
+include!(concat!(env!("OUT_DIR"), "/korean.rs"));
+include!(concat!(env!("OUT_DIR"), "/chinese_simplified.rs"));
+include!(concat!(env!("OUT_DIR"), "/chinese_traditional.rs"));
 
 type WordList = &'static [&'static str];
 
+/// The languages of the standard BIP39 wordlists. The wordlists are
+/// stored NFKD normalized.
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Language {
     English,
+    Spanish,
+    French,
+    Italian,
+    Portuguese,
+    Czech,
+    Japanese,
+    Korean,
+    ChineseSimplified,
+    ChineseTraditional,
 }
 
+impl Language {
+    pub const ALL: [Language; 10] = [
+        Language::English,
+        Language::Spanish,
+        Language::French,
+        Language::Italian,
+        Language::Portuguese,
+        Language::Czech,
+        Language::Japanese,
+        Language::Korean,
+        Language::ChineseSimplified,
+        Language::ChineseTraditional,
+    ];
+
+    /// The names accepted for a language on the command line
+    pub const NAMES: [&'static str; 10] = [
+        "english",
+        "spanish",
+        "french",
+        "italian",
+        "portuguese",
+        "czech",
+        "japanese",
+        "korean",
+        "chinese-simplified",
+        "chinese-traditional",
+    ];
+
+    pub fn name(self) -> &'static str {
+        Self::NAMES[Self::ALL.iter().position(|l| *l == self).unwrap()]
+    }
+}
+
+impl fmt::Display for Language {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Language {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match Self::NAMES
+            .iter()
+            .position(|name| name.eq_ignore_ascii_case(s))
+        {
+            Some(idx) => Ok(Self::ALL[idx]),
+            None => Err(format!("Unknown language: {}", s)),
+        }
+    }
+}
+
+arg_enum! {
+    /// The way seed words are turned into a keypair seed. Mobile seed
+    /// words are 12 words with all 0 checksum bits and the entropy
+    /// repeated into the seed, as generated by the mobile wallet. BIP39
+    /// seed words have a real checksum and are turned into a BIP39 seed
+    /// with an optional passphrase, and then into a key using SLIP-0010
+    /// along `BIP39_DERIVATION_PATH`.
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub enum SeedType {
+        Mobile,
+        Bip39,
+    }
+}
+
+/// The number of words allowed in a BIP39 mnemonic
+const BIP39_WORD_COUNTS: [usize; 5] = [12, 15, 18, 21, 24];
+/// The number of PBKDF2 rounds used to derive a BIP39 seed
+const BIP39_PBKDF2_ROUNDS: usize = 2048;
+/// The HMAC key used to derive a SLIP-0010 ed25519 master key
+const SLIP10_ED25519_KEY: &[u8] = b"ed25519 seed";
+/// The SLIP-0010 path the key of BIP39 seed words is derived along,
+/// m/44'/904'/0'/0'/0'. 904 is the SLIP-0044 coin type of Helium.
+/// Every index is hardened since ed25519 keys only have hardened
+/// derivation.
+pub const BIP39_DERIVATION_PATH: [u32; 5] = [44, 904, 0, 0, 0];
+/// Added to an index to make it a hardened index
+const SLIP10_HARDENED: u32 = 0x8000_0000;
+
 fn get_wordlist(language: Language) -> WordList {
     match language {
         Language::English => WORDS_ENGLISH,
+        Language::Spanish => WORDS_SPANISH,
+        Language::French => WORDS_FRENCH,
+        Language::Italian => WORDS_ITALIAN,
+        Language::Portuguese => WORDS_PORTUGUESE,
+        Language::Czech => WORDS_CZECH,
+        Language::Japanese => WORDS_JAPANESE,
+        Language::Korean => WORDS_KOREAN,
+        Language::ChineseSimplified => WORDS_CHINESE_SIMPLIFIED,
+        Language::ChineseTraditional => WORDS_CHINESE_TRADITIONAL,
+    }
+}
+
+/// Converts a mnemonic to a seed that can be used to generate a
+/// keypair. A passphrase and languages other than English are only
+/// supported for BIP39 seed words. When no language is given it is
+/// detected from the words. The words are zeroed once the seed is
+/// derived.
+pub fn mnemonic_to_seed(
+    words: Vec<String>,
+    seed_type: SeedType,
+    language: Option<Language>,
+    passphrase: &str,
+) -> Result<Secret<[u8; 32]>> {
+    let seed = match seed_type {
+        SeedType::Mobile if !passphrase.is_empty() => {
+            Err("A passphrase is not supported for mobile seed words".into())
+        }
+        SeedType::Mobile if language.unwrap_or(Language::English) != Language::English => {
+            Err("Mobile seed words are only supported in english".into())
+        }
+        SeedType::Mobile => mnemonic_to_entropy(&words),
+        SeedType::Bip39 => bip39_seed(&words, language, passphrase)
+            .and_then(|seed| slip10_ed25519_key(&seed[..], &BIP39_DERIVATION_PATH)),
+    };
+    words.into_iter().for_each(secret::wipe);
+    seed
+}
+
+/// Detects the language of a mnemonic. If the words are found in more
+/// than one wordlist, the languages for which the words have a valid
+/// BIP39 checksum are considered. An error is returned if the language
+/// is still ambiguous.
+pub fn detect_language(words: &[String]) -> Result<Language> {
+    let words = NormalizedWords::new(words);
+    let candidates: Vec<Language> = Language::ALL
+        .iter()
+        .filter(|language| {
+            let wordlist = get_wordlist(**language);
+            words.iter().all(|word| wordlist.contains(&word.as_str()))
+        })
+        .cloned()
+        .collect();
+    match candidates.len() {
+        0 => Err("Seed words not found in any wordlist".into()),
+        1 => Ok(candidates[0]),
+        _ => {
+            let valid: Vec<Language> = candidates
+                .iter()
+                .filter(|language| bip39_mnemonic_to_entropy(&words, Some(**language)).is_ok())
+                .cloned()
+                .collect();
+            match valid.len() {
+                1 => Ok(valid[0]),
+                _ => {
+                    let names: Vec<&str> = candidates.iter().map(|l| l.name()).collect();
+                    Err(format!(
+                        "Seed words are ambiguous between {}, specify a language",
+                        names.join(", ")
+                    )
+                    .into())
+                }
+            }
+        }
+    }
+}
+
+/// NFKD normalizes and lowercases a word so it can be compared with
+/// the wordlists
+pub fn normalize_word(word: &str) -> String {
+    let nfkd = word.nfkd().collect::<String>();
+    let normalized = nfkd.to_lowercase();
+    secret::wipe(nfkd);
+    normalized
+}
+
+/// Normalized seed words, which are zeroed when dropped
+struct NormalizedWords(Vec<String>);
+
+impl NormalizedWords {
+    fn new(words: &[String]) -> Self {
+        Self(words.iter().map(|w| normalize_word(w)).collect())
+    }
+}
+
+impl Deref for NormalizedWords {
+    type Target = [String];
+
+    fn deref(&self) -> &[String] {
+        &self.0
+    }
+}
+
+impl Drop for NormalizedWords {
+    fn drop(&mut self) {
+        self.0.drain(..).for_each(secret::wipe);
     }
 }
 
 /// Converts a 12 word mnemonic to a entropy that can be used to
 /// generate a keypair
-pub fn mnemonic_to_entropy(words: Vec<String>) -> Result<[u8; 32]> {
+pub fn mnemonic_to_entropy(words: &[String]) -> Result<Secret<[u8; 32]>> {
     if words.len() != 12 {
         return Err("Invalid number of seed words".into());
     }
-    let wordlist = get_wordlist(Language::English);
-
-    let mut bit_vec = Vec::with_capacity(words.len());
-    for word in words.iter() {
-        let idx_bits = match wordlist.iter().position(|s| *s == word.to_lowercase()) {
-            Some(idx) => format!("{:011b}", idx),
-            _ => return Err(format!("Seed word {} not found in wordlist", word).into()),
-        };
-        bit_vec.push(idx_bits);
-    }
-    let bits = bit_vec.join("");
+    let bits = words_to_bits(words, get_wordlist(Language::English))?;
+    let bits = str::from_utf8(&bits)?;
 
     let divider_index: usize = ((bits.len() as f64 / 33.0) * 32.0).floor() as usize;
     let (entropy_bits, checksum_bits) = bits.split_at(divider_index);
@@ -40,20 +246,202 @@ pub fn mnemonic_to_entropy(words: Vec<String>) -> Result<[u8; 32]> {
         return Err("invalid checksum".into());
     }
 
+    let entropy_base = Secret::new(bits_to_bytes(entropy_bits));
+    let mut entropy_bytes = Secret::<[u8; 32]>::zeroed();
+    entropy_bytes[..16].copy_from_slice(&entropy_base);
+    entropy_bytes[16..].copy_from_slice(&entropy_base);
+
+    Ok(entropy_bytes)
+}
+
+/// Converts 16 bytes of entropy to a 12 word mnemonic that can be
+/// read back with `mnemonic_to_entropy`. Like the mobile wallet the
+/// checksum bits are left as all 0.
+pub fn entropy_to_mnemonic(entropy: &[u8; 16]) -> Vec<String> {
+    let mut bits = bytes_to_bits(entropy);
+    bits.push_str("0000");
+    let words = bits_to_words(&bits, get_wordlist(Language::English));
+    secret::wipe(bits);
+    words
+}
+
+/// Recovers the mobile seed words for a keypair seed. This is only
+/// possible for seeds that were created from mobile seed words, since
+/// BIP39 seeds can not be reversed.
+pub fn seed_to_mnemonic(seed: &[u8; 32]) -> Result<Vec<String>> {
+    if seed[..16] != seed[16..] {
+        return Err(
+            "Seed words can only be recovered for wallets created from mobile seed words. \
+                    The key of BIP39 seed words can not be turned back into the words, export \
+                    it as hex or b58 instead"
+                .into(),
+        );
+    }
+    let mut entropy = Secret::<[u8; 16]>::zeroed();
+    entropy.copy_from_slice(&seed[..16]);
+    Ok(entropy_to_mnemonic(&entropy))
+}
+
+/// Converts a 12 to 24 word BIP39 mnemonic to the entropy it encodes
+/// after verifying its checksum. When no language is given it is
+/// detected from the words.
+pub fn bip39_mnemonic_to_entropy(
+    words: &[String],
+    language: Option<Language>,
+) -> Result<Secret<Vec<u8>>> {
+    if !BIP39_WORD_COUNTS.contains(&words.len()) {
+        return Err("Invalid number of seed words".into());
+    }
+    let language = match language {
+        Some(language) => language,
+        None => detect_language(words)?,
+    };
+    let bits = words_to_bits(words, get_wordlist(language))?;
+    let bits = str::from_utf8(&bits)?;
+
+    // Every 32 bits of entropy add one bit of checksum
+    let divider_index = bits.len() / 33 * 32;
+    let (entropy_bits, checksum_bits) = bits.split_at(divider_index);
+    let entropy = Secret::new(bits_to_bytes(entropy_bits));
+    if checksum_bits != bip39_checksum_bits(&entropy) {
+        return Err("invalid checksum".into());
+    }
+    Ok(entropy)
+}
+
+/// Converts 16 to 32 bytes of entropy, in multiples of 4 bytes, to a
+/// BIP39 mnemonic in the given language
+pub fn entropy_to_bip39_mnemonic(entropy: &[u8], language: Language) -> Result<Vec<String>> {
+    if entropy.len() < 16 || entropy.len() > 32 || entropy.len() % 4 != 0 {
+        return Err("Invalid entropy length".into());
+    }
+    let mut bits = bytes_to_bits(entropy);
+    bits.push_str(&bip39_checksum_bits(entropy));
+    let words = bits_to_words(&bits, get_wordlist(language));
+    secret::wipe(bits);
+    Ok(words)
+}
+
+/// Derives the 64 byte BIP39 seed for a mnemonic and passphrase. Both
+/// are NFKD normalized first.
+pub fn bip39_seed(
+    words: &[String],
+    language: Option<Language>,
+    passphrase: &str,
+) -> Result<Secret<[u8; 64]>> {
+    bip39_mnemonic_to_entropy(words, language)?;
+    let mnemonic = Secret::new(NormalizedWords::new(words).join(" "));
+    let salt = nfkd_secret(&["mnemonic", passphrase]);
+    let mut seed = Secret::<[u8; 64]>::zeroed();
+    pbkdf2::pbkdf2::<Hmac<Sha512>>(mnemonic.as_bytes(), &salt, BIP39_PBKDF2_ROUNDS, &mut *seed);
+    Ok(seed)
+}
+
+/// Returns the NFKD normalized concatenation of the given parts as a
+/// secret. The normalized length is counted first so that the buffer
+/// never grows and leaves a copy behind.
+fn nfkd_secret(parts: &[&str]) -> Secret<Vec<u8>> {
+    let nfkd = || parts.iter().flat_map(|part| part.chars()).nfkd();
+    let mut data = Vec::with_capacity(nfkd().map(char::len_utf8).sum());
+    for c in nfkd() {
+        data.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+    }
+    Secret::new(data)
+}
+
+/// Derives the SLIP-0010 ed25519 key for a given seed along a path of
+/// indexes, which are all taken as hardened indexes
+fn slip10_ed25519_key(seed: &[u8], path: &[u32]) -> Result<Secret<[u8; 32]>> {
+    let mut code = slip10_hmac(SLIP10_ED25519_KEY, &[seed])?;
+    for index in path {
+        if *index >= SLIP10_HARDENED {
+            return Err("Invalid derivation index".into());
+        }
+        let index = (index + SLIP10_HARDENED).to_be_bytes();
+        code = slip10_hmac(&code[32..], &[&[0], &code[..32], &index])?;
+    }
+    let mut key = Secret::<[u8; 32]>::zeroed();
+    key.copy_from_slice(&code[..32]);
+    Ok(key)
+}
+
+/// Returns an HMAC-SHA512 over the given data, of which the first half
+/// is the key and the second half the chain code
+fn slip10_hmac(key: &[u8], data: &[&[u8]]) -> Result<Secret<[u8; 64]>> {
+    let mut hmac = match Hmac::<Sha512>::new_varkey(key) {
+        Err(_) => return Err("Failed to initialize hmac".into()),
+        Ok(m) => m,
+    };
+    for data in data {
+        hmac.input(data);
+    }
+    let mut code = hmac.result().code();
+    let mut result = Secret::<[u8; 64]>::zeroed();
+    result.copy_from_slice(&code);
+    sodiumoxide::utils::memzero(&mut code);
+    Ok(result)
+}
+
+fn bip39_checksum_bits(entropy: &[u8]) -> String {
+    let hash = Sha256::digest(entropy);
+    let mut bits = bytes_to_bits(&hash);
+    bits.truncate(entropy.len() * 8 / 32);
+    bits
+}
+
+/// Returns the bits of the wordlist indexes of the words as a secret
+/// string of "0" and "1" characters
+fn words_to_bits(words: &[String], wordlist: WordList) -> Result<Secret<Vec<u8>>> {
+    let mut bits = Secret::new(vec![b'0'; words.len() * 11]);
+    let normalized_words = NormalizedWords::new(words);
+    for ((word, normalized), idx_bits) in words
+        .iter()
+        .zip(normalized_words.iter())
+        .zip(bits.chunks_mut(11))
+    {
+        let idx = match wordlist.iter().position(|s| s == normalized) {
+            Some(idx) => idx,
+            _ => return Err(format!("Seed word {} not found in wordlist", word).into()),
+        };
+        for (bit, c) in idx_bits.iter_mut().enumerate() {
+            *c = b'0' + (idx >> (10 - bit) & 1) as u8;
+        }
+    }
+    Ok(bits)
+}
+
+fn bits_to_words(bits: &str, wordlist: WordList) -> Vec<String> {
     lazy_static! {
-        static ref RE_BYTES: Regex = Regex::new("(.{1,8})").unwrap();
+        static ref RE_WORDS: Regex = Regex::new("(.{11})").unwrap();
     }
 
-    let mut entropy_base = [0u8; 16];
-    for (idx, matched) in RE_BYTES.find_iter(&entropy_bits).enumerate() {
-        entropy_base[idx] = binary_to_bytes(matched.as_str()) as u8;
+    RE_WORDS
+        .find_iter(bits)
+        .map(|matched| wordlist[binary_to_bytes(matched.as_str())].to_string())
+        .collect()
+}
+
+/// Returns the bits of the bytes as a string of "0" and "1" characters,
+/// with room for checksum bits to be added without growing
+fn bytes_to_bits(bytes: &[u8]) -> String {
+    let mut bits = String::with_capacity(bytes.len() * 8 + 8);
+    for byte in bytes {
+        for bit in (0..8).rev() {
+            bits.push(if byte >> bit & 1 == 1 { '1' } else { '0' });
+        }
     }
+    bits
+}
 
-    let mut entropy_bytes = [0u8; 32];
-    entropy_bytes[..16].copy_from_slice(&entropy_base);
-    entropy_bytes[16..].copy_from_slice(&entropy_base);
+fn bits_to_bytes(bits: &str) -> Vec<u8> {
+    lazy_static! {
+        static ref RE_BYTES: Regex = Regex::new("(.{1,8})").unwrap();
+    }
 
-    Ok(entropy_bytes)
+    RE_BYTES
+        .find_iter(bits)
+        .map(|matched| binary_to_bytes(matched.as_str()) as u8)
+        .collect()
 }
 
 /// Converts a binary string into an integer
@@ -66,6 +454,10 @@ mod tests {
     use super::*;
     use bs58;
 
+    fn split_words(words: &str) -> Vec<String> {
+        words.split_whitespace().map(|w| w.to_string()).collect()
+    }
+
     #[test]
     fn decode_words() {
         // The words and entryopy here were generated from the JS mobile-wallet implementation
@@ -74,8 +466,193 @@ mod tests {
             .into_vec()
             .expect("decoded entropy");
 
-        let word_list = words.split_whitespace().map(|w| w.to_string()).collect();
-        let entropy = mnemonic_to_entropy(word_list).expect("entropy");
-        assert_eq!(expected_entropy, entropy);
+        let word_list = split_words(words);
+        let entropy = mnemonic_to_entropy(&word_list).expect("entropy");
+        assert_eq!(expected_entropy, entropy[..]);
+
+        let seed = mnemonic_to_seed(split_words(words), SeedType::Mobile, None, "").expect("seed");
+        assert_eq!(expected_entropy, seed[..]);
+        assert!(mnemonic_to_seed(split_words(words), SeedType::Mobile, None, "TREZOR").is_err());
+        // A mobile mnemonic does not have a valid BIP39 checksum
+        assert!(mnemonic_to_seed(split_words(words), SeedType::Bip39, None, "").is_err());
+    }
+
+    #[test]
+    fn encode_words() {
+        let words = "catch poet clog intact scare jacket throw palm illegal buyer allow figure";
+        let entropy = bs58::decode("3RrA1FDa6mdw5JwKbUxEbZbMcJgSyWjhNwxsbX5pSos8")
+            .into_vec()
+            .expect("decoded entropy");
+        let mut entropy_base = [0u8; 16];
+        entropy_base.copy_from_slice(&entropy[..16]);
+
+        let word_list = entropy_to_mnemonic(&entropy_base);
+        assert_eq!(words, word_list.join(" "));
+        assert_eq!(
+            entropy,
+            mnemonic_to_entropy(&word_list).expect("entropy").to_vec()
+        );
+
+        let mut seed = [0u8; 32];
+        seed.copy_from_slice(&entropy);
+        assert_eq!(words, seed_to_mnemonic(&seed).expect("words").join(" "));
+        seed[31] ^= 1;
+        assert!(seed_to_mnemonic(&seed).is_err());
+    }
+
+    #[test]
+    fn bip39_words() {
+        // Test vectors from https://github.com/trezor/python-mnemonic
+        // with SLIP-0010 ed25519 keys derived from the seeds along
+        // m/44'/904'/0'/0'/0'
+        let vectors = [
+            (
+                "00000000000000000000000000000000",
+                "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon \
+                 abandon about",
+                "c55257c360c07c72029aebc1b53c05ed0362ada38ead3e3e9efa3708e53495531f09a6987599d18264\
+                 c1e1c92f2cf141630c7a3c4ab7c81b2f001698e7463b04",
+                "7ff9991572cdde53217593686b978138be8e91743d257144b99f4cbcdc13e591",
+            ),
+            (
+                "7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f",
+                "legal winner thank year wave sausage worth useful legal winner thank year wave \
+                 sausage worth useful legal winner thank year wave sausage worth title",
+                "bc09fca1804f7e69da93c2f2028eb238c227f2e9dda30cd63699232578480a4021b146ad717fbb7e45\
+                 1ce9eb835f43620bf5c514db0f8add49f5d121449d3e87",
+                "3782d8831c41b4f653b77f0d8e17696a78d5815d05d998c25934f843247cfa2c",
+            ),
+        ];
+        for (entropy, words, seed, key) in vectors.iter() {
+            let entropy = hex::decode(entropy).expect("entropy");
+            let word_list = split_words(words);
+            assert_eq!(
+                word_list,
+                entropy_to_bip39_mnemonic(&entropy, Language::English).expect("mnemonic")
+            );
+            assert_eq!(
+                entropy,
+                bip39_mnemonic_to_entropy(&word_list, None).expect("entropy")[..]
+            );
+            assert_eq!(
+                seed.to_string(),
+                hex::encode(&bip39_seed(&word_list, None, "TREZOR").expect("seed")[..])
+            );
+            assert_eq!(
+                key.to_string(),
+                hex::encode(
+                    &mnemonic_to_seed(word_list, SeedType::Bip39, None, "TREZOR").expect("key")[..]
+                )
+            );
+        }
+    }
+
+    #[test]
+    fn bip39_invalid_checksum() {
+        let words = split_words(
+            "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon \
+             abandon abandon",
+        );
+        assert!(bip39_mnemonic_to_entropy(&words, None).is_err());
+        assert!(bip39_mnemonic_to_entropy(&words[..11], None).is_err());
+    }
+
+    #[test]
+    fn bip39_japanese_words() {
+        // Test vectors from https://github.com/bip32JP/bip32JP.github.io
+        // with words separated by ideographic spaces
+        let passphrase = "㍍ガバヴァぱばぐゞちぢ十人十色";
+        let vectors = [
+            (
+                "00000000000000000000000000000000",
+                "あいこくしん　あいこくしん　あいこくしん　あいこくしん　あいこくしん　あいこくしん　\
+                 あいこくしん　あいこくしん　あいこくしん　あいこくしん　あいこくしん　あおぞら",
+                "a262d6fb6122ecf45be09c50492b31f92e9beb7d9a845987a02cefda57a15f9c467a17872029a9e922\
+                 99b5cbdf306e3a0ee620245cbd508959b6cb7ca637bd55",
+            ),
+            (
+                "7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f",
+                "そつう　れきだい　ほんやく　わかす　りくつ　ばいか　ろせん　やちん　そつう　れきだい　\
+                 ほんやく　わかめ",
+                "aee025cbe6ca256862f889e48110a6a382365142f7d16f2b9545285b3af64e542143a577e9c144e101\
+                 a6bdca18f8d97ec3366ebf5b088b1c1af9bc31346e60d9",
+            ),
+        ];
+        for (entropy, words, seed) in vectors.iter() {
+            let entropy = hex::decode(entropy).expect("entropy");
+            let word_list = split_words(words);
+            assert_eq!(
+                Language::Japanese,
+                detect_language(&word_list).expect("language")
+            );
+            assert_eq!(
+                NormalizedWords::new(&word_list)[..],
+                entropy_to_bip39_mnemonic(&entropy, Language::Japanese).expect("mnemonic")
+            );
+            assert_eq!(
+                entropy,
+                bip39_mnemonic_to_entropy(&word_list, None).expect("entropy")[..]
+            );
+            assert_eq!(
+                seed.to_string(),
+                hex::encode(&bip39_seed(&word_list, None, passphrase).expect("seed")[..])
+            );
+        }
+    }
+
+    #[test]
+    fn bip39_french_normalization() {
+        // Test vector from https://github.com/maciejhirsz/tiny-bip39 with
+        // composed accents in both the words and the passphrase
+        let words = split_words(
+            "paternel xénon curatif séparer docile capable exigence boulon styliste plexus \
+             surface embryon crayon gorge exister",
+        );
+        assert_eq!(Language::French, detect_language(&words).expect("language"));
+        assert_eq!(
+            "cff9ffd2b23549e73601db4129a334c81b28a40f0ee819b5d6a54c409999f0dfb6b89df17cae6408c967\
+             86165c205403d283baadc03ffdd391a490923b7d9493",
+            hex::encode(
+                &bip39_seed(&words, None, "nullius　à　nym.zone ¹teſts² Français").expect("seed")[..]
+            )
+        );
+        assert!(bip39_seed(&words, Some(Language::English), "").is_err());
+    }
+
+    #[test]
+    fn wordlists() {
+        for language in Language::ALL.iter() {
+            let wordlist = get_wordlist(*language);
+            assert_eq!(2048, wordlist.len());
+            for word in wordlist.iter() {
+                assert_eq!(*word, word.nfkd().collect::<String>());
+            }
+            assert_eq!(Ok(*language), language.name().parse());
+        }
+        // Languages that share words are told apart by the checksum
+        let words =
+            entropy_to_bip39_mnemonic(&[0u8; 16], Language::ChineseTraditional).expect("mnemonic");
+        assert!(bip39_mnemonic_to_entropy(&words, Some(Language::ChineseSimplified)).is_ok());
+        assert!(detect_language(&words).is_err());
+        assert!(mnemonic_to_seed(words, SeedType::Mobile, None, "").is_err());
+    }
+
+    #[test]
+    fn slip10_master_key() {
+        // SLIP-0010 ed25519 test vector 1
+        let seed = hex::decode("000102030405060708090a0b0c0d0e0f").expect("seed");
+        assert_eq!(
+            "2b4be7f19ee27bbf30c667b642d5f4aa69fd169872f8fc3059c08ebae2eb19e7",
+            hex::encode(&slip10_ed25519_key(&seed, &[]).expect("key")[..])
+        );
+        assert_eq!(
+            "68e0fe46dfb67e368c75379acec591dad19df3cde26e63b93a8e704f1dade7a3",
+            hex::encode(&slip10_ed25519_key(&seed, &[0]).expect("key")[..])
+        );
+        assert_eq!(
+            "8f94d394a8e8fd6b1bc2f3f49f5c47e385281d5c17e65324b0f62483e37e8793",
+            hex::encode(&slip10_ed25519_key(&seed, &[0, 1, 2, 2, 1_000_000_000]).expect("key")[..])
+        );
+        assert!(slip10_ed25519_key(&seed, &[SLIP10_HARDENED]).is_err());
     }
 }
@@ -3,9 +3,16 @@ use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
 use hmac::Hmac;
 use sha2::Sha256;
 use sodiumoxide::{crypto::pwhash::argon2id13, randombytes};
-use std::{convert::TryInto, fmt, io};
+use std::{
+    convert::TryInto,
+    fmt, io,
+    time::{Duration, Instant},
+};
 
-#[derive(Clone, Copy, Debug)]
+const PWHASH_KIND_PBKDF2: u8 = 0;
+const PWHASH_KIND_ARGON2ID13: u8 = 1;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum PWHash {
     PBKDF2(PBKDF2),
     Argon2id13(Argon2id13),
@@ -33,6 +40,25 @@ impl PWHash {
         }
     }
 
+    /// Reads the kind byte of a password hash and returns a default
+    /// password hash of that kind, to read the parameters into.
+    pub fn read_kind(reader: &mut dyn io::Read) -> Result<Self> {
+        let kind = reader.read_u8()?;
+        match kind {
+            PWHASH_KIND_PBKDF2 => Ok(PWHash::pbkdf2_default()),
+            PWHASH_KIND_ARGON2ID13 => Ok(PWHash::argon2id13_default()),
+            _ => Err(format!("Invalid pwhash kind {}", kind).into()),
+        }
+    }
+
+    pub fn write_kind(&self, writer: &mut dyn io::Write) -> Result {
+        match self {
+            PWHash::PBKDF2(_) => writer.write_u8(PWHASH_KIND_PBKDF2)?,
+            PWHash::Argon2id13(_) => writer.write_u8(PWHASH_KIND_ARGON2ID13)?,
+        }
+        Ok(())
+    }
+
     pub fn pbkdf2_default() -> Self {
         PWHash::PBKDF2(PBKDF2::with_iterations(PBKDF2_DEFAULT_ITERATIONS))
     }
@@ -44,6 +70,89 @@ impl PWHash {
     pub fn argon2id13_default() -> Self {
         PWHash::Argon2id13(Argon2id13::default())
     }
+
+    /// Returns an Argon2id13 password hash with the given number of
+    /// operations and memory limit in bytes
+    pub fn argon2id13(ops_limit: usize, mem_limit: usize) -> Result<Self> {
+        if ops_limit < ARGON2ID13_MIN_OPS_LIMIT {
+            return Err(format!(
+                "Argon2id13 ops limit must be at least {}",
+                ARGON2ID13_MIN_OPS_LIMIT
+            )
+            .into());
+        }
+        // The memory limit is stored as a u32
+        if mem_limit < ARGON2ID13_MIN_MEM_LIMIT || mem_limit > u32::MAX as usize {
+            return Err(format!(
+                "Argon2id13 mem limit must be between {} and {} bytes",
+                ARGON2ID13_MIN_MEM_LIMIT,
+                u32::MAX
+            )
+            .into());
+        }
+        Ok(PWHash::Argon2id13(Argon2id13::with_limits(
+            argon2id13::OpsLimit(ops_limit),
+            argon2id13::MemLimit(mem_limit),
+        )))
+    }
+
+    /// Describes the parameters of the password hash
+    pub fn params(&self) -> String {
+        match self {
+            PWHash::PBKDF2(hasher) => format!("{} iterations", hasher.iterations),
+            PWHash::Argon2id13(hasher) => format!(
+                "ops limit {}, mem limit {} MiB",
+                hasher.ops_limit.0,
+                hasher.mem_limit.0 / MIB
+            ),
+        }
+    }
+
+    /// Returns a PBKDF2 password hash that takes about the target time
+    /// on this machine. The iterations are rounded down to a thousand.
+    pub fn calibrate_pbkdf2(target: Duration) -> Result<Self> {
+        let time = PWHash::pbkdf2(PBKDF2_CALIBRATE_ITERATIONS).time()?;
+        let iterations =
+            PBKDF2_CALIBRATE_ITERATIONS as f64 * target.as_secs_f64() / time.as_secs_f64();
+        let iterations = iterations.min(u32::MAX as f64) as u32 / 1000 * 1000;
+        Ok(PWHash::pbkdf2(iterations.max(1000)))
+    }
+
+    /// Returns an Argon2id13 password hash that takes about the target
+    /// time on this machine. The memory limit starts at the given
+    /// maximum and is halved until a single pass fits in the target
+    /// time. The ops limit then uses up the rest of the time.
+    pub fn calibrate_argon2id13(target: Duration, max_mem_limit: usize) -> Result<Self> {
+        let mut mem_limit = max_mem_limit;
+        let time = loop {
+            let time = PWHash::argon2id13(ARGON2ID13_MIN_OPS_LIMIT, mem_limit)?.time()?;
+            if time <= target || mem_limit / 2 < ARGON2ID13_MIN_MEM_LIMIT {
+                break time;
+            }
+            mem_limit /= 2;
+        };
+        let ops_limit = (target.as_secs_f64() / time.as_secs_f64()) as usize;
+        PWHash::argon2id13(ops_limit.max(ARGON2ID13_MIN_OPS_LIMIT), mem_limit)
+    }
+
+    /// Returns the time it takes to hash a password on this machine
+    pub fn time(&self) -> Result<Duration> {
+        let mut hash = [0; 32];
+        let start = Instant::now();
+        self.pwhash(b"password", &mut hash)?;
+        Ok(start.elapsed())
+    }
+
+    /// Returns a password hash of the same kind and with the same
+    /// parameters as this one, but with a freshly generated salt.
+    pub fn with_new_salt(&self) -> Self {
+        match self {
+            PWHash::PBKDF2(hasher) => PWHash::PBKDF2(PBKDF2::with_iterations(hasher.iterations)),
+            PWHash::Argon2id13(hasher) => {
+                PWHash::Argon2id13(Argon2id13::with_limits(hasher.ops_limit, hasher.mem_limit))
+            }
+        }
+    }
 }
 
 impl fmt::Display for PWHash {
@@ -57,7 +166,15 @@ impl fmt::Display for PWHash {
 
 pub const PBKDF2_DEFAULT_ITERATIONS: u32 = 1_000_000;
 
-#[derive(Clone, Copy, Debug)]
+/// Iterations hashed to estimate the speed of PBKDF2
+const PBKDF2_CALIBRATE_ITERATIONS: u32 = 100_000;
+
+pub const ARGON2ID13_MIN_OPS_LIMIT: usize = 1;
+pub const ARGON2ID13_MIN_MEM_LIMIT: usize = 8192;
+
+pub const MIB: usize = 1024 * 1024;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub struct PBKDF2 {
     salt: [u8; 8],
     iterations: u32,
@@ -70,6 +187,10 @@ impl PBKDF2 {
         Self { salt, iterations }
     }
 
+    pub fn iterations(&self) -> u32 {
+        self.iterations
+    }
+
     pub fn pwhash(&self, password: &[u8], hash: &mut [u8]) -> Result {
         pbkdf2::pbkdf2::<Hmac<Sha256>>(password, &self.salt, self.iterations as usize, hash);
         Ok(())
@@ -95,6 +216,14 @@ pub struct Argon2id13 {
     ops_limit: argon2id13::OpsLimit,
 }
 
+impl PartialEq for Argon2id13 {
+    fn eq(&self, other: &Self) -> bool {
+        self.salt == other.salt
+            && self.mem_limit.0 == other.mem_limit.0
+            && self.ops_limit.0 == other.ops_limit.0
+    }
+}
+
 impl Default for Argon2id13 {
     fn default() -> Self {
         Self::with_limits(
@@ -113,6 +242,14 @@ impl Argon2id13 {
         }
     }
 
+    pub fn ops_limit(&self) -> usize {
+        self.ops_limit.0
+    }
+
+    pub fn mem_limit(&self) -> usize {
+        self.mem_limit.0
+    }
+
     pub fn pwhash(&self, password: &[u8], hash: &mut [u8]) -> Result {
         match argon2id13::derive_key(hash, password, &self.salt, self.ops_limit, self.mem_limit) {
             Ok(_) => Ok(()),
@@ -134,3 +271,30 @@ impl Argon2id13 {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn params() {
+        let pwhash = PWHash::argon2id13(3, 64 * MIB).expect("pwhash");
+        assert_eq!("ops limit 3, mem limit 64 MiB", pwhash.params());
+        assert_eq!("1000000 iterations", PWHash::pbkdf2_default().params());
+        assert!(PWHash::argon2id13(0, 64 * MIB).is_err());
+        assert!(PWHash::argon2id13(3, 1024).is_err());
+
+        // The parameters are stored with the wallet and kept with a
+        // new salt
+        let mut buffer = vec![];
+        pwhash.write_kind(&mut buffer).expect("kind write");
+        pwhash.write(&mut buffer).expect("pwhash write");
+        let mut reader = Cursor::new(buffer);
+        let mut read = PWHash::read_kind(&mut reader).expect("kind read");
+        read.read(&mut reader).expect("pwhash read");
+        assert_eq!(pwhash, read);
+        assert_eq!(pwhash.params(), pwhash.with_new_salt().params());
+        assert_ne!(pwhash, pwhash.with_new_salt());
+    }
+}
@@ -0,0 +1,290 @@
+//! Buffers for secrets such as passwords, derived keys and decrypted
+//! key material.
+//!
+//! A `Secret` keeps its data in memory allocated with `sodium_malloc`,
+//! which puts every allocation in pages of its own between guard pages
+//! and locks it against being swapped to disk where the system allows
+//! it. Since no two secrets share a page, dropping one secret never
+//! unlocks the memory of another. The data is zeroed when the secret is
+//! dropped, and secrets never show their data in `Debug` output.
+
+use libsodium_sys as ffi;
+use serde::de::{self, Deserialize, Deserializer, Visitor};
+use std::{
+    fmt,
+    marker::PhantomData,
+    ops::{Deref, DerefMut},
+    ptr::{self, NonNull},
+    slice,
+};
+
+/// Data that can be kept in a `Secret`
+pub trait SecretData {
+    /// What a secret holding the data dereferences to
+    type Target: ?Sized;
+
+    /// The bytes of the data, which are copied into the secret
+    fn bytes_mut(&mut self) -> &mut [u8];
+
+    /// Views `len` bytes of secret memory as the target
+    fn target(data: *mut u8, len: usize) -> *mut Self::Target;
+}
+
+impl<const N: usize> SecretData for [u8; N] {
+    type Target = [u8; N];
+
+    fn bytes_mut(&mut self) -> &mut [u8] {
+        self
+    }
+
+    fn target(data: *mut u8, _len: usize) -> *mut [u8; N] {
+        data as *mut [u8; N]
+    }
+}
+
+impl SecretData for Vec<u8> {
+    type Target = [u8];
+
+    fn bytes_mut(&mut self) -> &mut [u8] {
+        self
+    }
+
+    fn target(data: *mut u8, len: usize) -> *mut [u8] {
+        ptr::slice_from_raw_parts_mut(data, len)
+    }
+}
+
+impl SecretData for String {
+    type Target = str;
+
+    fn bytes_mut(&mut self) -> &mut [u8] {
+        // Zeroed bytes are still valid UTF-8
+        unsafe { self.as_bytes_mut() }
+    }
+
+    fn target(data: *mut u8, len: usize) -> *mut str {
+        ptr::slice_from_raw_parts_mut(data, len) as *mut str
+    }
+}
+
+/// Zeroes a string that held secret data before dropping it, for
+/// short lived strings that do not need a secret of their own
+pub fn wipe(text: String) {
+    sodiumoxide::utils::memzero(&mut text.into_bytes());
+}
+
+pub struct Secret<T: SecretData> {
+    data: NonNull<u8>,
+    len: usize,
+    locked: bool,
+    kind: PhantomData<T>,
+}
+
+// A secret owns its memory the way a Box does
+unsafe impl<T: SecretData> Send for Secret<T> {}
+unsafe impl<T: SecretData> Sync for Secret<T> {}
+
+impl<T: SecretData> Secret<T> {
+    /// Moves the data into a secret. The data is copied into the memory
+    /// of the secret and the original is zeroed. Copies made before the
+    /// data got here are not, so secrets are best filled in place.
+    pub fn new(mut data: T) -> Self {
+        let bytes = data.bytes_mut();
+        let mut secret = Self::alloc(bytes.len());
+        secret.memory().copy_from_slice(bytes);
+        sodiumoxide::utils::memzero(bytes);
+        secret
+    }
+
+    /// Allocates zeroed secret memory for `len` bytes
+    fn alloc(len: usize) -> Self {
+        sodiumoxide::init().expect("Failed to intialize sodium");
+        let data = unsafe { ffi::sodium_malloc(len) } as *mut u8;
+        let mut secret: Self = Secret {
+            data: NonNull::new(data).expect("Failed to allocate secret memory"),
+            len,
+            locked: false,
+            kind: PhantomData,
+        };
+        sodiumoxide::utils::memzero(secret.memory());
+        // sodium_malloc does not tell whether it could lock the memory,
+        // so it is locked again to find out. The pages belong to this
+        // secret alone, so this does not touch any other secret.
+        secret.locked = sodiumoxide::utils::mlock(secret.memory()).is_ok();
+        secret
+    }
+
+    fn memory(&mut self) -> &mut [u8] {
+        unsafe { slice::from_raw_parts_mut(self.data.as_ptr(), self.len) }
+    }
+
+    /// Whether the memory of the secret is locked against swapping
+    pub fn is_locked(&self) -> bool {
+        self.locked
+    }
+
+    /// Zeroes the data
+    fn clear(&mut self) {
+        sodiumoxide::utils::memzero(self.memory());
+    }
+}
+
+impl<const N: usize> Secret<[u8; N]> {
+    pub fn zeroed() -> Self {
+        Self::alloc(N)
+    }
+}
+
+impl<T: SecretData> Deref for Secret<T> {
+    type Target = T::Target;
+
+    fn deref(&self) -> &T::Target {
+        unsafe { &*T::target(self.data.as_ptr(), self.len) }
+    }
+}
+
+impl<T: SecretData> DerefMut for Secret<T> {
+    fn deref_mut(&mut self) -> &mut T::Target {
+        unsafe { &mut *T::target(self.data.as_ptr(), self.len) }
+    }
+}
+
+impl<const N: usize> PartialEq for Secret<[u8; N]> {
+    /// Compares the data in constant time
+    fn eq(&self, other: &Self) -> bool {
+        sodiumoxide::utils::memcmp(&self[..], &other[..])
+    }
+}
+
+impl<T: SecretData> Drop for Secret<T> {
+    fn drop(&mut self) {
+        self.clear();
+        // Frees the memory, zeroing and unlocking it once more
+        unsafe { ffi::sodium_free(self.data.as_ptr() as *mut _) };
+    }
+}
+
+impl<'de> Deserialize<'de> for Secret<String> {
+    /// Deserializes a string straight into secret memory, as long as the
+    /// deserializer can lend it out
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        deserializer.deserialize_str(SecretStringVisitor)
+    }
+}
+
+struct SecretStringVisitor;
+
+impl<'de> Visitor<'de> for SecretStringVisitor {
+    type Value = Secret<String>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a string")
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> std::result::Result<Self::Value, E> {
+        let mut secret = Secret::<String>::alloc(value.len());
+        secret.memory().copy_from_slice(value.as_bytes());
+        Ok(secret)
+    }
+
+    fn visit_string<E: de::Error>(self, value: String) -> std::result::Result<Self::Value, E> {
+        Ok(Secret::new(value))
+    }
+}
+
+impl<T: SecretData> fmt::Debug for Secret<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Secret(****)")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Borrowed memory, to look at the original after it is moved into
+    // a secret
+    impl SecretData for &mut [u8] {
+        type Target = [u8];
+
+        fn bytes_mut(&mut self) -> &mut [u8] {
+            self
+        }
+
+        fn target(data: *mut u8, len: usize) -> *mut [u8] {
+            ptr::slice_from_raw_parts_mut(data, len)
+        }
+    }
+
+    #[test]
+    fn original_cleared() {
+        let mut buffer = [0xaa; 64];
+        let secret = Secret::<Vec<u8>>::new(buffer.to_vec());
+        {
+            let mut moved = Secret::new(&mut buffer[..]);
+            assert_eq!(secret[..], moved[..]);
+            moved[0] = 0x55;
+            assert_eq!(0x55, moved[0]);
+        }
+        assert_eq!([0; 64][..], buffer[..]);
+    }
+
+    #[test]
+    fn cleared_array() {
+        let mut key = Secret::<[u8; 32]>::zeroed();
+        assert_eq!([0; 32], *key);
+        key.copy_from_slice(&[0xaa; 32]);
+        assert_eq!([0xaa; 32], *key);
+        key.clear();
+        assert_eq!([0; 32], *key);
+    }
+
+    #[test]
+    fn cleared_vec() {
+        let mut password = Secret::new(b"password".to_vec());
+        assert_eq!(b"password", &password[..]);
+        password.clear();
+        assert_eq!([0; 8], password[..]);
+        assert!(Secret::new(vec![]).is_empty());
+    }
+
+    #[test]
+    fn cleared_string() {
+        let mut words = Secret::new("catch poet clog".to_string());
+        assert_eq!("catch poet clog", &*words);
+        words.clear();
+        assert_eq!("\0".repeat(15), &*words);
+    }
+
+    #[test]
+    fn deserialized_string() {
+        let words: Vec<Secret<String>> =
+            serde_json::from_str(r#"["catch", "po\u0065t"]"#).expect("words");
+        assert_eq!("catch", &*words[0]);
+        assert_eq!("poet", &*words[1]);
+    }
+
+    #[test]
+    fn separate_pages() {
+        // Secrets are at least a page apart, so unlocking the memory of
+        // one never unlocks another
+        let first = Secret::<[u8; 32]>::zeroed();
+        let second = Secret::<[u8; 32]>::zeroed();
+        let (first, second) = (first.as_ptr() as usize, second.as_ptr() as usize);
+        assert!(first.max(second) - first.min(second) >= 4096);
+    }
+
+    #[test]
+    fn compared() {
+        let mut key = Secret::<[u8; 32]>::zeroed();
+        assert!(key == Secret::zeroed());
+        key[31] = 1;
+        assert!(key != Secret::zeroed());
+    }
+
+    #[test]
+    fn hidden_from_debug() {
+        let secret = Secret::new(b"password".to_vec());
+        assert_eq!("Secret(****)", format!("{:?}", secret));
+    }
+}
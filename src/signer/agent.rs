@@ -0,0 +1,152 @@
+use crate::{
+    keypair::{Keypair, PublicKey, KEYPAIR_MAX_LENGTH},
+    result::Result,
+    secret::Secret,
+    signer::Signer,
+    traits::ReadWrite,
+};
+
+/// A decrypted key kept in memory that is locked against being swapped
+/// to disk, and cleared when dropped. The wallet agent holds its key
+/// this way. The key is kept in its encoded form and decoded for each
+/// signature. Like every `Secret` it has guarded pages of its own, so
+/// dropping the key decoded for a signature does not unlock it.
+pub struct LockedKeypair {
+    public: PublicKey,
+    key: Secret<[u8; KEYPAIR_MAX_LENGTH]>,
+}
+
+impl LockedKeypair {
+    pub fn new(keypair: &Keypair) -> Result<Self> {
+        let mut key = Secret::<[u8; KEYPAIR_MAX_LENGTH]>::zeroed();
+        if !key.is_locked() {
+            return Err("Failed to lock memory for the key".into());
+        }
+        keypair.write(&mut &mut key[..])?;
+        Ok(LockedKeypair {
+            public: keypair.public,
+            key,
+        })
+    }
+}
+
+impl Signer for LockedKeypair {
+    fn public_key(&self) -> PublicKey {
+        self.public
+    }
+
+    fn sign(&self, data: &[u8]) -> Result<Vec<u8>> {
+        Keypair::read(&mut &self.key[..])?.sign(data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        keypair::{KeyType, Verify},
+        signer::remote,
+    };
+    use std::{
+        thread,
+        time::{Duration, Instant},
+    };
+
+    #[test]
+    fn locked_keypair() {
+        for key_type in &[KeyType::Ed25519, KeyType::EccCompact] {
+            let keypair =
+                LockedKeypair::new(&Keypair::gen_keypair_with_type(*key_type).expect("keypair"))
+                    .expect("locked keypair");
+            let signature = keypair.sign(b"hello world").expect("signature");
+            assert!(keypair
+                .public_key()
+                .verify(b"hello world", &signature)
+                .is_ok());
+        }
+    }
+
+    /// The kilobytes of memory locked in the mapping that holds the
+    /// given address
+    #[cfg(target_os = "linux")]
+    fn locked_kb(address: usize) -> u64 {
+        let smaps = std::fs::read_to_string("/proc/self/smaps").expect("smaps");
+        let mut in_mapping = false;
+        for line in smaps.lines() {
+            let field = line.split_whitespace().next().unwrap_or_default();
+            if let Some((start, end)) = field.split_once('-') {
+                if let (Ok(start), Ok(end)) = (
+                    usize::from_str_radix(start, 16),
+                    usize::from_str_radix(end, 16),
+                ) {
+                    in_mapping = start <= address && address < end;
+                    continue;
+                }
+            }
+            if in_mapping && field == "Locked:" {
+                return line
+                    .split_whitespace()
+                    .nth(1)
+                    .expect("size")
+                    .parse()
+                    .expect("size");
+            }
+        }
+        panic!("No mapping found for {:x}", address)
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn locked_after_sign() {
+        for key_type in &[KeyType::Ed25519, KeyType::EccCompact] {
+            let keypair =
+                LockedKeypair::new(&Keypair::gen_keypair_with_type(*key_type).expect("keypair"))
+                    .expect("locked keypair");
+            let address = keypair.key.as_ptr() as usize;
+            assert!(locked_kb(address) > 0);
+            keypair.sign(b"hello world").expect("signature");
+            assert!(keypair.key.is_locked());
+            assert!(locked_kb(address) > 0);
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn agent_expires() {
+        let keypair =
+            LockedKeypair::new(&Keypair::gen_keypair().expect("keypair")).expect("locked keypair");
+        let public_key = keypair.public_key();
+        let path = std::env::temp_dir().join(format!("helium-agent-{}.sock", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let expires = Instant::now() + Duration::from_secs(2);
+        let server_path = path.clone();
+        let agent = thread::spawn(move || {
+            remote::serve(&server_path, false, Some(expires), &keypair, |_, _| {
+                Ok(true)
+            })
+            .is_ok()
+        });
+        while !path.exists() {
+            thread::sleep(Duration::from_millis(10));
+        }
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&path)
+                .expect("socket")
+                .permissions()
+                .mode();
+            assert_eq!(0o600, mode & 0o777);
+        }
+
+        let signer = remote::RemoteSigner::connect(&path).expect("agent signer");
+        let signature = signer.sign(b"hello world").expect("signature");
+        assert!(public_key.verify(b"hello world", &signature).is_ok());
+
+        // The agent stops and removes its socket when it expires, even
+        // with a client still connected
+        assert!(agent.join().expect("agent"));
+        assert!(Instant::now() >= expires);
+        assert!(!path.exists());
+        assert!(signer.sign(b"hello world").is_err());
+    }
+}
@@ -0,0 +1,244 @@
+//! A signer that coordinates a FROST threshold signature.
+//!
+//! The coordinator holds no key share. For every signature it creates
+//! a session directory with a signing request, and participants
+//! exchange the files of the two signing rounds through it:
+//!
+//! ```text
+//! <sessions>/<session>/request.json        written by the coordinator
+//! <sessions>/<session>/commit.<id>.json    written by each participant
+//! <sessions>/<session>/package.json        written by the coordinator
+//! <sessions>/<session>/sigshare.<id>.json  written by each participant
+//! ```
+//!
+//! How the files get to the participants and back is up to the
+//! operator, the coordinator waits until they are in place.
+
+use crate::{
+    frost::{
+        sign::{self, Commitment, SignatureShare, SigningPackage, SigningRequest},
+        Group,
+    },
+    keypair::PublicKey,
+    result::Result,
+    signer::{remote, Signer},
+};
+use serde::de::DeserializeOwned;
+use serde_derive::{Deserialize, Serialize};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// The FROST group a coordinator signs for as stored in a signer
+/// reference file
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub struct Config {
+    /// A file with the public group information, such as the key
+    /// share file of any participant. Only the group is read.
+    pub group: PathBuf,
+
+    /// Directory to create signing sessions in
+    pub sessions: PathBuf,
+}
+
+/// The public part of a key share file
+#[derive(Deserialize)]
+struct GroupFile {
+    address: String,
+    group: Group,
+}
+
+/// Called with instructions for the operator whenever the coordinator
+/// waits for files from the participants. Returns once the operator
+/// wants the coordinator to look again.
+pub type Wait = Box<dyn Fn(&str) -> Result>;
+
+pub struct FrostSigner {
+    group: Group,
+    public: PublicKey,
+    sessions: PathBuf,
+    wait: Wait,
+}
+
+/// Opens a coordinator for the group in the given configuration
+pub fn open(config: &Config, wait: Wait) -> Result<Box<dyn Signer>> {
+    let data = fs::read(&config.group)?;
+    let file: GroupFile = serde_json::from_slice(&data)
+        .map_err(|err| format!("{}: invalid FROST group: {}", config.group.display(), err))?;
+    if file.address != file.group.address()? {
+        return Err(format!(
+            "{}: address does not match the group",
+            config.group.display()
+        )
+        .into());
+    }
+    Ok(Box::new(FrostSigner {
+        public: file.group.public_key()?,
+        group: file.group,
+        sessions: config.sessions.clone(),
+        wait,
+    }))
+}
+
+impl Signer for FrostSigner {
+    fn public_key(&self) -> PublicKey {
+        self.public
+    }
+
+    fn sign(&self, data: &[u8]) -> Result<Vec<u8>> {
+        let summary = remote::txn_summary(remote::RAW_TXN_TYPE, data)?;
+        self.sign_txn(remote::RAW_TXN_TYPE, &summary, data)
+    }
+
+    fn sign_txn(&self, txn_type: &str, summary: &str, data: &[u8]) -> Result<Vec<u8>> {
+        let request = SigningRequest::new(&self.group, txn_type, summary, data)?;
+        let dir = self.sessions.join(&request.session);
+        fs::create_dir_all(&dir)?;
+        write_json(&dir.join("request.json"), &request)?;
+
+        let mut commitments: Vec<Commitment>;
+        loop {
+            (self.wait)(&format!(
+                "Have at least {} participants run `frost commit` on {} and put their commit.<id>.json files in {}",
+                self.group.threshold,
+                dir.join("request.json").display(),
+                dir.display()
+            ))?;
+            commitments = read_files(&dir, "commit.")?;
+            if commitments.len() >= self.group.threshold as usize {
+                break;
+            }
+        }
+        let package = SigningPackage::new(&self.group, request, commitments)?;
+        write_json(&dir.join("package.json"), &package)?;
+
+        loop {
+            (self.wait)(&format!(
+                "Have participants {:?} run `frost sign` on {} and put their sigshare.<id>.json files in {}",
+                package
+                    .commitments
+                    .iter()
+                    .map(|commitment| commitment.identifier)
+                    .collect::<Vec<_>>(),
+                dir.join("package.json").display(),
+                dir.display()
+            ))?;
+            let shares: Vec<SignatureShare> = read_files(&dir, "sigshare.")?;
+            let complete = package.commitments.iter().all(|commitment| {
+                shares
+                    .iter()
+                    .any(|share| share.identifier == commitment.identifier)
+            });
+            if complete {
+                return sign::aggregate(&self.group, &package, &shares);
+            }
+        }
+    }
+}
+
+/// Reads all JSON files in a session directory whose names start with
+/// the given prefix
+fn read_files<T: DeserializeOwned>(dir: &Path, prefix: &str) -> Result<Vec<T>> {
+    let mut result = vec![];
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let name = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or_default();
+        if name.starts_with(prefix) && name.ends_with(".json") {
+            let data = fs::read(&path)?;
+            result.push(
+                serde_json::from_slice(&data)
+                    .map_err(|err| format!("{}: {}", path.display(), err))?,
+            );
+        }
+    }
+    Ok(result)
+}
+
+fn write_json<T: serde::Serialize>(path: &Path, value: &T) -> Result {
+    fs::write(path, serde_json::to_string_pretty(value)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{frost::dkg::tests::keygen, keypair::Verify};
+    use std::{cell::RefCell, rc::Rc};
+
+    #[test]
+    fn coordinate_signature() {
+        let shares = keygen(2, 3);
+        let dir = std::env::temp_dir().join(format!("helium-frost-{}", std::process::id()));
+        fs::create_dir_all(&dir).expect("temp dir");
+        let group_path = dir.join("group.json");
+        write_json(
+            &group_path,
+            &serde_json::json!({
+                "address": shares[0].address().expect("address"),
+                "group": shares[0].group,
+            }),
+        )
+        .expect("group file");
+
+        // Plays participants 1 and 3 answering each round. The first
+        // round only gets one commitment the first time around.
+        let rounds = Rc::new(RefCell::new(0));
+        let nonces = RefCell::new(vec![]);
+        let sessions = dir.join("sessions");
+        let wait_sessions = sessions.clone();
+        let wait_rounds = rounds.clone();
+        let wait: Wait = Box::new(move |_| {
+            let session = fs::read_dir(&wait_sessions)?
+                .next()
+                .expect("session")?
+                .path();
+            let round = *wait_rounds.borrow();
+            *wait_rounds.borrow_mut() += 1;
+            match round {
+                0 | 1 => {
+                    let data = fs::read(session.join("request.json"))?;
+                    let request: SigningRequest = serde_json::from_slice(&data)?;
+                    let share = &shares[round * 2];
+                    let (share_nonces, commitment) = sign::commit(share, &request)?;
+                    nonces.borrow_mut().push(share_nonces);
+                    write_json(
+                        &session.join(format!("commit.{}.json", share.identifier)),
+                        &commitment,
+                    )
+                }
+                _ => {
+                    let data = fs::read(session.join("package.json"))?;
+                    let package: SigningPackage = serde_json::from_slice(&data)?;
+                    for (share, share_nonces) in [&shares[0], &shares[2]]
+                        .iter()
+                        .zip(nonces.borrow_mut().drain(..))
+                    {
+                        let signature_share = sign::sign(share, share_nonces, &package)?;
+                        write_json(
+                            &session.join(format!("sigshare.{}.json", share.identifier)),
+                            &signature_share,
+                        )?;
+                    }
+                    Ok(())
+                }
+            }
+        });
+
+        let config = Config {
+            group: group_path,
+            sessions,
+        };
+        let signer = open(&config, wait).expect("signer");
+        let signature = signer.sign(b"hello world").expect("signature");
+        assert!(signer
+            .public_key()
+            .verify(b"hello world", &signature)
+            .is_ok());
+        assert_eq!(3, *rounds.borrow());
+        fs::remove_dir_all(&dir).expect("remove temp dir");
+    }
+}
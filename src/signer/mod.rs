@@ -0,0 +1,195 @@
+use crate::{
+    keypair::{Keypair, PubKeyBin, PublicKey},
+    result::Result,
+};
+use serde_derive::{Deserialize, Serialize};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+pub mod agent;
+pub mod frost;
+pub mod pkcs11;
+pub mod remote;
+
+/// A key custody backend that can sign on behalf of a public key
+/// without exposing the secret key to the caller.
+pub trait Signer {
+    /// The public key that signatures verify against
+    fn public_key(&self) -> PublicKey;
+
+    /// Signs the given bytes, returning a detached signature
+    fn sign(&self, data: &[u8]) -> Result<Vec<u8>>;
+
+    /// Signs an encoded transaction with its signatures stripped. The
+    /// transaction type and summary are passed along for signers that
+    /// show or log what they sign.
+    fn sign_txn(&self, _txn_type: &str, _summary: &str, data: &[u8]) -> Result<Vec<u8>> {
+        self.sign(data)
+    }
+
+    fn pubkey_bin(&self) -> PubKeyBin {
+        PubKeyBin::from(&self.public_key())
+    }
+}
+
+/// A decrypted keypair signs in-process. This is the default signer
+/// for encrypted wallet files.
+impl Signer for Keypair {
+    fn public_key(&self) -> PublicKey {
+        self.public
+    }
+
+    fn sign(&self, data: &[u8]) -> Result<Vec<u8>> {
+        Keypair::sign(self, data)
+    }
+}
+
+/// The configuration of a signer backend as stored in a signer
+/// reference file. A reference file is a JSON object with a "signer"
+/// field naming the backend and the options for that backend, and can
+/// be used anywhere a wallet file is expected.
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+#[serde(tag = "signer", rename_all = "snake_case")]
+pub enum SignerConfig {
+    /// Sign with the keypair in encrypted wallet files
+    Wallet { files: Vec<PathBuf> },
+    /// Sign with an ed25519 key on a PKCS#11 token
+    Pkcs11(pkcs11::Config),
+    /// Sign through a signing service listening on a Unix socket
+    Remote(remote::Config),
+    /// Coordinate a threshold signature by a FROST group
+    Frost(frost::Config),
+}
+
+impl SignerConfig {
+    /// Reads the signer configuration from the given file. Relative
+    /// paths in the configuration are resolved against the directory
+    /// of the file.
+    pub fn load(path: &Path) -> Result<Self> {
+        let data = fs::read(path)?;
+        let config: SignerConfig = serde_json::from_slice(&data)
+            .map_err(|err| format!("{}: invalid signer reference: {}", path.display(), err))?;
+        Ok(config.resolve_paths(path.parent().unwrap_or_else(|| Path::new(""))))
+    }
+
+    /// Returns whether the given file is a signer reference file
+    /// rather than an encrypted wallet. Encrypted wallets start with
+    /// a binary format kind, reference files with a JSON object.
+    pub fn is_reference(path: &Path) -> Result<bool> {
+        let data = fs::read(path)?;
+        Ok(data.iter().find(|b| !b.is_ascii_whitespace()) == Some(&b'{'))
+    }
+
+    fn resolve_paths(self, base: &Path) -> Self {
+        match self {
+            SignerConfig::Wallet { files } => SignerConfig::Wallet {
+                files: files.into_iter().map(|file| base.join(file)).collect(),
+            },
+            SignerConfig::Remote(config) => SignerConfig::Remote(remote::Config {
+                socket: base.join(config.socket),
+                ..config
+            }),
+            SignerConfig::Frost(config) => SignerConfig::Frost(frost::Config {
+                group: base.join(config.group),
+                sessions: base.join(config.sessions),
+            }),
+            config => config,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keypair::{Network, Verify};
+
+    #[test]
+    fn keypair_signer() {
+        let keypair = Keypair::gen_keypair().expect("keypair");
+        let signer: &dyn Signer = &keypair;
+        assert_eq!(keypair.pubkey_bin(), signer.pubkey_bin());
+        let signature = signer.sign(b"hello world").expect("signature");
+        assert!(keypair.public.verify(b"hello world", &signature).is_ok());
+    }
+
+    #[test]
+    fn wallet_config() {
+        let config: SignerConfig = serde_json::from_str(
+            r#"{"signer": "wallet", "files": ["wallet.key.1", "/wallet.key.2"]}"#,
+        )
+        .expect("config");
+        assert_eq!(
+            SignerConfig::Wallet {
+                files: vec![
+                    PathBuf::from("/keys/wallet.key.1"),
+                    PathBuf::from("/wallet.key.2")
+                ]
+            },
+            config.resolve_paths(Path::new("/keys"))
+        );
+        assert!(serde_json::from_str::<SignerConfig>(r#"{"signer": "unknown"}"#).is_err());
+    }
+
+    #[test]
+    fn remote_config() {
+        let config: SignerConfig =
+            serde_json::from_str(r#"{"signer": "remote", "socket": "signer.sock"}"#)
+                .expect("config");
+        let expected = remote::Config {
+            socket: PathBuf::from("/run/signer.sock"),
+            address: None,
+        };
+        assert_eq!(
+            SignerConfig::Remote(expected),
+            config.resolve_paths(Path::new("/run"))
+        );
+    }
+
+    #[test]
+    fn frost_config() {
+        let config: SignerConfig = serde_json::from_str(
+            r#"{"signer": "frost", "group": "share.1.json", "sessions": "sessions"}"#,
+        )
+        .expect("config");
+        let expected = frost::Config {
+            group: PathBuf::from("/treasury/share.1.json"),
+            sessions: PathBuf::from("/treasury/sessions"),
+        };
+        assert_eq!(
+            SignerConfig::Frost(expected),
+            config.resolve_paths(Path::new("/treasury"))
+        );
+    }
+
+    #[test]
+    fn pkcs11_config() {
+        let json = r#"{
+            "signer": "pkcs11",
+            "module": "/usr/lib/softhsm/libsofthsm2.so",
+            "token_label": "treasury",
+            "key_id": "01"
+        }"#;
+        let config: SignerConfig = serde_json::from_str(json).expect("config");
+        let expected = pkcs11::Config {
+            module: PathBuf::from("/usr/lib/softhsm/libsofthsm2.so"),
+            slot: None,
+            token_label: Some("treasury".to_string()),
+            key_label: None,
+            key_id: Some("01".to_string()),
+            network: Network::Mainnet,
+            address: None,
+        };
+        assert_eq!(SignerConfig::Pkcs11(expected), config);
+        let json = serde_json::to_string(&config).expect("json");
+        assert_eq!(config, serde_json::from_str(&json).expect("config"));
+
+        // A key has to be identified by its label or id
+        let json = r#"{"signer": "pkcs11", "module": "libsofthsm2.so", "slot": 1}"#;
+        match serde_json::from_str(json).expect("config") {
+            SignerConfig::Pkcs11(config) => assert!(pkcs11::open(&config, b"1234").is_err()),
+            _ => panic!("Expected a PKCS#11 config"),
+        }
+    }
+}
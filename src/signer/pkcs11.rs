@@ -0,0 +1,253 @@
+use crate::{keypair::Network, result::Result, signer::Signer};
+use serde_derive::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// The location of an ed25519 key on a PKCS#11 token as stored in a
+/// signer reference file. The key is found by its label, its id, or
+/// both.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub struct Config {
+    /// Path of the PKCS#11 module to load
+    pub module: PathBuf,
+
+    /// Id of the slot holding the token. The token is looked up by its
+    /// label if not given, or the first slot with a token is used.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub slot: Option<u64>,
+
+    /// Label of the token holding the key
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token_label: Option<String>,
+
+    /// Label (CKA_LABEL) of the key
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key_label: Option<String>,
+
+    /// Hex encoded id (CKA_ID) of the key
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key_id: Option<String>,
+
+    /// The network the key is used on
+    #[serde(default)]
+    pub network: Network,
+
+    /// The expected address of the key. Signing is refused if the key
+    /// on the token has a different address.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub address: Option<String>,
+}
+
+impl Config {
+    pub fn key_id(&self) -> Result<Option<Vec<u8>>> {
+        match &self.key_id {
+            Some(id) => Ok(Some(hex::decode(id)?)),
+            None => Ok(None),
+        }
+    }
+
+    fn validate(&self) -> Result {
+        if self.key_label.is_none() && self.key_id.is_none() {
+            return Err("A PKCS#11 key label or key id is required".into());
+        }
+        self.key_id()?;
+        Ok(())
+    }
+}
+
+#[cfg(feature = "pkcs11")]
+pub use self::token::Pkcs11Signer;
+
+/// Opens a signer for the key in the given configuration, logging in
+/// to the token with the given user PIN
+pub fn open(config: &Config, pin: &[u8]) -> Result<Box<dyn Signer>> {
+    config.validate()?;
+    open_token(config, pin)
+}
+
+#[cfg(feature = "pkcs11")]
+fn open_token(config: &Config, pin: &[u8]) -> Result<Box<dyn Signer>> {
+    Ok(Box::new(Pkcs11Signer::open(config, pin)?))
+}
+
+#[cfg(not(feature = "pkcs11"))]
+fn open_token(_config: &Config, _pin: &[u8]) -> Result<Box<dyn Signer>> {
+    Err("PKCS#11 support is not enabled, rebuild with \"--features pkcs11\"".into())
+}
+
+#[cfg(feature = "pkcs11")]
+mod token {
+    use super::Config;
+    use crate::{
+        keypair::{PubKeyBin, PublicKey, Verify, KEYTYPE_ED25519},
+        result::Result,
+        signer::Signer,
+        traits::B58,
+    };
+    use pkcs11::{types::*, Ctx};
+    use std::{convert::TryFrom, ptr};
+
+    /// PKCS#11 v3.0 values for Edwards curve keys and signatures, which
+    /// are not defined by the pkcs11 crate
+    const CKK_EC_EDWARDS: CK_KEY_TYPE = 0x40;
+    const CKM_EDDSA: CK_MECHANISM_TYPE = 0x1057;
+
+    /// A signer for an ed25519 key on a PKCS#11 token. The secret key
+    /// never leaves the token, the token signs the data it is given.
+    pub struct Pkcs11Signer {
+        ctx: Ctx,
+        session: CK_SESSION_HANDLE,
+        key: CK_OBJECT_HANDLE,
+        public: PublicKey,
+    }
+
+    impl Pkcs11Signer {
+        /// Loads the module in the given configuration, logs in to the
+        /// token with the given user PIN and looks up the private and
+        /// public key.
+        pub fn open(config: &Config, pin: &[u8]) -> Result<Self> {
+            let ctx = Ctx::new_and_initialize(&config.module)?;
+            let slot = find_slot(&ctx, config)?;
+            let session = ctx.open_session(slot, CKF_SERIAL_SESSION, None, None)?;
+            ctx.login(session, CKU_USER, Some(std::str::from_utf8(pin)?))?;
+
+            let key = find_key(&ctx, session, CKO_PRIVATE_KEY, config)?;
+            let public_key = find_key(&ctx, session, CKO_PUBLIC_KEY, config)?;
+            let point = get_attribute(&ctx, session, public_key, CKA_EC_POINT)?;
+
+            let mut pubkey_bin = PubKeyBin::default();
+            pubkey_bin.0[0] = config.network.to_byte() | KEYTYPE_ED25519;
+            pubkey_bin.0[1..].copy_from_slice(decode_ec_point(&point)?);
+            if let Some(address) = &config.address {
+                if pubkey_bin.to_b58()? != *address {
+                    return Err(format!(
+                        "Key on token has address {}, expected {}",
+                        pubkey_bin, address
+                    )
+                    .into());
+                }
+            }
+            Ok(Pkcs11Signer {
+                ctx,
+                session,
+                key,
+                public: PublicKey::try_from(pubkey_bin)?,
+            })
+        }
+    }
+
+    impl Signer for Pkcs11Signer {
+        fn public_key(&self) -> PublicKey {
+            self.public
+        }
+
+        fn sign(&self, data: &[u8]) -> Result<Vec<u8>> {
+            let mechanism = CK_MECHANISM {
+                mechanism: CKM_EDDSA,
+                pParameter: ptr::null_mut(),
+                ulParameterLen: 0,
+            };
+            self.ctx.sign_init(self.session, &mechanism, self.key)?;
+            let signature = self.ctx.sign(self.session, data)?;
+            // Catch tokens that sign with another mechanism than pure
+            // ed25519 before the signature is used in a transaction
+            self.public.verify(data, &signature)?;
+            Ok(signature)
+        }
+    }
+
+    impl Drop for Pkcs11Signer {
+        fn drop(&mut self) {
+            let _ = self.ctx.logout(self.session);
+            let _ = self.ctx.close_session(self.session);
+        }
+    }
+
+    fn find_slot(ctx: &Ctx, config: &Config) -> Result<CK_SLOT_ID> {
+        if let Some(slot) = config.slot {
+            return Ok(slot as CK_SLOT_ID);
+        }
+        for slot in ctx.get_slot_list(true)? {
+            match &config.token_label {
+                Some(label) => {
+                    let info = ctx.get_token_info(slot)?;
+                    if String::from_utf8_lossy(&info.label[..]).trim_end() == label {
+                        return Ok(slot);
+                    }
+                }
+                None => return Ok(slot),
+            }
+        }
+        Err("No matching PKCS#11 token found".into())
+    }
+
+    fn find_key(
+        ctx: &Ctx,
+        session: CK_SESSION_HANDLE,
+        class: CK_OBJECT_CLASS,
+        config: &Config,
+    ) -> Result<CK_OBJECT_HANDLE> {
+        let key_id = config.key_id()?;
+        let mut template = vec![
+            CK_ATTRIBUTE::new(CKA_CLASS).with_ck_ulong(&class),
+            CK_ATTRIBUTE::new(CKA_KEY_TYPE).with_ck_ulong(&CKK_EC_EDWARDS),
+        ];
+        if let Some(label) = &config.key_label {
+            template.push(CK_ATTRIBUTE::new(CKA_LABEL).with_string(label));
+        }
+        if let Some(id) = &key_id {
+            template.push(CK_ATTRIBUTE::new(CKA_ID).with_bytes(id));
+        }
+        ctx.find_objects_init(session, &template)?;
+        let objects = ctx.find_objects(session, 2);
+        ctx.find_objects_final(session)?;
+        let kind = if class == CKO_PRIVATE_KEY {
+            "private"
+        } else {
+            "public"
+        };
+        match objects?.as_slice() {
+            [object] => Ok(*object),
+            [] => Err(format!("No ed25519 {} key found on token", kind).into()),
+            _ => Err(format!("More than one matching {} key found on token", kind).into()),
+        }
+    }
+
+    fn get_attribute(
+        ctx: &Ctx,
+        session: CK_SESSION_HANDLE,
+        object: CK_OBJECT_HANDLE,
+        attribute: CK_ATTRIBUTE_TYPE,
+    ) -> Result<Vec<u8>> {
+        let mut template = vec![CK_ATTRIBUTE::new(attribute)];
+        ctx.get_attribute_value(session, object, &mut template)?;
+        let value = vec![0u8; template[0].ulValueLen as usize];
+        let mut template = vec![CK_ATTRIBUTE::new(attribute).with_bytes(&value)];
+        ctx.get_attribute_value(session, object, &mut template)?;
+        Ok(value)
+    }
+
+    /// Returns the public key bytes of an ed25519 CKA_EC_POINT, which
+    /// tokens return either raw or as a DER encoded octet string
+    fn decode_ec_point(point: &[u8]) -> Result<&[u8]> {
+        match point {
+            [0x04, 0x20, key @ ..] if key.len() == 32 => Ok(key),
+            key if key.len() == 32 => Ok(key),
+            _ => Err("Invalid ed25519 public key on token".into()),
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn ec_point() {
+            let key = [7u8; 32];
+            let mut der = vec![0x04, 0x20];
+            der.extend_from_slice(&key);
+            assert_eq!(&key[..], decode_ec_point(&der).expect("der point"));
+            assert_eq!(&key[..], decode_ec_point(&key).expect("raw point"));
+            assert!(decode_ec_point(&der[..20]).is_err());
+        }
+    }
+}
@@ -0,0 +1,561 @@
+//! A signer that sends signing requests to a signing service over a
+//! Unix socket, and a reference server for that service.
+//!
+//! The protocol is newline delimited JSON. The client writes one
+//! request object per line and the server answers each with one
+//! response object on a line of its own:
+//!
+//! ```text
+//! > {"method":"public_key"}
+//! < {"public_key":"<b58 address>"}
+//! > {"method":"sign","txn_type":"payment_v2","summary":"Pay ...","data":"<b64>"}
+//! < {"signature":"<b64>"}
+//! < {"error":"<message>"}
+//! ```
+//!
+//! The data of a sign request is the encoded transaction with its
+//! signatures stripped, exactly the bytes the signature is over. The
+//! server decodes the data itself and refuses requests whose summary
+//! does not match, so the summary shown to the operator of the service
+//! describes what is actually signed. Raw data that is an encoded
+//! transaction is refused, so a transaction can not be passed off as
+//! raw data to avoid its summary.
+
+use crate::{
+    keypair::{PubKeyBin, PublicKey, Verify},
+    message,
+    result::Result,
+    signer::Signer,
+    token,
+    traits::{TxnSummary, B58, B64},
+};
+use helium_api::{
+    BlockchainTxnAddGatewayV1, BlockchainTxnAssertLocationV1, BlockchainTxnCreateHtlcV1,
+    BlockchainTxnOuiV1, BlockchainTxnPaymentV1, BlockchainTxnPaymentV2, BlockchainTxnPriceOracleV1,
+    BlockchainTxnRedeemHtlcV1, BlockchainTxnSecurityExchangeV1, BlockchainTxnTokenBurnV1,
+    BlockchainTxnTransferHotspotV1, BlockchainTxnVarsV1, Message,
+};
+use serde_derive::{Deserialize, Serialize};
+use std::{
+    convert::TryFrom,
+    path::{Path, PathBuf},
+    time::Instant,
+};
+
+/// The transaction type of sign requests for data that is not a
+/// transaction
+pub const RAW_TXN_TYPE: &str = "raw";
+
+/// The location of a signing service as stored in a signer reference
+/// file
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub struct Config {
+    /// Path of the Unix socket the signing service listens on
+    pub socket: PathBuf,
+
+    /// The expected address of the service's key. Signing is refused
+    /// if the service signs for a different address.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub address: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+#[serde(tag = "method", rename_all = "snake_case")]
+pub enum Request {
+    /// Asks for the address of the key the service signs with
+    PublicKey,
+    /// Asks for a signature over the given base64 encoded data
+    Sign {
+        txn_type: String,
+        summary: String,
+        data: String,
+    },
+}
+
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Response {
+    /// The b58 address of the key the service signs with
+    PublicKey(String),
+    /// A base64 encoded detached signature
+    Signature(String),
+    Error(String),
+}
+
+/// Decodes the signing bytes of a transaction of the given type and
+/// returns its summary
+pub fn txn_summary(txn_type: &str, data: &[u8]) -> Result<String> {
+    fn summary<T: Message + Default + TxnSummary>(data: &[u8]) -> Result<String> {
+        T::decode(data)?.summary()
+    }
+    match txn_type {
+        "payment_v1" => summary::<BlockchainTxnPaymentV1>(data),
+        "payment_v2" => summary::<BlockchainTxnPaymentV2>(data),
+        "create_htlc_v1" => summary::<BlockchainTxnCreateHtlcV1>(data),
+        "redeem_htlc_v1" => summary::<BlockchainTxnRedeemHtlcV1>(data),
+        "add_gateway_v1" => summary::<BlockchainTxnAddGatewayV1>(data),
+        "assert_location_v1" => summary::<BlockchainTxnAssertLocationV1>(data),
+        "oui_v1" => summary::<BlockchainTxnOuiV1>(data),
+        "price_oracle_v1" => summary::<BlockchainTxnPriceOracleV1>(data),
+        "security_exchange_v1" => summary::<BlockchainTxnSecurityExchangeV1>(data),
+        "token_burn_v1" => summary::<BlockchainTxnTokenBurnV1>(data),
+        "transfer_hotspot_v1" => summary::<BlockchainTxnTransferHotspotV1>(data),
+        "vars_v1" => summary::<BlockchainTxnVarsV1>(data),
+        message::MESSAGE_TXN_TYPE => message::summary(data),
+        token::TOKEN_TXN_TYPE => token::summary(data),
+        RAW_TXN_TYPE => raw_summary(data),
+        _ => Err(format!("Unsupported transaction type {}", txn_type).into()),
+    }
+}
+
+fn raw_summary(data: &[u8]) -> Result<String> {
+    if let Some(txn_type) = decoded_txn_type(data) {
+        return Err(format!("Raw data is an encoded {} transaction", txn_type).into());
+    }
+    Ok(format!("Sign {} bytes: {}", data.len(), hex::encode(data)))
+}
+
+/// Returns the type of the transaction the given bytes are the exact
+/// encoding of, if any
+fn decoded_txn_type(data: &[u8]) -> Option<&'static str> {
+    fn is_encoded<T: Message + Default>(data: &[u8]) -> bool {
+        let mut buf = vec![];
+        match T::decode(data) {
+            Ok(txn) => txn.encode(&mut buf).is_ok() && buf == data,
+            Err(_) => false,
+        }
+    }
+    if data.is_empty() {
+        return None;
+    }
+    type IsEncoded = fn(&[u8]) -> bool;
+    let txn_types: [(&str, IsEncoded); 12] = [
+        ("payment_v1", is_encoded::<BlockchainTxnPaymentV1>),
+        ("payment_v2", is_encoded::<BlockchainTxnPaymentV2>),
+        ("create_htlc_v1", is_encoded::<BlockchainTxnCreateHtlcV1>),
+        ("redeem_htlc_v1", is_encoded::<BlockchainTxnRedeemHtlcV1>),
+        ("add_gateway_v1", is_encoded::<BlockchainTxnAddGatewayV1>),
+        (
+            "assert_location_v1",
+            is_encoded::<BlockchainTxnAssertLocationV1>,
+        ),
+        ("oui_v1", is_encoded::<BlockchainTxnOuiV1>),
+        ("price_oracle_v1", is_encoded::<BlockchainTxnPriceOracleV1>),
+        (
+            "security_exchange_v1",
+            is_encoded::<BlockchainTxnSecurityExchangeV1>,
+        ),
+        ("token_burn_v1", is_encoded::<BlockchainTxnTokenBurnV1>),
+        (
+            "transfer_hotspot_v1",
+            is_encoded::<BlockchainTxnTransferHotspotV1>,
+        ),
+        ("vars_v1", is_encoded::<BlockchainTxnVarsV1>),
+    ];
+    txn_types
+        .iter()
+        .find(|(_, is_encoded)| is_encoded(data))
+        .map(|(txn_type, _)| *txn_type)
+}
+
+/// Answers a request with the given signer. Sign requests are passed
+/// to the approve function with their type and summary, and only
+/// signed if it returns true.
+pub fn handle<F>(signer: &dyn Signer, request: Request, approve: F) -> Response
+where
+    F: Fn(&str, &str) -> Result<bool>,
+{
+    let result = match request {
+        Request::PublicKey => signer.pubkey_bin().to_b58().map(Response::PublicKey),
+        Request::Sign {
+            txn_type,
+            summary,
+            data,
+        } => sign_request(signer, &txn_type, &summary, &data, approve).map(Response::Signature),
+    };
+    result.unwrap_or_else(|err| Response::Error(err.to_string()))
+}
+
+fn sign_request<F>(
+    signer: &dyn Signer,
+    txn_type: &str,
+    summary: &str,
+    data: &str,
+    approve: F,
+) -> Result<String>
+where
+    F: Fn(&str, &str) -> Result<bool>,
+{
+    let data = Vec::<u8>::from_b64(data)?;
+    if txn_summary(txn_type, &data)? != summary {
+        return Err("Summary does not match the transaction".into());
+    }
+    if !approve(txn_type, summary)? {
+        return Err("Signing request refused".into());
+    }
+    signer.sign_txn(txn_type, summary, &data)?.to_b64()
+}
+
+/// Connects to the signing service in the given configuration
+pub fn open(config: &Config) -> Result<Box<dyn Signer>> {
+    let signer = connect(&config.socket)?;
+    if let Some(address) = &config.address {
+        if signer.pubkey_bin().to_b58()? != *address {
+            return Err(format!(
+                "Signing service has address {}, expected {}",
+                signer.pubkey_bin(),
+                address
+            )
+            .into());
+        }
+    }
+    Ok(signer)
+}
+
+#[cfg(unix)]
+pub use self::socket::{serve, RemoteSigner};
+
+#[cfg(unix)]
+fn connect(path: &Path) -> Result<Box<dyn Signer>> {
+    Ok(Box::new(RemoteSigner::connect(path)?))
+}
+
+#[cfg(not(unix))]
+fn connect(_path: &Path) -> Result<Box<dyn Signer>> {
+    Err("Remote signers require Unix sockets".into())
+}
+
+#[cfg(not(unix))]
+pub fn serve<F>(
+    _path: &Path,
+    _replace: bool,
+    _expires: Option<Instant>,
+    _signer: &dyn Signer,
+    _approve: F,
+) -> Result
+where
+    F: Fn(&str, &str) -> Result<bool>,
+{
+    Err("Remote signers require Unix sockets".into())
+}
+
+#[cfg(unix)]
+mod socket {
+    use super::*;
+    use std::{
+        fs,
+        io::{self, BufRead, BufReader, Write},
+        os::unix::{
+            fs::{DirBuilderExt, FileTypeExt, PermissionsExt},
+            net::{UnixListener, UnixStream},
+        },
+        thread,
+        time::Duration,
+    };
+
+    /// How often an expiring server checks whether it has expired
+    const POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+    /// A signer that forwards signing requests to a signing service
+    /// over a single connection
+    pub struct RemoteSigner {
+        stream: UnixStream,
+        public: PublicKey,
+    }
+
+    impl RemoteSigner {
+        /// Connects to the signing service listening on the given
+        /// socket and asks for its public key
+        pub fn connect(path: &Path) -> Result<Self> {
+            let stream =
+                UnixStream::connect(path).map_err(|err| format!("{}: {}", path.display(), err))?;
+            let public = match call(&stream, &Request::PublicKey)? {
+                Response::PublicKey(address) => {
+                    PublicKey::try_from(PubKeyBin::from_b58(&address)?)?
+                }
+                response => return Err(unexpected(response)),
+            };
+            Ok(RemoteSigner { stream, public })
+        }
+    }
+
+    impl Signer for RemoteSigner {
+        fn public_key(&self) -> PublicKey {
+            self.public
+        }
+
+        fn sign(&self, data: &[u8]) -> Result<Vec<u8>> {
+            let summary = txn_summary(RAW_TXN_TYPE, data)?;
+            self.sign_txn(RAW_TXN_TYPE, &summary, data)
+        }
+
+        fn sign_txn(&self, txn_type: &str, summary: &str, data: &[u8]) -> Result<Vec<u8>> {
+            let request = Request::Sign {
+                txn_type: txn_type.to_string(),
+                summary: summary.to_string(),
+                data: data.to_vec().to_b64()?,
+            };
+            let signature = match call(&self.stream, &request)? {
+                Response::Signature(signature) => Vec::<u8>::from_b64(&signature)?,
+                response => return Err(unexpected(response)),
+            };
+            // Do not hand out a signature that the network would reject
+            self.public.verify(data, &signature)?;
+            Ok(signature)
+        }
+    }
+
+    fn call(mut stream: &UnixStream, request: &Request) -> Result<Response> {
+        let mut line = serde_json::to_string(request)?;
+        line.push('\n');
+        stream.write_all(line.as_bytes())?;
+        let mut line = String::new();
+        if BufReader::new(stream).read_line(&mut line)? == 0 {
+            return Err("Signing service closed the connection".into());
+        }
+        Ok(serde_json::from_str(&line)?)
+    }
+
+    fn unexpected(response: Response) -> Box<dyn std::error::Error> {
+        match response {
+            Response::Error(err) => format!("Signing service error: {}", err).into(),
+            response => format!("Unexpected signing service response: {:?}", response).into(),
+        }
+    }
+
+    /// Listens on a Unix socket at the given path that only the
+    /// current user can connect to, and answers requests with the
+    /// given signer until an error occurs or the given expiry time
+    /// passes. An existing socket at the path is only replaced if
+    /// requested. The socket is removed when serving expires.
+    ///
+    /// The socket is bound in a directory only the current user can
+    /// access and linked into place once its permissions are set, so
+    /// other users can not connect before that.
+    pub fn serve<F>(
+        path: &Path,
+        replace: bool,
+        expires: Option<Instant>,
+        signer: &dyn Signer,
+        approve: F,
+    ) -> Result
+    where
+        F: Fn(&str, &str) -> Result<bool>,
+    {
+        if let Ok(meta) = fs::symlink_metadata(path) {
+            if replace && meta.file_type().is_socket() {
+                fs::remove_file(path)?;
+            }
+        }
+        let listener = bind_private(path).map_err(|err| format!("{}: {}", path.display(), err))?;
+        // Poll for connections so serving stops when it expires
+        listener.set_nonblocking(expires.is_some())?;
+        while !is_expired(expires) {
+            match listener.accept() {
+                Ok((stream, _)) => {
+                    stream.set_nonblocking(false)?;
+                    // A client going away or sending garbage only ends
+                    // its own connection
+                    let _ = serve_connection(stream, signer, &approve, expires);
+                }
+                Err(ref err) if is_timeout(err) => thread::sleep(POLL_INTERVAL),
+                Err(err) => return Err(err.into()),
+            }
+        }
+        fs::remove_file(path)?;
+        Ok(())
+    }
+
+    fn bind_private(path: &Path) -> io::Result<UnixListener> {
+        let name = path
+            .file_name()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "not a socket path"))?;
+        let mut dir_name = std::ffi::OsString::from(".");
+        dir_name.push(name);
+        dir_name.push(format!(".{}", std::process::id()));
+        let dir = path.with_file_name(dir_name);
+        fs::DirBuilder::new().mode(0o700).create(&dir)?;
+        let private_path = dir.join(name);
+        let result = UnixListener::bind(&private_path).and_then(|listener| {
+            fs::set_permissions(&private_path, fs::Permissions::from_mode(0o600))?;
+            // Linking fails rather than replacing an existing file
+            fs::hard_link(&private_path, path)?;
+            Ok(listener)
+        });
+        let _ = fs::remove_dir_all(&dir);
+        result
+    }
+
+    pub(crate) fn serve_connection<F>(
+        stream: UnixStream,
+        signer: &dyn Signer,
+        approve: &F,
+        expires: Option<Instant>,
+    ) -> Result
+    where
+        F: Fn(&str, &str) -> Result<bool>,
+    {
+        if expires.is_some() {
+            stream.set_read_timeout(Some(POLL_INTERVAL))?;
+        }
+        let mut writer = &stream;
+        let mut reader = BufReader::new(&stream);
+        let mut line = vec![];
+        while !is_expired(expires) {
+            match reader.read_until(b'\n', &mut line) {
+                Ok(0) => break,
+                Ok(_) => (),
+                Err(ref err) if is_timeout(err) => continue,
+                Err(err) => return Err(err.into()),
+            }
+            let response = match serde_json::from_slice(&line) {
+                Ok(request) => handle(signer, request, approve),
+                Err(err) => Response::Error(format!("Invalid request: {}", err)),
+            };
+            line.clear();
+            let mut response = serde_json::to_string(&response)?;
+            response.push('\n');
+            writer.write_all(response.as_bytes())?;
+        }
+        Ok(())
+    }
+
+    fn is_expired(expires: Option<Instant>) -> bool {
+        match expires {
+            Some(expires) => Instant::now() >= expires,
+            None => false,
+        }
+    }
+
+    fn is_timeout(err: &io::Error) -> bool {
+        err.kind() == io::ErrorKind::WouldBlock || err.kind() == io::ErrorKind::TimedOut
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{keypair::Keypair, traits::Sign};
+    use std::sync::Arc;
+
+    fn payment(payer: &Keypair) -> BlockchainTxnPaymentV1 {
+        BlockchainTxnPaymentV1 {
+            payer: payer.pubkey_bin().to_vec(),
+            payee: Keypair::gen_keypair()
+                .expect("keypair")
+                .pubkey_bin()
+                .to_vec(),
+            amount: 100_000_000,
+            fee: 35_000,
+            nonce: 1,
+            signature: vec![],
+        }
+    }
+
+    #[test]
+    fn protocol_json() {
+        let request: Request = serde_json::from_str(r#"{"method":"public_key"}"#).expect("request");
+        assert_eq!(Request::PublicKey, request);
+        let request = Request::Sign {
+            txn_type: "payment_v1".to_string(),
+            summary: "Pay".to_string(),
+            data: "AAE=".to_string(),
+        };
+        let json = serde_json::to_string(&request).expect("json");
+        assert_eq!(
+            r#"{"method":"sign","txn_type":"payment_v1","summary":"Pay","data":"AAE="}"#,
+            json
+        );
+        assert_eq!(
+            r#"{"signature":"AAE="}"#,
+            serde_json::to_string(&Response::Signature("AAE=".to_string())).expect("json")
+        );
+    }
+
+    #[test]
+    fn handle_sign() {
+        let keypair = Keypair::gen_keypair().expect("keypair");
+        let txn = payment(&keypair);
+        let mut data = vec![];
+        txn.encode(&mut data).expect("encode");
+        let request = |summary: String| Request::Sign {
+            txn_type: txn.txn_type().to_string(),
+            summary,
+            data: data.to_b64().expect("b64"),
+        };
+
+        let summary = txn.summary().expect("summary");
+        match handle(&keypair, request(summary.clone()), |_, _| Ok(true)) {
+            Response::Signature(signature) => {
+                let signature = Vec::<u8>::from_b64(&signature).expect("signature");
+                assert!(txn.verify(&keypair.public, &signature).is_ok());
+            }
+            response => panic!("Unexpected response {:?}", response),
+        }
+        // A summary that does not describe the data is refused, as is a
+        // request that is not approved
+        assert!(matches!(
+            handle(&keypair, request("Pay 1 HNT".to_string()), |_, _| Ok(true)),
+            Response::Error(_)
+        ));
+        assert!(matches!(
+            handle(&keypair, request(summary), |_, _| Ok(false)),
+            Response::Error(_)
+        ));
+    }
+
+    #[test]
+    fn raw_txn() {
+        let keypair = Keypair::gen_keypair().expect("keypair");
+        let mut data = vec![];
+        payment(&keypair).encode(&mut data).expect("encode");
+        // An encoded transaction is not signed as raw data, but other
+        // data is
+        assert!(txn_summary(RAW_TXN_TYPE, &data).is_err());
+        assert!(txn_summary(RAW_TXN_TYPE, b"hello world").is_ok());
+        let request = Request::Sign {
+            txn_type: RAW_TXN_TYPE.to_string(),
+            summary: format!("Sign {} bytes: {}", data.len(), hex::encode(&data)),
+            data: data.to_b64().expect("b64"),
+        };
+        assert!(matches!(
+            handle(&keypair, request, |_, _| Ok(true)),
+            Response::Error(_)
+        ));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn client_server() {
+        use std::{os::unix::net::UnixListener, thread};
+
+        let keypair = Keypair::gen_keypair().expect("keypair");
+        let txn = payment(&keypair);
+        let path = std::env::temp_dir().join(format!("helium-signer-{}.sock", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let listener = UnixListener::bind(&path).expect("listener");
+        let keypair = Arc::new(keypair);
+        let server_keypair = keypair.clone();
+        let server = thread::spawn(move || {
+            let (stream, _) = listener.accept().expect("connection");
+            socket::serve_connection(
+                stream,
+                server_keypair.as_ref(),
+                &|txn_type: &str, _: &str| Ok(txn_type == "payment_v1"),
+                None,
+            )
+            .is_ok()
+        });
+
+        let signer = RemoteSigner::connect(&path).expect("remote signer");
+        assert_eq!(keypair.pubkey_bin(), signer.pubkey_bin());
+        let signature = txn.sign(&signer).expect("signature");
+        assert!(txn.verify(&keypair.public, &signature).is_ok());
+        // The server only approves payments
+        assert!(signer.sign(b"hello world").is_err());
+
+        drop(signer);
+        assert!(server.join().expect("server"));
+        let _ = std::fs::remove_file(&path);
+    }
+}
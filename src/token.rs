@@ -0,0 +1,224 @@
+//! Short lived sign-in tokens that prove control of a Helium address.
+//!
+//! Tokens are JSON Web Tokens with `alg` EdDSA, signed by the ed25519
+//! key of a wallet. The b58 address of the wallet is the `sub` claim,
+//! and every token is scoped to an audience and expires. A service can
+//! hand out a nonce as a challenge and require it in the `nonce` claim
+//! to rule out replayed tokens.
+
+use crate::{
+    keypair::{KeyType, PubKeyBin, PublicKey, Verify},
+    result::Result,
+    signer::Signer,
+    traits::{B58, B64},
+};
+use serde_derive::{Deserialize, Serialize};
+use std::{
+    convert::TryFrom,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// The transaction type signers see for tokens
+pub const TOKEN_TXN_TYPE: &str = "token";
+
+/// How far in the future the issue time of a token may be, to allow
+/// for clock differences between the issuer and the verifier
+const CLOCK_SKEW: u64 = 60;
+
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+struct Header {
+    alg: String,
+    typ: String,
+}
+
+/// The claims of a token
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub struct Claims {
+    /// The address of the wallet that signed the token
+    pub sub: String,
+    /// The service the token is for
+    pub aud: String,
+    /// Issue time in seconds since the epoch
+    pub iat: u64,
+    /// Expiry time in seconds since the epoch
+    pub exp: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<String>,
+}
+
+/// Issues a token for the given audience that expires after the given
+/// time to live, signed by the given signer
+pub fn issue(
+    signer: &dyn Signer,
+    audience: &str,
+    ttl: Duration,
+    nonce: Option<String>,
+) -> Result<String> {
+    if signer.public_key().key_type() != KeyType::Ed25519 {
+        return Err("EdDSA tokens need an ed25519 key".into());
+    }
+    let iat = now()?;
+    let claims = Claims {
+        sub: signer.pubkey_bin().to_b58()?,
+        aud: audience.to_string(),
+        iat,
+        exp: iat + ttl.as_secs(),
+        nonce,
+    };
+    let header = Header {
+        alg: "EdDSA".to_string(),
+        typ: "JWT".to_string(),
+    };
+    let signing_input = format!(
+        "{}.{}",
+        serde_json::to_vec(&header)?.to_b64_url()?,
+        serde_json::to_vec(&claims)?.to_b64_url()?
+    );
+    let signature = signer.sign_txn(
+        TOKEN_TXN_TYPE,
+        &summary(signing_input.as_bytes())?,
+        signing_input.as_bytes(),
+    )?;
+    Ok(format!("{}.{}", signing_input, signature.to_b64_url()?))
+}
+
+/// Checks that a token is signed by the given address for the given
+/// audience and has not expired. If a nonce is given the token has to
+/// carry that nonce. Returns the claims of the token.
+pub fn verify(token: &str, address: &str, audience: &str, nonce: Option<&str>) -> Result<Claims> {
+    let parts: Vec<&str> = token.split('.').collect();
+    if parts.len() != 3 {
+        return Err("Invalid token".into());
+    }
+    let signing_input = &token[..parts[0].len() + 1 + parts[1].len()];
+    let claims = decode_claims(signing_input)?;
+    let public_key = PublicKey::try_from(PubKeyBin::from_b58(&claims.sub)?)?;
+    if public_key.key_type() != KeyType::Ed25519 {
+        return Err("Token is not signed by an ed25519 key".into());
+    }
+    public_key.verify(
+        signing_input.as_bytes(),
+        &Vec::<u8>::from_b64_url(parts[2])?,
+    )?;
+
+    if claims.sub != address {
+        return Err(format!("Token is for {}, not {}", claims.sub, address).into());
+    }
+    if claims.aud != audience {
+        return Err(format!("Token is for audience {}, not {}", claims.aud, audience).into());
+    }
+    let now = now()?;
+    if claims.exp <= now {
+        return Err("Token has expired".into());
+    }
+    if claims.iat > now + CLOCK_SKEW {
+        return Err("Token is issued in the future".into());
+    }
+    if let Some(nonce) = nonce {
+        if claims.nonce.as_deref() != Some(nonce) {
+            return Err("Token nonce does not match".into());
+        }
+    }
+    Ok(claims)
+}
+
+/// Returns the claims of a token without verifying it
+pub fn decode(token: &str) -> Result<Claims> {
+    match token.rfind('.') {
+        Some(end) => decode_claims(&token[..end]),
+        None => Err("Invalid token".into()),
+    }
+}
+
+/// Returns the summary signers show for the signing input of a token,
+/// or an error if the data is not a token signing input
+pub fn summary(data: &[u8]) -> Result<String> {
+    let claims = decode_claims(std::str::from_utf8(data)?)?;
+    let nonce = match &claims.nonce {
+        Some(nonce) => format!(" with nonce {}", nonce),
+        None => String::new(),
+    };
+    Ok(format!(
+        "Sign in to {} as {} for {} seconds{}",
+        claims.aud,
+        claims.sub,
+        claims.exp.saturating_sub(claims.iat),
+        nonce
+    ))
+}
+
+/// Decodes the header and claims of a token signing input
+fn decode_claims(signing_input: &str) -> Result<Claims> {
+    let mut parts = signing_input.split('.');
+    let (header, claims) = match (parts.next(), parts.next(), parts.next()) {
+        (Some(header), Some(claims), None) => (header, claims),
+        _ => return Err("Invalid token".into()),
+    };
+    let header: Header = serde_json::from_slice(&Vec::<u8>::from_b64_url(header)?)?;
+    if header.alg != "EdDSA" {
+        return Err(format!("Unsupported token algorithm {}", header.alg).into());
+    }
+    Ok(serde_json::from_slice(&Vec::<u8>::from_b64_url(claims)?)?)
+}
+
+fn now() -> Result<u64> {
+    Ok(SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keypair::Keypair;
+
+    #[test]
+    fn issue_verify() {
+        let keypair = Keypair::gen_keypair().expect("keypair");
+        let address = keypair.pubkey_bin().to_b58().expect("address");
+        let token = issue(
+            &keypair,
+            "dashboard",
+            Duration::from_secs(60),
+            Some("abc".to_string()),
+        )
+        .expect("token");
+
+        let claims = verify(&token, &address, "dashboard", Some("abc")).expect("claims");
+        assert_eq!(address, claims.sub);
+        assert_eq!(claims, decode(&token).expect("claims"));
+        assert!(verify(&token, &address, "dashboard", None).is_ok());
+        assert!(verify(&token, &address, "other", None).is_err());
+        assert!(verify(&token, &address, "dashboard", Some("xyz")).is_err());
+        let other = Keypair::gen_keypair()
+            .expect("keypair")
+            .pubkey_bin()
+            .to_b58()
+            .expect("address");
+        assert!(verify(&token, &other, "dashboard", None).is_err());
+
+        // Changing the claims breaks the signature
+        let parts: Vec<&str> = token.split('.').collect();
+        let mut claims = decode(&token).expect("claims");
+        claims.exp += 3600;
+        let forged = format!(
+            "{}.{}.{}",
+            parts[0],
+            serde_json::to_vec(&claims)
+                .expect("json")
+                .to_b64_url()
+                .expect("b64"),
+            parts[2]
+        );
+        assert!(verify(&forged, &address, "dashboard", None).is_err());
+    }
+
+    #[test]
+    fn expired() {
+        let keypair = Keypair::gen_keypair().expect("keypair");
+        let address = keypair.pubkey_bin().to_b58().expect("address");
+        let token = issue(&keypair, "dashboard", Duration::from_secs(0), None).expect("token");
+        assert!(verify(&token, &address, "dashboard", None).is_err());
+
+        let keypair = Keypair::gen_keypair_with_type(KeyType::EccCompact).expect("keypair");
+        assert!(issue(&keypair, "dashboard", Duration::from_secs(60), None).is_err());
+    }
+}
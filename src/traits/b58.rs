@@ -1,7 +1,8 @@
 use crate::{
-    keypair::{PubKeyBin, PublicKey, KEYTYPE_ED25519},
+    keypair::{PubKeyBin, PublicKey},
     result::Result,
 };
+use std::convert::TryFrom;
 
 pub trait B58 {
     fn to_b58(&self) -> Result<String>;
@@ -18,12 +19,7 @@ impl B58 for PublicKey {
 
     fn from_b58(b58: &str) -> Result<PublicKey> {
         let pubkey_bin = PubKeyBin::from_b58(b58)?;
-
-        if pubkey_bin.0[0] != KEYTYPE_ED25519 {
-            return Err(format!("Invalid key type {}", pubkey_bin.0[0]).into());
-        }
-        let pubkey: PublicKey = pubkey_bin.into();
-        Ok(pubkey)
+        PublicKey::try_from(pubkey_bin)
     }
 }
 
@@ -40,7 +36,13 @@ impl B58 for PubKeyBin {
         // First 0 value is the version byte
         let data = bs58::decode(b58).with_check(Some(0)).into_vec()?;
         let mut pubkey_bin = PubKeyBin::default();
+        if data.len() != pubkey_bin.0.len() + 1 {
+            return Err(format!("Invalid address length {}", data.len()).into());
+        }
         pubkey_bin.0.copy_from_slice(&data[1..]);
+        // Reject addresses for unknown networks or key types
+        pubkey_bin.network()?;
+        pubkey_bin.key_type()?;
         Ok(pubkey_bin)
     }
 }
@@ -6,6 +6,7 @@ pub use self::sign::Sign;
 pub use self::txn_envelope::TxnEnvelope;
 pub use self::txn_fee::{TxnFee, TxnFeeConfig, TxnStakingFee};
 pub use self::txn_payer::TxnPayer;
+pub use self::txn_summary::TxnSummary;
 
 pub mod b58;
 pub mod b64;
@@ -15,3 +16,4 @@ pub mod sign;
 pub mod txn_envelope;
 pub mod txn_fee;
 pub mod txn_payer;
+pub mod txn_summary;
@@ -1,9 +1,9 @@
 use crate::{
-    keypair::{PubKeyBin, PublicKey, KEYTYPE_ED25519},
+    keypair::{PubKeyBin, PublicKey},
     result::Result,
 };
 use io::{Read, Write};
-use std::io;
+use std::{convert::TryFrom, io};
 
 pub trait ReadWrite {
     fn read(reader: &mut dyn Read) -> Result<Self>
@@ -20,11 +20,7 @@ impl ReadWrite for PublicKey {
 
     fn read(reader: &mut dyn Read) -> Result<PublicKey> {
         let pubkey_bin = PubKeyBin::read(reader)?;
-        if pubkey_bin.0[0] != KEYTYPE_ED25519 {
-            return Err(format!("Invalid key type {}", pubkey_bin.0[0]).into());
-        }
-        let pubkey: PublicKey = pubkey_bin.into();
-        Ok(pubkey)
+        PublicKey::try_from(pubkey_bin)
     }
 }
 
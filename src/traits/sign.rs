@@ -1,5 +1,7 @@
-use crate::keypair::{Keypair, PublicKey, Verify};
+use crate::keypair::{PublicKey, Verify};
 use crate::result::Result;
+use crate::signer::Signer;
+use crate::traits::TxnSummary;
 use helium_api::{
     BlockchainTxnAddGatewayV1, BlockchainTxnAssertLocationV1, BlockchainTxnCreateHtlcV1,
     BlockchainTxnOuiV1, BlockchainTxnPaymentV1, BlockchainTxnPaymentV2, BlockchainTxnPriceOracleV1,
@@ -7,8 +9,8 @@ use helium_api::{
     BlockchainTxnTransferHotspotV1, BlockchainTxnVarsV1, Message,
 };
 
-pub trait Sign: Message + std::clone::Clone {
-    fn sign(&self, keypair: &Keypair) -> Result<Vec<u8>>
+pub trait Sign: Message + std::clone::Clone + TxnSummary {
+    fn sign(&self, signer: &dyn Signer) -> Result<Vec<u8>>
     where
         Self: std::marker::Sized;
     fn verify(&self, pubkey: &PublicKey, signature: &[u8]) -> Result;
@@ -17,12 +19,12 @@ pub trait Sign: Message + std::clone::Clone {
 macro_rules! impl_sign {
     ($txn_type:ty, $( $sig: ident ),+ ) => {
         impl Sign for $txn_type {
-            fn sign(&self, keypair: &Keypair) -> Result<Vec<u8>> {
+            fn sign(&self, signer: &dyn Signer) -> Result<Vec<u8>> {
                 let mut buf = vec![];
                 let mut txn = self.clone();
                 $(txn.$sig = vec![];)+
                 txn.encode(& mut buf)?;
-                Ok(keypair.sign(&buf))
+                signer.sign_txn(self.txn_type(), &self.summary()?, &buf)
             }
 
             fn verify(&self, pubkey: &PublicKey, signature: &[u8]) -> Result {
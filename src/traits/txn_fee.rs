@@ -186,8 +186,8 @@ mod tests {
 
     #[test]
     fn payment_v1_fee() {
-        let payer = Keypair::gen_keypair();
-        let payee = Keypair::gen_keypair();
+        let payer = Keypair::gen_keypair().expect("keypair");
+        let payee = Keypair::gen_keypair().expect("keypair");
         let fee_config = TxnFeeConfig::active();
         let mut txn = BlockchainTxnPaymentV1 {
             payee: payee.pubkey_bin().into(),
@@ -210,8 +210,8 @@ mod tests {
 
     #[test]
     fn payment_v2_fee() {
-        let payer = Keypair::gen_keypair();
-        let payee = Keypair::gen_keypair();
+        let payer = Keypair::gen_keypair().expect("keypair");
+        let payee = Keypair::gen_keypair().expect("keypair");
         let fee_config = TxnFeeConfig::active();
         let payment = Payment {
             payee: payee.pubkey_bin().into(),
@@ -230,8 +230,8 @@ mod tests {
 
     #[test]
     fn create_htlc_fee() {
-        let payer = Keypair::gen_keypair();
-        let payee = Keypair::gen_keypair();
+        let payer = Keypair::gen_keypair().expect("keypair");
+        let payee = Keypair::gen_keypair().expect("keypair");
         let fee_config = TxnFeeConfig::active();
         let txn = BlockchainTxnCreateHtlcV1 {
             amount: 10_000,
@@ -250,8 +250,8 @@ mod tests {
 
     #[test]
     fn redeem_htlc_fee() {
-        let payer = Keypair::gen_keypair();
-        let payee = Keypair::gen_keypair();
+        let payer = Keypair::gen_keypair().expect("keypair");
+        let payee = Keypair::gen_keypair().expect("keypair");
         let fee_config = TxnFeeConfig::active();
         let txn = BlockchainTxnRedeemHtlcV1 {
             fee: 0,
@@ -266,8 +266,8 @@ mod tests {
 
     #[test]
     fn security_exchange_fee() {
-        let payer = Keypair::gen_keypair();
-        let payee = Keypair::gen_keypair();
+        let payer = Keypair::gen_keypair().expect("keypair");
+        let payee = Keypair::gen_keypair().expect("keypair");
         let fee_config = TxnFeeConfig::active();
         let txn = BlockchainTxnSecurityExchangeV1 {
             payee: payee.pubkey_bin().into(),
@@ -283,8 +283,8 @@ mod tests {
 
     #[test]
     fn add_gateway_fee() {
-        let owner = Keypair::gen_keypair();
-        let gateway = Keypair::gen_keypair();
+        let owner = Keypair::gen_keypair().expect("keypair");
+        let gateway = Keypair::gen_keypair().expect("keypair");
         let mut txn = BlockchainTxnAddGatewayV1 {
             owner: owner.pubkey_bin().into(),
             gateway: gateway.pubkey_bin().into(),
@@ -315,7 +315,7 @@ mod tests {
 
     #[test]
     fn oui_fee() {
-        let owner = Keypair::gen_keypair();
+        let owner = Keypair::gen_keypair().expect("keypair");
         let mut txn = BlockchainTxnOuiV1 {
             owner: owner.pubkey_bin().into(),
             payer: vec![],
@@ -0,0 +1,282 @@
+use crate::{keypair::PubKeyBin, result::Result, traits::B58};
+use helium_api::{
+    BlockchainTxnAddGatewayV1, BlockchainTxnAssertLocationV1, BlockchainTxnCreateHtlcV1,
+    BlockchainTxnOuiV1, BlockchainTxnPaymentV1, BlockchainTxnPaymentV2, BlockchainTxnPriceOracleV1,
+    BlockchainTxnRedeemHtlcV1, BlockchainTxnSecurityExchangeV1, BlockchainTxnTokenBurnV1,
+    BlockchainTxnTransferHotspotV1, BlockchainTxnVarsV1, Hnt, Hst,
+};
+
+/// Describes a transaction to whoever is asked to sign it, for example
+/// the operator of a remote signer.
+pub trait TxnSummary {
+    /// The type of the transaction, e.g. "payment_v2"
+    fn txn_type(&self) -> &'static str;
+
+    /// A one line, human readable description of what the transaction
+    /// does
+    fn summary(&self) -> Result<String>;
+}
+
+fn address(data: &[u8]) -> Result<String> {
+    match data.len() {
+        0 => Ok("none".to_string()),
+        len if len == PubKeyBin::default().0.len() => PubKeyBin::from_vec(data).to_b58(),
+        _ => data.to_vec().to_b58(),
+    }
+}
+
+/// Describes who pays the transaction fee when it is not the owner of
+/// the transaction
+fn paid_by(payer: &[u8]) -> Result<String> {
+    if payer.is_empty() {
+        Ok(String::new())
+    } else {
+        Ok(format!(", paid by {}", address(payer)?))
+    }
+}
+
+impl TxnSummary for BlockchainTxnPaymentV1 {
+    fn txn_type(&self) -> &'static str {
+        "payment_v1"
+    }
+
+    fn summary(&self) -> Result<String> {
+        Ok(format!(
+            "Pay {} HNT from {} to {} (fee {} DC, nonce {})",
+            Hnt::from_bones(self.amount),
+            address(&self.payer)?,
+            address(&self.payee)?,
+            self.fee,
+            self.nonce
+        ))
+    }
+}
+
+impl TxnSummary for BlockchainTxnPaymentV2 {
+    fn txn_type(&self) -> &'static str {
+        "payment_v2"
+    }
+
+    fn summary(&self) -> Result<String> {
+        let mut payments = Vec::with_capacity(self.payments.len());
+        for payment in &self.payments {
+            payments.push(format!(
+                "{} HNT to {}",
+                Hnt::from_bones(payment.amount),
+                address(&payment.payee)?
+            ));
+        }
+        Ok(format!(
+            "Pay {} from {} (fee {} DC, nonce {})",
+            payments.join(", "),
+            address(&self.payer)?,
+            self.fee,
+            self.nonce
+        ))
+    }
+}
+
+impl TxnSummary for BlockchainTxnCreateHtlcV1 {
+    fn txn_type(&self) -> &'static str {
+        "create_htlc_v1"
+    }
+
+    fn summary(&self) -> Result<String> {
+        Ok(format!(
+            "Lock {} HNT from {} for {} in HTLC {} until block {} (fee {} DC, nonce {})",
+            Hnt::from_bones(self.amount),
+            address(&self.payer)?,
+            address(&self.payee)?,
+            address(&self.address)?,
+            self.timelock,
+            self.fee,
+            self.nonce
+        ))
+    }
+}
+
+impl TxnSummary for BlockchainTxnRedeemHtlcV1 {
+    fn txn_type(&self) -> &'static str {
+        "redeem_htlc_v1"
+    }
+
+    fn summary(&self) -> Result<String> {
+        Ok(format!(
+            "Redeem HTLC {} for {} (fee {} DC)",
+            address(&self.address)?,
+            address(&self.payee)?,
+            self.fee
+        ))
+    }
+}
+
+impl TxnSummary for BlockchainTxnAddGatewayV1 {
+    fn txn_type(&self) -> &'static str {
+        "add_gateway_v1"
+    }
+
+    fn summary(&self) -> Result<String> {
+        Ok(format!(
+            "Add hotspot {} for owner {}{} (staking fee {} DC, fee {} DC)",
+            address(&self.gateway)?,
+            address(&self.owner)?,
+            paid_by(&self.payer)?,
+            self.staking_fee,
+            self.fee
+        ))
+    }
+}
+
+impl TxnSummary for BlockchainTxnAssertLocationV1 {
+    fn txn_type(&self) -> &'static str {
+        "assert_location_v1"
+    }
+
+    fn summary(&self) -> Result<String> {
+        Ok(format!(
+            "Assert location {} for hotspot {} of owner {}{} (staking fee {} DC, fee {} DC, nonce {})",
+            self.location,
+            address(&self.gateway)?,
+            address(&self.owner)?,
+            paid_by(&self.payer)?,
+            self.staking_fee,
+            self.fee,
+            self.nonce
+        ))
+    }
+}
+
+impl TxnSummary for BlockchainTxnOuiV1 {
+    fn txn_type(&self) -> &'static str {
+        "oui_v1"
+    }
+
+    fn summary(&self) -> Result<String> {
+        Ok(format!(
+            "Create OUI {} for owner {}{} with {} router(s) and subnet size {} (staking fee {} DC, fee {} DC)",
+            self.oui,
+            address(&self.owner)?,
+            paid_by(&self.payer)?,
+            self.addresses.len(),
+            self.requested_subnet_size,
+            self.staking_fee,
+            self.fee
+        ))
+    }
+}
+
+impl TxnSummary for BlockchainTxnPriceOracleV1 {
+    fn txn_type(&self) -> &'static str {
+        "price_oracle_v1"
+    }
+
+    fn summary(&self) -> Result<String> {
+        Ok(format!(
+            "Report a price of {}.{:08} USD at block {} from oracle {}",
+            self.price / 100_000_000,
+            self.price % 100_000_000,
+            self.block_height,
+            address(&self.public_key)?
+        ))
+    }
+}
+
+impl TxnSummary for BlockchainTxnSecurityExchangeV1 {
+    fn txn_type(&self) -> &'static str {
+        "security_exchange_v1"
+    }
+
+    fn summary(&self) -> Result<String> {
+        Ok(format!(
+            "Transfer {} HST from {} to {} (fee {} DC, nonce {})",
+            Hst::from_bones(self.amount),
+            address(&self.payer)?,
+            address(&self.payee)?,
+            self.fee,
+            self.nonce
+        ))
+    }
+}
+
+impl TxnSummary for BlockchainTxnTokenBurnV1 {
+    fn txn_type(&self) -> &'static str {
+        "token_burn_v1"
+    }
+
+    fn summary(&self) -> Result<String> {
+        Ok(format!(
+            "Burn {} HNT from {} for data credits for {} with memo {} (fee {} DC, nonce {})",
+            Hnt::from_bones(self.amount),
+            address(&self.payer)?,
+            address(&self.payee)?,
+            self.memo,
+            self.fee,
+            self.nonce
+        ))
+    }
+}
+
+impl TxnSummary for BlockchainTxnTransferHotspotV1 {
+    fn txn_type(&self) -> &'static str {
+        "transfer_hotspot_v1"
+    }
+
+    fn summary(&self) -> Result<String> {
+        Ok(format!(
+            "Transfer hotspot {} from {} to {} for {} HNT (fee {} DC, buyer nonce {})",
+            address(&self.gateway)?,
+            address(&self.seller)?,
+            address(&self.buyer)?,
+            Hnt::from_bones(self.amount_to_seller),
+            self.fee,
+            self.buyer_nonce
+        ))
+    }
+}
+
+impl TxnSummary for BlockchainTxnVarsV1 {
+    fn txn_type(&self) -> &'static str {
+        "vars_v1"
+    }
+
+    fn summary(&self) -> Result<String> {
+        let names: Vec<&str> = self.vars.iter().map(|var| var.name.as_str()).collect();
+        Ok(format!(
+            "Set chain vars [{}], unset {}, cancel {} (nonce {})",
+            names.join(", "),
+            self.unsets.len(),
+            self.cancels.len(),
+            self.nonce
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keypair::Keypair;
+
+    #[test]
+    fn payment_summary() {
+        let payer = PubKeyBin::from(&Keypair::gen_keypair().expect("keypair").public);
+        let payee = PubKeyBin::from(&Keypair::gen_keypair().expect("keypair").public);
+        let txn = BlockchainTxnPaymentV1 {
+            payer: payer.to_vec(),
+            payee: payee.to_vec(),
+            amount: 150_000_000,
+            fee: 35_000,
+            nonce: 7,
+            signature: vec![],
+        };
+        assert_eq!("payment_v1", txn.txn_type());
+        assert_eq!(
+            format!(
+                "Pay {} HNT from {} to {} (fee 35000 DC, nonce 7)",
+                Hnt::from_bones(150_000_000),
+                payer,
+                payee
+            ),
+            txn.summary().expect("summary")
+        );
+    }
+}
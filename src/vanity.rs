@@ -0,0 +1,238 @@
+use crate::{
+    keypair::{Keypair, Network},
+    result::Result,
+    traits::B58,
+};
+use regex::{Regex, RegexBuilder};
+use std::{
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        mpsc, Arc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+/// The characters that can appear in a base58 encoded address
+const B58_ALPHABET: &str = "123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+
+/// Every address starts with a "1" for the version byte followed by a
+/// character that is fixed by the network and key type. Prefixes are
+/// matched against the characters that follow.
+pub const ADDRESS_PREFIX_LEN: usize = 2;
+
+/// How many keys a search thread generates between checks for a
+/// match found by another thread
+const SEARCH_BATCH: u64 = 64;
+
+/// A pattern for an address to search for
+pub struct Pattern {
+    prefix: Option<String>,
+    suffix: Option<String>,
+    regex: Option<Regex>,
+    ignore_case: bool,
+}
+
+impl Pattern {
+    /// Constructs a pattern from an optional prefix, suffix and
+    /// regular expression, all of which have to match. Returns an
+    /// error if the prefix or suffix contains characters that can not
+    /// appear in a base58 address.
+    pub fn new(
+        prefix: Option<&str>,
+        suffix: Option<&str>,
+        regex: Option<&str>,
+        ignore_case: bool,
+    ) -> Result<Self> {
+        if prefix.is_none() && suffix.is_none() && regex.is_none() {
+            return Err("At least one of a prefix, suffix or regex is required".into());
+        }
+        for part in prefix.iter().chain(suffix.iter()) {
+            for c in part.chars() {
+                if char_matches(c, ignore_case) == 0 {
+                    return Err(
+                        format!("Character '{}' can not appear in a base58 address", c).into(),
+                    );
+                }
+            }
+        }
+        let regex = match regex {
+            Some(regex) => Some(
+                RegexBuilder::new(regex)
+                    .case_insensitive(ignore_case)
+                    .build()?,
+            ),
+            None => None,
+        };
+        let normalize = |s: &str| {
+            if ignore_case {
+                s.to_lowercase()
+            } else {
+                s.to_string()
+            }
+        };
+        Ok(Self {
+            prefix: prefix.map(normalize),
+            suffix: suffix.map(normalize),
+            regex,
+            ignore_case,
+        })
+    }
+
+    pub fn is_match(&self, address: &str) -> bool {
+        let address = if self.ignore_case {
+            address.to_lowercase()
+        } else {
+            address.to_string()
+        };
+        if let Some(prefix) = &self.prefix {
+            if !address[ADDRESS_PREFIX_LEN..].starts_with(prefix.as_str()) {
+                return false;
+            }
+        }
+        if let Some(suffix) = &self.suffix {
+            if !address.ends_with(suffix.as_str()) {
+                return false;
+            }
+        }
+        match &self.regex {
+            Some(regex) => regex.is_match(&address),
+            None => true,
+        }
+    }
+
+    /// The expected number of keys to generate before finding a
+    /// match. The difficulty of a regular expression is not known.
+    pub fn difficulty(&self) -> Option<f64> {
+        if self.regex.is_some() {
+            return None;
+        }
+        let difficulty = self
+            .prefix
+            .iter()
+            .chain(self.suffix.iter())
+            .flat_map(|part| part.chars())
+            .map(|c| B58_ALPHABET.len() as f64 / char_matches(c, self.ignore_case) as f64)
+            .product();
+        Some(difficulty)
+    }
+}
+
+/// The number of base58 characters a given character matches
+fn char_matches(c: char, ignore_case: bool) -> usize {
+    if ignore_case {
+        B58_ALPHABET
+            .chars()
+            .filter(|a| a.to_lowercase().eq(c.to_lowercase()))
+            .count()
+    } else {
+        B58_ALPHABET.chars().filter(|a| *a == c).count()
+    }
+}
+
+/// Searches for a keypair on the given network with an address that
+/// matches the given pattern using the given number of threads. The
+/// progress function is called about once a second with the number of
+/// keys generated so far and the time spent.
+pub fn search<F>(pattern: Pattern, network: Network, threads: usize, progress: F) -> Result<Keypair>
+where
+    F: Fn(u64, Duration),
+{
+    let pattern = Arc::new(pattern);
+    let found = Arc::new(AtomicBool::new(false));
+    let attempts = Arc::new(AtomicU64::new(0));
+    let (sender, receiver) = mpsc::channel();
+    let mut handles = Vec::with_capacity(threads);
+    for _ in 0..threads.max(1) {
+        let pattern = pattern.clone();
+        let found = found.clone();
+        let attempts = attempts.clone();
+        let sender = sender.clone();
+        handles.push(thread::spawn(move || {
+            while !found.load(Ordering::Relaxed) {
+                for _ in 0..SEARCH_BATCH {
+                    let keypair = match Keypair::gen_keypair() {
+                        Ok(keypair) => keypair.with_network(network),
+                        // The search ends without a result once every
+                        // thread stops
+                        Err(_) => return,
+                    };
+                    let address = keypair.public.to_b58().expect("address");
+                    if pattern.is_match(&address) {
+                        found.store(true, Ordering::Relaxed);
+                        let _ = sender.send(keypair);
+                        break;
+                    }
+                }
+                attempts.fetch_add(SEARCH_BATCH, Ordering::Relaxed);
+            }
+        }));
+    }
+    drop(sender);
+
+    let start = Instant::now();
+    let keypair = loop {
+        match receiver.recv_timeout(Duration::from_secs(1)) {
+            Ok(keypair) => break keypair,
+            Err(mpsc::RecvTimeoutError::Timeout) => {
+                progress(attempts.load(Ordering::Relaxed), start.elapsed())
+            }
+            Err(mpsc::RecvTimeoutError::Disconnected) => {
+                return Err("Vanity search stopped without a result".into())
+            }
+        }
+    };
+    found.store(true, Ordering::Relaxed);
+    for handle in handles {
+        let _ = handle.join();
+    }
+    Ok(keypair)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn invalid_characters() {
+        assert!(Pattern::new(Some("abc0"), None, None, false).is_err());
+        assert!(Pattern::new(None, Some("Ol"), None, false).is_err());
+        // Lower case l is not valid, but upper case L is
+        assert!(Pattern::new(None, Some("l"), None, true).is_ok());
+        assert!(Pattern::new(None, Some("0"), None, true).is_err());
+        assert!(Pattern::new(None, None, Some("("), false).is_err());
+        assert!(Pattern::new(None, None, None, false).is_err());
+    }
+
+    #[test]
+    fn match_address() {
+        let address = "14b8NWbxikG9ccvZV4Mp1xQoYgMSjSZScNpvgqq8rrQiSiF3x9g";
+        let pattern = Pattern::new(Some("b8N"), Some("x9g"), None, false).expect("pattern");
+        assert!(pattern.is_match(address));
+        let pattern = Pattern::new(Some("B8n"), None, None, false).expect("pattern");
+        assert!(!pattern.is_match(address));
+        let pattern = Pattern::new(Some("B8n"), Some("X9G"), None, true).expect("pattern");
+        assert!(pattern.is_match(address));
+        let pattern = Pattern::new(None, None, Some("^1.b8N.*[0-9]g$"), false).expect("pattern");
+        assert!(pattern.is_match(address));
+    }
+
+    #[test]
+    fn pattern_difficulty() {
+        let pattern = Pattern::new(Some("ab"), None, None, false).expect("pattern");
+        assert_eq!(Some(58.0 * 58.0), pattern.difficulty());
+        let pattern = Pattern::new(Some("a"), Some("9"), None, true).expect("pattern");
+        assert_eq!(Some(29.0 * 58.0), pattern.difficulty());
+        let pattern = Pattern::new(None, None, Some("a"), true).expect("pattern");
+        assert_eq!(None, pattern.difficulty());
+    }
+
+    #[test]
+    fn search_suffix() {
+        let pattern = Pattern::new(None, Some("a"), None, true).expect("pattern");
+        let keypair = search(pattern, Network::Testnet, 2, |_, _| ()).expect("keypair");
+        let address = keypair.public.to_b58().expect("address");
+        assert!(address.to_lowercase().ends_with('a'));
+        assert_eq!(Network::Testnet, keypair.network());
+    }
+}
@@ -1,15 +1,22 @@
 use crate::{
-    format::{self, Format},
-    keypair::{Keypair, PubKeyBin},
+    encrypt,
+    format::{self, Format, KeyShare, Slot},
+    keypair::{Keypair, Network, PubKeyBin, PublicKey, KEYPAIR_MAX_LENGTH},
     pwhash::PWHash,
     result::Result,
+    secret::Secret,
     traits::{ReadWrite, B58},
 };
 use aead::NewAead;
 use aes_gcm::Aes256Gcm;
 use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
 use sodiumoxide::randombytes;
-use std::io::{self, Cursor};
+use std::{
+    io::{self, Cursor, Read},
+    time::{SystemTime, UNIX_EPOCH},
+};
 
 pub type Tag = [u8; 16];
 pub type IV = [u8; 12];
@@ -17,12 +24,27 @@ pub type AESKey = [u8; 32];
 
 const WALLET_KIND_BASIC_V1: u16 = 0x0001;
 const WALLET_KIND_BASIC_V2: u16 = 0x0002;
+const WALLET_KIND_BASIC_V3: u16 = 0x0003;
 
 const WALLET_KIND_SHARDED_V1: u16 = 0x0101;
 const WALLET_KIND_SHARDED_V2: u16 = 0x0102;
+const WALLET_KIND_SHARDED_V3: u16 = 0x0103;
+// Sharded V3 is taken by generation tagged shards, so the sharded
+// version of the authenticated header format is V4
+const WALLET_KIND_SHARDED_V4: u16 = 0x0104;
+
+const WALLET_KIND_SEALED_V1: u16 = 0x0201;
+
+const WALLET_KIND_SLOTTED_V1: u16 = 0x0301;
+
+/// Derives the key that authenticates the slot table of a slotted
+/// wallet from its data key
+const SLOT_TABLE_CONTEXT: &[u8] = b"helium-wallet slot table";
 
-const PWHASH_KIND_PBKDF2: u8 = 0;
-const PWHASH_KIND_ARGON2ID13: u8 = 1;
+const METADATA_LABEL: u8 = 1;
+const METADATA_NETWORK: u8 = 2;
+const METADATA_CREATED: u8 = 3;
+const METADATA_KEYFILE: u8 = 4;
 
 pub struct Wallet {
     pub pubkey_bin: PubKeyBin,
@@ -30,66 +52,362 @@ pub struct Wallet {
     pub tag: Tag,
     pub encrypted: Vec<u8>,
     pub format: Format,
+    /// Metadata of wallets in the authenticated header format (basic V3
+    /// and sharded V4), where the AES-GCM tag covers the whole header.
+    /// Wallets in older formats have no metadata and only authenticate
+    /// their address.
+    pub metadata: Option<Metadata>,
+}
+
+/// Optional information about a wallet that is stored, authenticated
+/// but not encrypted, in the wallet header
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Metadata {
+    pub label: Option<String>,
+    pub network: Option<Network>,
+    /// Creation time in seconds since the epoch
+    pub created: Option<u64>,
+    /// Whether decrypting the wallet needs a keyfile besides the
+    /// password
+    pub keyfile: bool,
+}
+
+impl Metadata {
+    /// Metadata for a new wallet on the given network
+    pub fn new(network: Network, label: Option<String>) -> Self {
+        let created = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .ok();
+        Metadata {
+            label,
+            network: Some(network),
+            created,
+            keyfile: false,
+        }
+    }
+
+    /// Reads metadata entries. Each entry is a tag byte, a u16 length
+    /// and the value. Entries with unknown tags are skipped.
+    pub fn read(reader: &mut dyn io::Read) -> Result<Self> {
+        let len = reader.read_u16::<LittleEndian>()?;
+        let mut data = vec![0; len as usize];
+        reader.read_exact(&mut data)?;
+
+        let mut metadata = Metadata::default();
+        let mut entries = Cursor::new(data);
+        while entries.position() < len as u64 {
+            let tag = entries.read_u8()?;
+            let mut value = vec![0; entries.read_u16::<LittleEndian>()? as usize];
+            entries.read_exact(&mut value)?;
+            match tag {
+                METADATA_LABEL => metadata.label = Some(String::from_utf8(value)?),
+                METADATA_NETWORK => {
+                    let byte = *value.first().ok_or("Invalid wallet network")?;
+                    metadata.network = Some(Network::from_byte(byte)?)
+                }
+                METADATA_CREATED => {
+                    metadata.created = Some(Cursor::new(value).read_u64::<LittleEndian>()?)
+                }
+                METADATA_KEYFILE => metadata.keyfile = true,
+                _ => (),
+            }
+        }
+        Ok(metadata)
+    }
+
+    pub fn write(&self, writer: &mut dyn io::Write) -> Result {
+        let mut entries = vec![];
+        if let Some(label) = &self.label {
+            Self::write_entry(&mut entries, METADATA_LABEL, label.as_bytes())?;
+        }
+        if let Some(network) = self.network {
+            Self::write_entry(&mut entries, METADATA_NETWORK, &[network.to_byte()])?;
+        }
+        if let Some(created) = self.created {
+            Self::write_entry(&mut entries, METADATA_CREATED, &created.to_le_bytes())?;
+        }
+        if self.keyfile {
+            Self::write_entry(&mut entries, METADATA_KEYFILE, &[])?;
+        }
+        if entries.len() > u16::MAX as usize {
+            return Err("Wallet metadata is too large".into());
+        }
+        writer.write_u16::<LittleEndian>(entries.len() as u16)?;
+        writer.write_all(&entries)?;
+        Ok(())
+    }
+
+    fn write_entry(writer: &mut dyn io::Write, tag: u8, value: &[u8]) -> Result {
+        if value.len() > u16::MAX as usize {
+            return Err("Wallet metadata entry is too large".into());
+        }
+        writer.write_u8(tag)?;
+        writer.write_u16::<LittleEndian>(value.len() as u16)?;
+        writer.write_all(value)?;
+        Ok(())
+    }
 }
 
 impl Wallet {
+    /// Encrypts a keypair into a wallet with metadata for the network
+    /// of the keypair and the current time.
     pub fn encrypt(keypair: &Keypair, password: &[u8], fmt: Format) -> Result<Wallet> {
-        let mut encryption_key = AESKey::default();
+        let metadata = Metadata::new(keypair.network(), None);
+        Self::encrypt_with_metadata(keypair, password, None, fmt, metadata)
+    }
+
+    /// Encrypts a keypair into a wallet with the given metadata. If a
+    /// keyfile is given, the wallet records that it needs the keyfile
+    /// and can only be decrypted with both the password and the
+    /// keyfile.
+    pub fn encrypt_with_metadata(
+        keypair: &Keypair,
+        password: &[u8],
+        keyfile: Option<&[u8]>,
+        fmt: Format,
+        mut metadata: Metadata,
+    ) -> Result<Wallet> {
+        metadata.keyfile = keyfile.is_some();
+        Self::encrypt_with(keypair, password, keyfile, fmt, Some(metadata))
+    }
+
+    fn encrypt_with(
+        keypair: &Keypair,
+        password: &[u8],
+        keyfile: Option<&[u8]>,
+        fmt: Format,
+        metadata: Option<Metadata>,
+    ) -> Result<Wallet> {
+        if let Some(keyfile) = keyfile {
+            if keyfile.is_empty() {
+                return Err("Keyfile is empty".into());
+            }
+            if let Format::Slotted(_) = fmt {
+                return Err("Wallets with unlock slots use keyfile slots instead".into());
+            }
+        }
+        let mut encryption_key = Secret::<AESKey>::zeroed();
         let mut format = fmt;
-        format.derive_key(password, &mut encryption_key)?;
+        Self::derive_key(&mut format, password, keyfile, &mut encryption_key)?;
 
-        let mut iv = IV::default();
-        randombytes::randombytes_into(&mut iv);
+        let mut wallet = Wallet {
+            pubkey_bin: keypair.pubkey_bin(),
+            iv: IV::default(),
+            tag: Tag::default(),
+            encrypted: vec![],
+            format,
+            metadata,
+        };
+        wallet.encrypt_with_key(keypair, &encryption_key)?;
+        Ok(wallet)
+    }
 
-        let pubkey_bin = keypair.pubkey_bin();
+    /// Encrypts the keypair with the given key and a fresh IV,
+    /// authenticating the current header, and authenticates the slot
+    /// table of a slotted wallet
+    fn encrypt_with_key(&mut self, keypair: &Keypair, key: &AESKey) -> Result {
+        randombytes::randombytes_into(&mut self.iv);
+        let associated_data = self.associated_data()?;
 
         use aead::generic_array::GenericArray;
-        let aead = Aes256Gcm::new(*GenericArray::from_slice(&encryption_key));
+        let aead = Aes256Gcm::new(*GenericArray::from_slice(&key[..]));
 
-        let mut encrypted = vec![];
+        // The plaintext is encrypted in place. Room for it is reserved up
+        // front so that no reallocation leaves a copy of it behind.
+        let mut encrypted = Vec::with_capacity(KEYPAIR_MAX_LENGTH);
         keypair.write(&mut encrypted)?;
 
-        match aead.encrypt_in_place_detached(iv.as_ref().into(), &pubkey_bin.0, &mut encrypted) {
-            Err(_) => Err("Failed to encrypt wallet".into()),
-            Ok(gtag) => Ok(Wallet {
-                pubkey_bin,
-                iv,
-                tag: gtag.into(),
-                encrypted,
-                format,
-            }),
+        match aead.encrypt_in_place_detached(
+            self.iv.as_ref().into(),
+            &associated_data,
+            &mut encrypted,
+        ) {
+            Err(_) => return Err("Failed to encrypt wallet".into()),
+            Ok(gtag) => {
+                self.tag = gtag.into();
+                self.encrypted = encrypted;
+            }
+        }
+        if let Format::Slotted(_) = self.format {
+            self.authenticate_slots(key)?;
         }
+        Ok(())
     }
 
     pub fn decrypt(&self, password: &[u8]) -> Result<Keypair> {
-        let mut encryption_key = AESKey::default();
+        self.decrypt_with_keyfile(password, None)
+    }
+
+    /// Decrypts the wallet with the password and, for wallets that need
+    /// one, the keyfile
+    pub fn decrypt_with_keyfile(&self, password: &[u8], keyfile: Option<&[u8]>) -> Result<Keypair> {
+        match (self.needs_keyfile(), keyfile) {
+            (true, None) => return Err("Wallet needs a keyfile".into()),
+            (false, Some(_)) => return Err("Wallet does not use a keyfile".into()),
+            _ => (),
+        }
+        let mut encryption_key = Secret::<AESKey>::zeroed();
         let mut format = self.format.clone();
-        format.derive_key(password, &mut encryption_key)?;
+        Self::derive_key(&mut format, password, keyfile, &mut encryption_key)?;
+        self.decrypt_with_key(&encryption_key)
+    }
 
+    fn decrypt_with_key(&self, key: &AESKey) -> Result<Keypair> {
+        self.verify_slots(key)?;
         use aead::generic_array::GenericArray;
-        let aead = Aes256Gcm::new(*GenericArray::from_slice(&encryption_key));
-        let mut buffer = self.encrypted.to_owned();
+        let aead = Aes256Gcm::new(*GenericArray::from_slice(&key[..]));
+        let mut buffer = Secret::new(self.encrypted.to_owned());
         match aead.decrypt_in_place_detached(
             self.iv.as_ref().into(),
-            &self.pubkey_bin.0,
-            &mut buffer,
+            &self.associated_data()?,
+            &mut buffer[..],
             self.tag.as_ref().into(),
         ) {
             Err(_) => Err("Failed to decrypt wallet"),
             _ => Ok(()),
         }?;
-        let keypair = Keypair::read(&mut Cursor::new(buffer))?;
+        let keypair = Keypair::read(&mut Cursor::new(&buffer[..]))?;
         Ok(keypair)
     }
 
+    /// Derives the AES key from the password with the wallet format.
+    /// A keyfile is mixed into the derived key with an HMAC keyed by
+    /// the keyfile contents, so the password alone no longer decrypts
+    /// the wallet.
+    fn derive_key(
+        format: &mut Format,
+        password: &[u8],
+        keyfile: Option<&[u8]>,
+        key: &mut AESKey,
+    ) -> Result {
+        format.derive_key(password, key)?;
+        if let Some(keyfile) = keyfile {
+            let mut hmac = match Hmac::<Sha256>::new_varkey(keyfile) {
+                Err(_) => return Err("Failed to initialize hmac".into()),
+                Ok(m) => m,
+            };
+            hmac.input(key);
+            key.copy_from_slice(&hmac.result().code());
+        }
+        Ok(())
+    }
+
+    /// Returns the data authenticated along with the encrypted key.
+    /// This is the address for older wallet formats, with the
+    /// generation tag for generation tagged shards, and the whole
+    /// header for wallets with metadata. The key share of a shard is
+    /// left out since all shards share one tag. A tampered key share
+    /// still fails decryption since it changes the combined key. The
+    /// slot table of a slotted wallet is left out as well, since it
+    /// changes without the key being encrypted again. It is
+    /// authenticated with its own MAC, see `slot_table_mac`.
+    fn associated_data(&self) -> Result<Vec<u8>> {
+        if self.metadata.is_none() {
+            let mut data = self.pubkey_bin.0.to_vec();
+            if let Format::Sharded(format::Sharded {
+                generation: Some(generation),
+                ..
+            }) = &self.format
+            {
+                data.extend_from_slice(generation);
+            }
+            return Ok(data);
+        }
+        let mut header = Wallet {
+            encrypted: vec![],
+            format: self.format.clone(),
+            metadata: self.metadata.clone(),
+            ..*self
+        };
+        match &mut header.format {
+            Format::Sharded(format) => format.key_shares = vec![KeyShare::default()],
+            Format::Slotted(format) => *format = format::Slotted::default(),
+            Format::Basic(_) => (),
+        }
+        let mut data = vec![];
+        header.write_header(&mut data)?;
+        Ok(data)
+    }
+
+    /// Returns the MAC over the header of a slotted wallet, including
+    /// its slot table but not the MAC itself. The MAC is keyed with a
+    /// key derived from the data key, so only the holder of a slot
+    /// secret can change the slots.
+    fn slot_table_mac(&self, data_key: &AESKey) -> Result<Hmac<Sha256>> {
+        let mut header = Wallet {
+            encrypted: vec![],
+            format: self.format.clone(),
+            metadata: self.metadata.clone(),
+            ..*self
+        };
+        header.mut_slotted_format()?.table_mac = format::SlotTableMac::default();
+        let mut data = vec![];
+        header.write_header(&mut data)?;
+
+        let mut mac_key = Secret::<[u8; 32]>::zeroed();
+        let mut hmac = match Hmac::<Sha256>::new_varkey(data_key) {
+            Err(_) => return Err("Failed to initialize hmac".into()),
+            Ok(m) => m,
+        };
+        hmac.input(SLOT_TABLE_CONTEXT);
+        mac_key.copy_from_slice(&hmac.result().code());
+        let mut hmac = match Hmac::<Sha256>::new_varkey(&mac_key[..]) {
+            Err(_) => return Err("Failed to initialize hmac".into()),
+            Ok(m) => m,
+        };
+        hmac.input(&data);
+        Ok(hmac)
+    }
+
+    /// Stores the MAC of the current slot table
+    fn authenticate_slots(&mut self, data_key: &AESKey) -> Result {
+        let code = self.slot_table_mac(data_key)?.result().code();
+        self.mut_slotted_format()?.table_mac.copy_from_slice(&code);
+        Ok(())
+    }
+
+    /// Checks the slot table of a slotted wallet against its MAC
+    fn verify_slots(&self, data_key: &AESKey) -> Result {
+        match &self.format {
+            Format::Slotted(format) => {
+                match self.slot_table_mac(data_key)?.verify(&format.table_mac) {
+                    Ok(_) => Ok(()),
+                    Err(_) => Err("Slot table failed to authenticate".into()),
+                }
+            }
+            _ => Ok(()),
+        }
+    }
+
     pub fn address(&self) -> Result<String> {
         self.pubkey_bin.to_b58()
     }
 
-    pub fn pwhash(&self) -> &PWHash {
+    pub fn network(&self) -> Result<Network> {
+        self.pubkey_bin.network()
+    }
+
+    pub fn pwhash(&self) -> Result<&PWHash> {
         self.format.pwhash()
     }
 
+    pub fn label(&self) -> Option<&str> {
+        self.metadata.as_ref()?.label.as_deref()
+    }
+
+    /// Returns the creation time of the wallet in seconds since the
+    /// epoch, if known
+    pub fn created(&self) -> Option<u64> {
+        self.metadata.as_ref()?.created
+    }
+
+    /// Returns whether decrypting the wallet needs a keyfile besides
+    /// the password
+    pub fn needs_keyfile(&self) -> bool {
+        matches!(&self.metadata, Some(metadata) if metadata.keyfile)
+    }
+
     fn mut_sharded_format(&mut self) -> Result<&mut format::Sharded> {
         match &mut self.format {
             Format::Sharded(format) => Ok(format),
@@ -108,6 +426,53 @@ impl Wallet {
         self.sharded_format().is_ok()
     }
 
+    fn mut_slotted_format(&mut self) -> Result<&mut format::Slotted> {
+        match &mut self.format {
+            Format::Slotted(format) => Ok(format),
+            _ => Err("Wallet has no unlock slots".into()),
+        }
+    }
+
+    pub fn slots(&self) -> Result<&[Slot]> {
+        match &self.format {
+            Format::Slotted(format) => Ok(&format.slots),
+            _ => Err("Wallet has no unlock slots".into()),
+        }
+    }
+
+    /// Adds an unlock slot with a new secret, given the secret of an
+    /// existing slot
+    pub fn add_slot(
+        &mut self,
+        secret: &[u8],
+        name: &str,
+        new_secret: &[u8],
+        pwhash: PWHash,
+    ) -> Result {
+        self.change_slots(secret, |slots, data_key| {
+            slots.add(data_key, name, new_secret, pwhash)
+        })
+    }
+
+    /// Removes an unlock slot, given the secret of any slot
+    pub fn remove_slot(&mut self, secret: &[u8], name: &str) -> Result {
+        self.change_slots(secret, |slots, _| slots.remove(name))
+    }
+
+    /// Changes the slot table with the data key unlocked by the given
+    /// secret. The current slot table is checked against its MAC, and
+    /// the changed table gets a new MAC. The encrypted key is left as
+    /// it is.
+    fn change_slots<F>(&mut self, secret: &[u8], change: F) -> Result
+    where
+        F: FnOnce(&mut format::Slotted, &AESKey) -> Result,
+    {
+        let data_key = self.mut_slotted_format()?.unlock(secret)?;
+        self.verify_slots(&data_key)?;
+        change(self.mut_slotted_format()?, &data_key)?;
+        self.authenticate_slots(&data_key)
+    }
+
     pub fn shards(&self) -> Result<Vec<Wallet>> {
         let format = self.sharded_format()?;
         let mut wallets = vec![];
@@ -115,42 +480,71 @@ impl Wallet {
             wallets.push(Self {
                 format: Format::Sharded(shard),
                 encrypted: self.encrypted.clone(),
+                metadata: self.metadata.clone(),
                 ..*self
             })
         }
         Ok(wallets)
     }
 
+    /// Returns the number of a single shard, which is the index of its
+    /// key share starting at 1
+    pub fn shard_number(&self) -> Result<u8> {
+        match &self.sharded_format()?.key_shares[..] {
+            [share] => Ok(share.0[0]),
+            _ => Err("Wallet is not a single shard".into()),
+        }
+    }
+
     pub fn absorb_shard(&mut self, shard: &Wallet) -> Result {
+        if self.pubkey_bin != shard.pubkey_bin {
+            return Err(format!("Shard is for a different wallet {}", shard.pubkey_bin).into());
+        }
+        if self.metadata != shard.metadata {
+            return Err("Shard metadata does not match the other shards".into());
+        }
         let format = self.mut_sharded_format()?;
         let other_format = shard.sharded_format()?;
 
         format.absorb(&other_format)
     }
 
-    fn read_pwhash(reader: &mut dyn io::Read) -> Result<PWHash> {
-        let kind = reader.read_u8()?;
-        match kind {
-            PWHASH_KIND_PBKDF2 => Ok(PWHash::pbkdf2_default()),
-            PWHASH_KIND_ARGON2ID13 => Ok(PWHash::argon2id13_default()),
-            _ => Err(format!("Invalid pwhash kind {}", kind).into()),
-        }
-    }
-
     pub fn read(reader: &mut dyn io::Read) -> Result<Wallet> {
         let kind = reader.read_u16::<LittleEndian>()?;
         let mut format = match kind {
             WALLET_KIND_BASIC_V1 => Format::basic(PWHash::pbkdf2_default()),
-            WALLET_KIND_BASIC_V2 => Format::basic(Self::read_pwhash(reader)?),
+            WALLET_KIND_BASIC_V2 | WALLET_KIND_BASIC_V3 => {
+                Format::basic(PWHash::read_kind(reader)?)
+            }
             WALLET_KIND_SHARDED_V1 => Format::sharded_default(PWHash::pbkdf2_default()),
-            WALLET_KIND_SHARDED_V2 => Format::sharded_default(Self::read_pwhash(reader)?),
+            WALLET_KIND_SHARDED_V2 => Format::sharded_default(PWHash::read_kind(reader)?),
+            WALLET_KIND_SHARDED_V3 | WALLET_KIND_SHARDED_V4 => {
+                Format::sharded_generation_default(PWHash::read_kind(reader)?)
+            }
+            WALLET_KIND_SLOTTED_V1 => Format::Slotted(format::Slotted::default()),
+            WALLET_KIND_SEALED_V1 => return Err("Wallet is sealed to a custodian".into()),
             _ => return Err(format!("Invalid wallet kind {}", kind).into()),
         };
         format.read(reader)?;
         let pubkey_bin = PubKeyBin::read(reader)?;
         let mut iv = IV::default();
         reader.read_exact(&mut iv)?;
-        format.mut_pwhash().read(reader)?;
+        // Slots carry their own password hash
+        if kind != WALLET_KIND_SLOTTED_V1 {
+            format.mut_pwhash()?.read(reader)?;
+        }
+        let metadata = match kind {
+            WALLET_KIND_BASIC_V3 | WALLET_KIND_SHARDED_V4 | WALLET_KIND_SLOTTED_V1 => {
+                let metadata = Metadata::read(reader)?;
+                if let Some(network) = metadata.network {
+                    if network != pubkey_bin.network()? {
+                        return Err("Wallet network does not match its address".into());
+                    }
+                }
+                Some(metadata)
+            }
+            _ => None,
+        };
         let mut tag = Tag::default();
         reader.read_exact(&mut tag)?;
         let mut encrypted = vec![];
@@ -162,34 +556,111 @@ impl Wallet {
             tag,
             format,
             encrypted,
+            metadata,
         })
     }
 
-    fn write_pwhash(pwhash: &PWHash, writer: &mut dyn io::Write) -> Result {
-        match pwhash {
-            PWHash::PBKDF2(_) => writer.write_u8(PWHASH_KIND_PBKDF2)?,
-            PWHash::Argon2id13(_) => writer.write_u8(PWHASH_KIND_ARGON2ID13)?,
-        }
-        Ok(())
-    }
-
-    pub fn write(&self, writer: &mut dyn io::Write) -> Result {
-        let kind = match self.format {
-            Format::Basic(_) => WALLET_KIND_BASIC_V2,
-            Format::Sharded(_) => WALLET_KIND_SHARDED_V2,
+    /// Writes everything up to the tag
+    fn write_header(&self, writer: &mut dyn io::Write) -> Result {
+        let kind = match (&self.format, &self.metadata) {
+            (Format::Basic(_), Some(_)) => WALLET_KIND_BASIC_V3,
+            (Format::Basic(_), None) => WALLET_KIND_BASIC_V2,
+            (Format::Sharded(format), Some(_)) if format.generation.is_some() => {
+                WALLET_KIND_SHARDED_V4
+            }
+            (Format::Sharded(_), Some(_)) => {
+                return Err("Sharded wallet with metadata needs a generation".into())
+            }
+            (Format::Sharded(format), None) if format.generation.is_some() => {
+                WALLET_KIND_SHARDED_V3
+            }
+            (Format::Sharded(_), None) => WALLET_KIND_SHARDED_V2,
+            (Format::Slotted(_), Some(_)) => WALLET_KIND_SLOTTED_V1,
+            (Format::Slotted(_), None) => return Err("Slotted wallet needs metadata".into()),
         };
         writer.write_u16::<LittleEndian>(kind)?;
-        Self::write_pwhash(self.format.pwhash(), writer)?;
+        let slotted = kind == WALLET_KIND_SLOTTED_V1;
+        if !slotted {
+            self.format.pwhash()?.write_kind(writer)?;
+        }
         self.format.write(writer)?;
         self.pubkey_bin.write(writer)?;
         writer.write_all(&self.iv)?;
-        self.format.pwhash().write(writer)?;
+        if !slotted {
+            self.format.pwhash()?.write(writer)?;
+        }
+        if let Some(metadata) = &self.metadata {
+            metadata.write(writer)?;
+        }
+        Ok(())
+    }
+
+    pub fn write(&self, writer: &mut dyn io::Write) -> Result {
+        self.write_header(writer)?;
         writer.write_all(&self.tag)?;
         writer.write_all(&self.encrypted)?;
         Ok(())
     }
 }
 
+/// A wallet, usually a shard, that is encrypted to the address of a
+/// custodian on top of its password. Only the custodian's key can
+/// unseal it back into a wallet. The address of the sealed wallet and
+/// of the custodian are stored in the clear.
+pub struct SealedWallet {
+    pub pubkey_bin: PubKeyBin,
+    pub custodian: PubKeyBin,
+    envelope: Vec<u8>,
+}
+
+impl SealedWallet {
+    pub fn seal(wallet: &Wallet, custodian: &PublicKey) -> Result<Self> {
+        let mut data = vec![];
+        wallet.write(&mut data)?;
+        Ok(SealedWallet {
+            pubkey_bin: wallet.pubkey_bin,
+            custodian: PubKeyBin::from(custodian),
+            envelope: encrypt::encrypt(&data, custodian, None)?,
+        })
+    }
+
+    pub fn unseal(&self, custodian: &Keypair) -> Result<Wallet> {
+        let decrypted = encrypt::decrypt(&self.envelope, custodian)?;
+        let wallet = Wallet::read(&mut Cursor::new(decrypted.data))?;
+        if wallet.pubkey_bin != self.pubkey_bin {
+            return Err("Sealed wallet does not match its address".into());
+        }
+        Ok(wallet)
+    }
+
+    /// Returns whether the given wallet file data is a sealed wallet
+    pub fn is_sealed(data: &[u8]) -> bool {
+        data.len() >= 2 && u16::from_le_bytes([data[0], data[1]]) == WALLET_KIND_SEALED_V1
+    }
+
+    pub fn read(reader: &mut dyn io::Read) -> Result<Self> {
+        let kind = reader.read_u16::<LittleEndian>()?;
+        if kind != WALLET_KIND_SEALED_V1 {
+            return Err(format!("Invalid sealed wallet kind {}", kind).into());
+        }
+        let pubkey_bin = PubKeyBin::read(reader)?;
+        let mut envelope = vec![];
+        reader.read_to_end(&mut envelope)?;
+        Ok(SealedWallet {
+            pubkey_bin,
+            custodian: encrypt::recipient(&envelope)?,
+            envelope,
+        })
+    }
+
+    pub fn write(&self, writer: &mut dyn io::Write) -> Result {
+        writer.write_u16::<LittleEndian>(WALLET_KIND_SEALED_V1)?;
+        self.pubkey_bin.write(writer)?;
+        writer.write_all(&self.envelope)?;
+        Ok(())
+    }
+}
+
 //
 // Test
 //
@@ -197,10 +668,11 @@ impl Wallet {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::keypair::KeyType;
 
     #[test]
     fn rountrip_basic() {
-        let from_keypair = Keypair::gen_keypair();
+        let from_keypair = Keypair::gen_keypair().expect("keypair");
         let format = format::Basic {
             pwhash: PWHash::argon2id13_default(),
         };
@@ -213,12 +685,13 @@ mod tests {
 
     #[test]
     fn rountrip_sharded() {
-        let from_keypair = Keypair::gen_keypair();
+        let from_keypair = Keypair::gen_keypair().expect("keypair");
         let format = format::Sharded {
             key_share_count: 5,
             recovery_threshold: 3,
             pwhash: PWHash::argon2id13_default(),
             key_shares: vec![],
+            generation: None,
         };
         let password = b"passsword";
         let wallet = Wallet::encrypt(&from_keypair, password, Format::Sharded(format))
@@ -226,4 +699,476 @@ mod tests {
         let to_keypair = wallet.decrypt(password).expect("wallet to keypair");
         assert_eq!(from_keypair, to_keypair);
     }
+
+    #[test]
+    fn roundtrip_ecc_compact() {
+        let keypair = Keypair::gen_keypair_with_type(KeyType::EccCompact).expect("keypair");
+        let password = b"passsword";
+        let format = Format::Basic(format::Basic {
+            pwhash: PWHash::pbkdf2(1_000),
+        });
+        let wallet = Wallet::encrypt(&keypair, password, format).expect("wallet creation");
+        let mut buffer = Vec::new();
+        wallet.write(&mut buffer).expect("wallet written");
+        let wallet = Wallet::read(&mut Cursor::new(buffer)).expect("wallet read");
+        assert_eq!(keypair.pubkey_bin(), wallet.pubkey_bin);
+        assert_eq!(keypair, wallet.decrypt(password).expect("wallet decrypted"));
+    }
+
+    #[test]
+    fn rekey_basic() {
+        let from_keypair = Keypair::gen_keypair().expect("keypair");
+        let wallet = Wallet::encrypt(
+            &from_keypair,
+            b"password",
+            Format::basic(PWHash::pbkdf2(1_000)),
+        )
+        .expect("wallet creation");
+        let keypair = wallet.decrypt(b"password").expect("wallet to keypair");
+        let new_wallet = Wallet::encrypt(
+            &keypair,
+            b"new password",
+            wallet.format.regenerate().expect("format"),
+        )
+        .expect("wallet rekey");
+        assert!(new_wallet.decrypt(b"password").is_err());
+        let to_keypair = new_wallet
+            .decrypt(b"new password")
+            .expect("rekeyed wallet to keypair");
+        assert_eq!(from_keypair, to_keypair);
+    }
+
+    #[test]
+    fn absorb_other_generation() {
+        let keypair = Keypair::gen_keypair().expect("keypair");
+        let password = b"password";
+        let format = Format::sharded(3, 2, PWHash::pbkdf2(1_000));
+        let wallet = Wallet::encrypt(&keypair, password, format.clone()).expect("wallet creation");
+        let other_wallet =
+            Wallet::encrypt(&keypair, password, format.regenerate().expect("format"))
+                .expect("wallet creation");
+
+        let mut shards = wallet.shards().expect("shards");
+        let other_shards = other_wallet.shards().expect("shards");
+        let shard = shards.pop().expect("shard");
+        assert!(shards[0].absorb_shard(&other_shards[1]).is_err());
+        assert!(shards[0].absorb_shard(&shard).is_ok());
+    }
+
+    #[test]
+    fn absorb_invalid_shards() {
+        let password = b"password";
+        let format = Format::sharded(3, 2, PWHash::pbkdf2(1_000));
+        let wallet = Wallet::encrypt(
+            &Keypair::gen_keypair().expect("keypair"),
+            password,
+            format.clone(),
+        )
+        .expect("wallet creation");
+        let other_wallet =
+            Wallet::encrypt(&Keypair::gen_keypair().expect("keypair"), password, format)
+                .expect("wallet creation");
+
+        let mut shards = wallet.shards().expect("shards");
+        let other_shards = other_wallet.shards().expect("shards");
+        let shard = shards.pop().expect("shard");
+        assert!(shards[0].absorb_shard(&other_shards[1]).is_err());
+        assert!(shards[0].absorb_shard(&shard).is_ok());
+        assert!(shards[0].absorb_shard(&shard).is_err());
+    }
+
+    #[test]
+    fn read_write_shards() {
+        let keypair = Keypair::gen_keypair().expect("keypair");
+        let password = b"password";
+        let format = Format::sharded(3, 2, PWHash::pbkdf2(1_000));
+        let wallet = Wallet::encrypt(&keypair, password, format).expect("wallet creation");
+
+        let mut shards = wallet.shards().expect("shards");
+        // Strip the generation and metadata from one shard to write it
+        // as a V2 shard, which does not combine with the tagged shards
+        if let Format::Sharded(format) = &mut shards[2].format {
+            format.generation = None;
+        }
+        shards[2].metadata = None;
+        let mut read_shards = vec![];
+        for shard in &shards {
+            let mut buffer = vec![];
+            shard.write(&mut buffer).expect("shard write");
+            read_shards.push(Wallet::read(&mut Cursor::new(buffer)).expect("shard read"));
+        }
+        let v2_shard = read_shards.pop().expect("shard");
+        if let Format::Sharded(format) = &v2_shard.format {
+            assert!(format.generation.is_none());
+        }
+
+        let mut combined = read_shards.remove(0);
+        assert!(combined.absorb_shard(&v2_shard).is_err());
+        combined
+            .absorb_shard(&read_shards[0])
+            .expect("shard absorb");
+        let to_keypair = combined.decrypt(password).expect("wallet to keypair");
+        assert_eq!(keypair, to_keypair);
+    }
+
+    #[test]
+    fn sealed_shards() {
+        let keypair = Keypair::gen_keypair().expect("keypair");
+        let custodian = Keypair::gen_keypair().expect("keypair");
+        let password = b"password";
+        let format = Format::sharded(3, 2, PWHash::pbkdf2(1_000));
+        let wallet = Wallet::encrypt(&keypair, password, format).expect("wallet creation");
+        let shards = wallet.shards().expect("shards");
+
+        let sealed = SealedWallet::seal(&shards[0], &custodian.public).expect("sealed shard");
+        let mut buffer = vec![];
+        sealed.write(&mut buffer).expect("sealed shard write");
+        assert!(SealedWallet::is_sealed(&buffer));
+        assert!(Wallet::read(&mut Cursor::new(&buffer)).is_err());
+
+        let sealed = SealedWallet::read(&mut Cursor::new(&buffer)).expect("sealed shard read");
+        assert_eq!(keypair.pubkey_bin(), sealed.pubkey_bin);
+        assert_eq!(custodian.pubkey_bin(), sealed.custodian);
+        assert!(sealed
+            .unseal(&Keypair::gen_keypair().expect("keypair"))
+            .is_err());
+        let mut combined = sealed.unseal(&custodian).expect("unsealed shard");
+        assert_eq!(1, combined.shard_number().expect("shard number"));
+        assert_eq!(2, shards[1].shard_number().expect("shard number"));
+        combined.absorb_shard(&shards[1]).expect("shard absorb");
+        assert!(combined.shard_number().is_err());
+        assert_eq!(
+            keypair,
+            combined.decrypt(password).expect("wallet to keypair")
+        );
+    }
+
+    #[test]
+    fn authenticated_header() {
+        let keypair = Keypair::gen_keypair().expect("keypair");
+        let password = b"password";
+        let metadata = Metadata::new(keypair.network(), Some("cold storage".to_string()));
+        let wallet = Wallet::encrypt_with_metadata(
+            &keypair,
+            password,
+            None,
+            Format::basic(PWHash::pbkdf2(1_000)),
+            metadata.clone(),
+        )
+        .expect("wallet creation");
+
+        let mut buffer = vec![];
+        wallet.write(&mut buffer).expect("wallet write");
+        assert_eq!(WALLET_KIND_BASIC_V3.to_le_bytes(), buffer[..2]);
+        let mut wallet = Wallet::read(&mut Cursor::new(&buffer)).expect("wallet read");
+        assert_eq!(Some(metadata), wallet.metadata);
+        assert_eq!(
+            keypair,
+            wallet.decrypt(password).expect("wallet to keypair")
+        );
+
+        wallet.metadata.as_mut().expect("metadata").label = Some("hot storage".to_string());
+        assert!(wallet.decrypt(password).is_err());
+
+        // The network in the metadata has to match the address
+        let metadata = Metadata::new(Network::Testnet, None);
+        let wallet = Wallet::encrypt_with_metadata(
+            &keypair,
+            password,
+            None,
+            Format::basic(PWHash::pbkdf2(1_000)),
+            metadata,
+        )
+        .expect("wallet creation");
+        let mut buffer = vec![];
+        wallet.write(&mut buffer).expect("wallet write");
+        assert!(Wallet::read(&mut Cursor::new(&buffer)).is_err());
+    }
+
+    #[test]
+    fn authenticated_header_sharded() {
+        let keypair = Keypair::gen_keypair().expect("keypair");
+        let password = b"password";
+        let format = Format::sharded(3, 2, PWHash::pbkdf2(1_000));
+        let wallet = Wallet::encrypt(&keypair, password, format).expect("wallet creation");
+
+        let mut shards = vec![];
+        for shard in wallet.shards().expect("shards") {
+            let mut buffer = vec![];
+            shard.write(&mut buffer).expect("shard write");
+            assert_eq!(WALLET_KIND_SHARDED_V4.to_le_bytes(), buffer[..2]);
+            shards.push(Wallet::read(&mut Cursor::new(&buffer)).expect("shard read"));
+        }
+        let mut combined = shards.remove(0);
+        combined.absorb_shard(&shards[0]).expect("shard absorb");
+        assert_eq!(
+            keypair,
+            combined.decrypt(password).expect("wallet to keypair")
+        );
+
+        combined
+            .mut_sharded_format()
+            .expect("sharded format")
+            .recovery_threshold = 1;
+        assert!(combined.decrypt(password).is_err());
+
+        let mut tampered = shards.pop().expect("shard");
+        tampered.metadata = Some(Metadata::default());
+        let err = shards[0]
+            .absorb_shard(&tampered)
+            .expect_err("tampered metadata");
+        assert_eq!(
+            "Shard metadata does not match the other shards",
+            err.to_string()
+        );
+    }
+
+    // A basic V2 wallet for 13JTvHGhf3YKEs54teCYV57TcjKTG3PX42naaeiExDRahhMPXJD
+    // with password "password" and 1,000 PBKDF2 iterations
+    const BASIC_V2_WALLET: &str =
+        "020000012f0bede2dd7d57a9916a3fbaae0841f1dc1446f0d3e273d9d25b3524\
+        879e9c588be9a8bf6b1858ed1dc7fb2224f38c15ef1200b0e80300007c91947422b1d3cb2382622b5adfe6cc\
+        878a8620d52b626d234622cbf14c22c58a4ccacad7cab365a6969ad3f2602b43bb4232a077e88a72d77f468c\
+        da5a16b21c4a0cb73638aaee0e11686268a8f2741a3272e60f29aeda232b2d9e2dc6a84b59b3870a83b2c971\
+        c78b831856f74123f6";
+
+    #[test]
+    fn keyfile() {
+        let keypair = Keypair::gen_keypair().expect("keypair");
+        let password = b"password";
+        let keyfile = b"keyfile contents";
+        let wallet = Wallet::encrypt_with_metadata(
+            &keypair,
+            password,
+            Some(keyfile),
+            Format::basic(PWHash::pbkdf2(1_000)),
+            Metadata::new(keypair.network(), None),
+        )
+        .expect("wallet creation");
+
+        let mut buffer = vec![];
+        wallet.write(&mut buffer).expect("wallet write");
+        let mut wallet = Wallet::read(&mut Cursor::new(&buffer)).expect("wallet read");
+        assert!(wallet.needs_keyfile());
+        assert_eq!(
+            keypair,
+            wallet
+                .decrypt_with_keyfile(password, Some(keyfile))
+                .expect("wallet to keypair")
+        );
+        assert!(wallet.decrypt(password).is_err());
+        assert!(wallet
+            .decrypt_with_keyfile(password, Some(b"other keyfile"))
+            .is_err());
+        assert!(wallet
+            .decrypt_with_keyfile(b"other password", Some(keyfile))
+            .is_err());
+
+        // Dropping the keyfile flag from the header does not help
+        wallet.metadata.as_mut().expect("metadata").keyfile = false;
+        assert!(wallet.decrypt(password).is_err());
+
+        // Sharded wallets mix the keyfile into the combined key
+        let format = Format::sharded(3, 2, PWHash::pbkdf2(1_000));
+        let metadata = Metadata::new(keypair.network(), None);
+        let wallet =
+            Wallet::encrypt_with_metadata(&keypair, password, Some(keyfile), format, metadata)
+                .expect("wallet creation");
+        let mut shards = wallet.shards().expect("shards");
+        let mut combined = shards.remove(0);
+        combined.absorb_shard(&shards[1]).expect("shard absorb");
+        assert!(combined.needs_keyfile());
+        assert_eq!(
+            keypair,
+            combined
+                .decrypt_with_keyfile(password, Some(keyfile))
+                .expect("wallet to keypair")
+        );
+        assert!(combined.decrypt(password).is_err());
+
+        // Slotted wallets use keyfile slots instead
+        let format = Format::slotted("password", PWHash::pbkdf2(1_000));
+        let metadata = Metadata::new(keypair.network(), None);
+        assert!(
+            Wallet::encrypt_with_metadata(&keypair, password, Some(keyfile), format, metadata)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn read_legacy_basic() {
+        let address = "13JTvHGhf3YKEs54teCYV57TcjKTG3PX42naaeiExDRahhMPXJD";
+        let v2 = hex::decode(BASIC_V2_WALLET).expect("wallet hex");
+        let wallet = Wallet::read(&mut Cursor::new(&v2)).expect("wallet read");
+        assert!(wallet.metadata.is_none());
+        assert_eq!(address, wallet.address().expect("address"));
+        let keypair = wallet.decrypt(b"password").expect("wallet to keypair");
+        assert_eq!(address, keypair.public.to_b58().expect("address"));
+
+        // Legacy wallets are written back in their own format
+        let mut buffer = vec![];
+        wallet.write(&mut buffer).expect("wallet write");
+        assert_eq!(v2, buffer);
+
+        // V1 is V2 without the pwhash kind
+        let mut v1 = WALLET_KIND_BASIC_V1.to_le_bytes().to_vec();
+        v1.extend_from_slice(&v2[3..]);
+        let wallet = Wallet::read(&mut Cursor::new(&v1)).expect("wallet read");
+        assert_eq!(
+            keypair,
+            wallet.decrypt(b"password").expect("wallet to keypair")
+        );
+    }
+
+    #[test]
+    fn read_legacy_sharded() {
+        let keypair = Keypair::gen_keypair().expect("keypair");
+        let password = b"password";
+        let format = Format::sharded(3, 2, PWHash::pbkdf2(1_000));
+        let wallet =
+            Wallet::encrypt_with(&keypair, password, None, format, None).expect("wallet creation");
+
+        let mut shards = vec![];
+        for shard in wallet.shards().expect("shards") {
+            let mut buffer = vec![];
+            shard.write(&mut buffer).expect("shard write");
+            assert_eq!(WALLET_KIND_SHARDED_V3.to_le_bytes(), buffer[..2]);
+            shards.push(Wallet::read(&mut Cursor::new(&buffer)).expect("shard read"));
+        }
+        let mut combined = shards.remove(0);
+        assert!(combined.metadata.is_none());
+        combined.absorb_shard(&shards[1]).expect("shard absorb");
+        assert_eq!(
+            keypair,
+            combined.decrypt(password).expect("wallet to keypair")
+        );
+
+        // The generation tag is authenticated
+        combined
+            .mut_sharded_format()
+            .expect("sharded format")
+            .generation = Some([0xaa; 16]);
+        assert!(combined.decrypt(password).is_err());
+    }
+
+    #[test]
+    fn unlock_slots() {
+        let keypair = Keypair::gen_keypair().expect("keypair");
+        let format = Format::slotted("operator", PWHash::pbkdf2(1_000));
+        let mut wallet = Wallet::encrypt(&keypair, b"operator", format).expect("wallet creation");
+        let (tag, encrypted) = (wallet.tag, wallet.encrypted.clone());
+
+        assert!(wallet
+            .add_slot(b"wrong", "recovery", b"recovery", PWHash::pbkdf2(1_000))
+            .is_err());
+        wallet
+            .add_slot(b"operator", "recovery", b"recovery", PWHash::pbkdf2(1_000))
+            .expect("slot add");
+        wallet
+            .add_slot(b"recovery", "keyfile", &[0xaa; 64], PWHash::pbkdf2(1_000))
+            .expect("slot add");
+        assert!(wallet
+            .add_slot(b"operator", "keyfile", b"other", PWHash::pbkdf2(1_000))
+            .is_err());
+        // Changing the slots leaves the encrypted key as it is
+        assert_eq!(tag, wallet.tag);
+        assert_eq!(encrypted, wallet.encrypted);
+
+        let mut buffer = vec![];
+        wallet.write(&mut buffer).expect("wallet write");
+        assert_eq!(WALLET_KIND_SLOTTED_V1.to_le_bytes(), buffer[..2]);
+        let mut wallet = Wallet::read(&mut Cursor::new(&buffer)).expect("wallet read");
+        let names: Vec<&str> = wallet
+            .slots()
+            .expect("slots")
+            .iter()
+            .map(|slot| slot.name.as_str())
+            .collect();
+        assert_eq!(vec!["operator", "recovery", "keyfile"], names);
+        for secret in &[&b"operator"[..], &b"recovery"[..], &[0xaa; 64][..]] {
+            assert_eq!(keypair, wallet.decrypt(secret).expect("wallet to keypair"));
+        }
+        assert!(wallet.decrypt(b"wrong").is_err());
+
+        assert!(wallet.remove_slot(b"wrong", "operator").is_err());
+        wallet
+            .remove_slot(b"recovery", "operator")
+            .expect("slot remove");
+        assert!(wallet.decrypt(b"operator").is_err());
+        wallet
+            .remove_slot(b"recovery", "keyfile")
+            .expect("slot remove");
+        assert!(wallet.remove_slot(b"recovery", "recovery").is_err());
+        assert_eq!(
+            keypair,
+            wallet.decrypt(b"recovery").expect("wallet to keypair")
+        );
+
+        // A slot from another wallet does not unlock this one
+        let other = Wallet::encrypt(
+            &Keypair::gen_keypair().expect("keypair"),
+            b"other",
+            Format::slotted("other", PWHash::pbkdf2(1_000)),
+        )
+        .expect("wallet creation");
+        wallet
+            .mut_slotted_format()
+            .expect("slotted format")
+            .slots
+            .push(other.slots().expect("slots")[0].clone());
+        assert!(wallet.decrypt(b"other").is_err());
+        // and a changed slot table no longer authenticates at all
+        assert!(wallet.decrypt(b"recovery").is_err());
+        assert!(wallet
+            .add_slot(b"recovery", "new", b"new", PWHash::pbkdf2(1_000))
+            .is_err());
+    }
+
+    #[test]
+    fn slot_table_authenticated() {
+        let keypair = Keypair::gen_keypair().expect("keypair");
+        let format = Format::slotted("operator", PWHash::pbkdf2(1_000));
+        let mut wallet = Wallet::encrypt(&keypair, b"operator", format).expect("wallet creation");
+        wallet
+            .add_slot(b"operator", "recovery", b"recovery", PWHash::pbkdf2(1_000))
+            .expect("slot add");
+
+        let mut buffer = vec![];
+        wallet.write(&mut buffer).expect("wallet write");
+
+        // Removing a slot without the data key is detected
+        let mut removed = Wallet::read(&mut Cursor::new(&buffer)).expect("wallet read");
+        removed
+            .mut_slotted_format()
+            .expect("slotted format")
+            .slots
+            .remove(0);
+        assert!(removed.decrypt(b"recovery").is_err());
+
+        // As is an emptied slot table, which has no password hash
+        let mut emptied = Wallet::read(&mut Cursor::new(&buffer)).expect("wallet read");
+        emptied
+            .mut_slotted_format()
+            .expect("slotted format")
+            .slots
+            .clear();
+        assert!(emptied.pwhash().is_err());
+        assert!(emptied.decrypt(b"recovery").is_err());
+
+        // And so is a changed MAC
+        let mut forged = Wallet::read(&mut Cursor::new(&buffer)).expect("wallet read");
+        forged
+            .mut_slotted_format()
+            .expect("slotted format")
+            .table_mac[0] ^= 1;
+        assert!(forged.decrypt(b"recovery").is_err());
+        assert!(forged.remove_slot(b"recovery", "operator").is_err());
+
+        // Only a wallet with a single slot is regenerated
+        assert!(wallet.format.regenerate().is_err());
+        wallet
+            .remove_slot(b"operator", "operator")
+            .expect("slot remove");
+        assert!(wallet.format.regenerate().is_ok());
+    }
 }